@@ -0,0 +1,47 @@
+use rex::{
+    render::Renderer,
+    layout::LayoutSettings,
+    font::{FontContext, backend::ttf_parser::TtfMathFont}, tiny_skia::TinySkiaBackend
+};
+
+const FONT_FILE_PATH : &str = "resources/XITS_Math.otf";
+const DEFAULT_OUTPUT_FILE : &str = "test.png";
+const FORMULA: &str = r"\int_0^1 x^2 \,\mathrm{d}x";
+const FONT_SIZE : f64 = 16.;
+
+fn main() {
+    env_logger::init();
+    // -- Output file can be given as first argument
+    let output_file_path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_OUTPUT_FILE.to_string());
+
+    // -- Load font
+    let font_file = std::fs::read(FONT_FILE_PATH).unwrap();
+    let font = load_font(&font_file);
+
+
+    // -- Create ReX context
+    let ctx = FontContext::new(&font);
+    let layout_settings = LayoutSettings::new(&ctx).font_size(FONT_SIZE);
+
+
+    // -- parse & layout
+    let parse_nodes = rex::parser::parse(FORMULA).unwrap();
+    let layout = rex::layout::engine::layout(&parse_nodes, layout_settings).unwrap();
+
+
+    // -- Render to a pixmap, with the top of the formula at the top of the image
+    let size = layout.size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width.ceil() as u32, (size.height - size.depth).ceil() as u32).unwrap();
+    pixmap.fill(tiny_skia::Color::WHITE);
+    let mut backend = TinySkiaBackend::with_transform(&mut pixmap, tiny_skia::Transform::from_translate(0., size.height as f32));
+    let renderer = Renderer::new();
+    renderer.render(&layout, &mut backend);
+
+    // -- Write PNG file
+    pixmap.save_png(output_file_path).unwrap();
+}
+
+fn load_font<'a>(file : &'a [u8]) -> TtfMathFont<'a> {
+    let font = ttf_parser::Face::parse(file, 0).unwrap();
+    TtfMathFont::new(font).unwrap()
+}
@@ -292,7 +292,9 @@ pub enum AnyUnit {
     /// em
     Em(f64),
     /// pixels
-    Px(f64)
+    Px(f64),
+    /// ex, i.e. the x-height of the font ; it is resolved at layout time from the font's `accent_base_height`
+    Ex(f64),
 }
 
 impl AnyUnit {
@@ -302,6 +304,7 @@ impl AnyUnit {
         match self {
             AnyUnit::Em(val) => val.is_sign_negative(),
             AnyUnit::Px(val) => val.is_sign_negative(),
+            AnyUnit::Ex(val) => val.is_sign_negative(),
         }
     }
 }
@@ -311,6 +314,7 @@ impl Display for AnyUnit {
         match self {
             AnyUnit::Em(value) => write!(f, "{}em", value),
             AnyUnit::Px(value) => write!(f, "{}px", value),
+            AnyUnit::Ex(value) => write!(f, "{}ex", value),
         }
     }
 }
@@ -5,7 +5,7 @@
 
 use crate::dimensions::AnyUnit;
 use crate::font::common::GlyphId;
-use crate::parser::error::ParseError;
+use crate::parser::error::{ParseError, LocatedParseError};
 use std::fmt;
 use crate::font::TexSymbolType;
 use crate::parser::symbols::Symbol;
@@ -16,14 +16,20 @@ pub type LayoutResult<T> = ::std::result::Result<T, LayoutError>;
 pub type ParseResult<T> = ::std::result::Result<T, ParseError>;
 
 /// Errors during the layout phase ; currently, these can only be font errors.
+/// 
+/// The existing variants are stable and may be matched on ; new variants may be added in the future, hence the `#[non_exhaustive]` attribute.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum LayoutError {
     /// a font error
     Font(FontError)
 }
 
 /// Errors having to do with font file provided
+/// 
+/// The existing variants are stable and may be matched on ; new variants may be added in the future, hence the `#[non_exhaustive]` attribute.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum FontError {
     /// The font does not contain a glyph for the given char.
     MissingGlyphCodepoint(char),
@@ -33,6 +39,33 @@ pub enum FontError {
     NoMATHTable,
 }
 
+impl FontError {
+    /// If the error is due to a missing glyph for a char, returns this char.
+    pub fn missing_codepoint(&self) -> Option<char> {
+        match *self {
+            FontError::MissingGlyphCodepoint(codepoint) => Some(codepoint),
+            _ => None,
+        }
+    }
+
+    /// If the error is due to a missing glyph id, returns this glyph id.
+    pub fn missing_gid(&self) -> Option<GlyphId> {
+        match *self {
+            FontError::MissingGlyphGID(gid) => Some(gid),
+            _ => None,
+        }
+    }
+}
+
+impl LayoutError {
+    /// If the error comes from the font, returns the corresponding [`FontError`].
+    pub fn font_error(&self) -> Option<&FontError> {
+        match self {
+            LayoutError::Font(error) => Some(error),
+        }
+    }
+}
+
 impl From<FontError> for LayoutError {
     fn from(e: FontError) -> Self {
         LayoutError::Font(e)
@@ -44,13 +77,13 @@ impl From<FontError> for LayoutError {
 /// A generic error type covering any error that may happen during the process.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
-    /// a parse error
-    Parse(ParseError),
+    /// a parse error, along with its position in the formula
+    Parse(LocatedParseError),
     /// a layout error (including font errors)
     Layout(LayoutError)
 }
-impl From<ParseError> for Error {
-    fn from(e: ParseError) -> Self {
+impl From<LocatedParseError> for Error {
+    fn from(e: LocatedParseError) -> Self {
         Error::Parse(e)
     }
 }
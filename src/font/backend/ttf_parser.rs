@@ -3,7 +3,7 @@ use std::convert::TryInto;
 
 use ttf_parser::{math::GlyphPart, LazyArray16};
 
-use crate::{font::{Constants, VariantGlyph, common::{GlyphInstruction, GlyphId}, Direction, Glyph}, error::FontError, dimensions::units::Ratio};
+use crate::{font::{Constants, VariantGlyph, OutlineBuilder, common::{GlyphInstruction, GlyphId}, Direction, Glyph}, error::FontError, dimensions::units::Ratio};
 use crate::dimensions::Unit;
 use crate::dimensions::units::{Em, FUnit};
 
@@ -52,6 +52,55 @@ impl<'a> TtfMathFont<'a> {
     pub fn font_matrix(&self) -> ttf_parser::cff::Matrix {
         self.font_matrix
     }
+
+    /// Returns the commands drawing the outline of the glyph with id `gid`, in font units.
+    /// The result is empty if the glyph has no outline (e.g. a space).
+    pub fn outline_commands(&self, gid: GlyphId) -> Vec<OutlineCommand> {
+        struct Collector(Vec<OutlineCommand>);
+
+        impl OutlineBuilder for Collector {
+            fn move_to(&mut self, x: f32, y: f32) {
+                self.0.push(OutlineCommand::MoveTo(x, y));
+            }
+
+            fn line_to(&mut self, x: f32, y: f32) {
+                self.0.push(OutlineCommand::LineTo(x, y));
+            }
+
+            fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+                self.0.push(OutlineCommand::QuadTo(x1, y1, x, y));
+            }
+
+            fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+                self.0.push(OutlineCommand::CurveTo(x1, y1, x2, y2, x, y));
+            }
+
+            fn close(&mut self) {
+                self.0.push(OutlineCommand::Close);
+            }
+        }
+
+        let mut collector = Collector(Vec::new());
+        crate::font::MathFont::outline(self, gid, &mut collector);
+        collector.0
+    }
+}
+
+/// A command drawing part of the outline of a glyph, cf [`TtfMathFont::outline_commands`].
+/// 
+/// Coordinates are in font units, with positive Y values above the baseline ; they must be multiplied by the [font's matrix](TtfMathFont::font_matrix) to get lengths in ems.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlineCommand {
+    /// Starts a new contour at the given point
+    MoveTo(f32, f32),
+    /// Straight line to the given point
+    LineTo(f32, f32),
+    /// Quadratic Bézier curve with the given control point, to the given point
+    QuadTo(f32, f32, f32, f32),
+    /// Cubic Bézier curve with the two given control points, to the given point
+    CurveTo(f32, f32, f32, f32, f32, f32),
+    /// Closes the current contour
+    Close,
 }
 
 
@@ -116,6 +165,17 @@ impl<'a> TtfMathFont<'a> {
             radical_vertical_gap:               em(math_constants.radical_vertical_gap().value.into()),
             radical_rule_thickness:             em(math_constants.radical_rule_thickness().value.into()),
             radical_extra_ascender:             em(math_constants.radical_extra_ascender().value.into()),
+            radical_kern_before_degree:         em(math_constants.radical_kern_before_degree().value.into()),
+            radical_kern_after_degree:          em(math_constants.radical_kern_after_degree().value.into()),
+            radical_degree_bottom_raise_percent: 0.01 * f64::from(math_constants.radical_degree_bottom_raise_percent()),
+
+            overbar_vertical_gap:     em(math_constants.overbar_vertical_gap().value.into()),
+            overbar_rule_thickness:   em(math_constants.overbar_rule_thickness().value.into()),
+            overbar_extra_ascender:   em(math_constants.overbar_extra_ascender().value.into()),
+
+            underbar_vertical_gap:    em(math_constants.underbar_vertical_gap().value.into()),
+            underbar_rule_thickness:  em(math_constants.underbar_rule_thickness().value.into()),
+            underbar_extra_descender: em(math_constants.underbar_extra_descender().value.into()),
 
             stack_display_style_gap_min:      em(math_constants.stack_display_style_gap_min().value.into()),
             stack_top_display_style_shift_up: em(math_constants.stack_top_display_style_shift_up().value.into()),
@@ -235,6 +295,20 @@ impl<'a> crate::font::MathFont for TtfMathFont<'a> {
         VariantGlyph::Constructable(Direction::Vertical, instructions)
     }
 
+    fn vert_variant_glyphs(&self, gid: GlyphId) -> Vec<GlyphId> {
+        self.math.variants
+            .and_then(|variants| variants.vertical_constructions.get(gid.into()))
+            .map(|construction| construction.variants.into_iter().map(|record| GlyphId::from(record.variant_glyph)).collect())
+            .unwrap_or_default()
+    }
+
+    fn horz_variant_glyphs(&self, gid: GlyphId) -> Vec<GlyphId> {
+        self.math.variants
+            .and_then(|variants| variants.horizontal_constructions.get(gid.into()))
+            .map(|construction| construction.variants.into_iter().map(|record| GlyphId::from(record.variant_glyph)).collect())
+            .unwrap_or_default()
+    }
+
     fn glyph_index(&self, codepoint: char) -> Option<crate::font::common::GlyphId> {
         let glyph_index_ttf_parser = self.font.glyph_index(codepoint)?;
         Some(crate::font::common::GlyphId::from(glyph_index_ttf_parser))
@@ -303,6 +377,34 @@ impl<'a> crate::font::MathFont for TtfMathFont<'a> {
         Unit::<Ratio<Em, FUnit>>::new(self.font_matrix.sx as f64)
     }
 
+    fn outline(&self, gid: GlyphId, builder: &mut dyn OutlineBuilder) {
+        struct Adapter<'b>(&'b mut dyn OutlineBuilder);
+
+        impl<'b> ttf_parser::OutlineBuilder for Adapter<'b> {
+            fn move_to(&mut self, x: f32, y: f32) {
+                self.0.move_to(x, y);
+            }
+
+            fn line_to(&mut self, x: f32, y: f32) {
+                self.0.line_to(x, y);
+            }
+
+            fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+                self.0.quad_to(x1, y1, x, y);
+            }
+
+            fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+                self.0.curve_to(x1, y1, x2, y2, x, y);
+            }
+
+            fn close(&mut self) {
+                self.0.close();
+            }
+        }
+
+        self.font.outline_glyph(gid.into(), &mut Adapter(builder));
+    }
+
 
 }
 
@@ -545,4 +647,28 @@ mod tests {
         }
         total_size
     }
+
+    #[test]
+    fn outline_commands_follow_glyph_contours() {
+        let font = TtfMathFont::new(ttf_parser::Face::parse(FIRA_MATH_FONT_FILE, 0).unwrap()).unwrap();
+
+        let gid_x = font.font().glyph_index('x').unwrap();
+        let commands = font.outline_commands(gid_x.into());
+        assert!(matches!(commands.first(), Some(OutlineCommand::MoveTo(_, _))));
+        assert_eq!(commands.last(), Some(&OutlineCommand::Close));
+
+        // all points of the outline are within the glyph's bounding box
+        let bbox = font.font().glyph_bounding_box(gid_x).unwrap();
+        for command in commands {
+            let (x, y) = match command {
+                OutlineCommand::MoveTo(x, y) | OutlineCommand::LineTo(x, y) | OutlineCommand::QuadTo(_, _, x, y) | OutlineCommand::CurveTo(_, _, _, _, x, y) => (x, y),
+                OutlineCommand::Close => continue,
+            };
+            assert!(f32::from(bbox.x_min) <= x && x <= f32::from(bbox.x_max));
+            assert!(f32::from(bbox.y_min) <= y && y <= f32::from(bbox.y_max));
+        }
+
+        let gid_space = font.font().glyph_index(' ').unwrap();
+        assert!(font.outline_commands(gid_space.into()).is_empty());
+    }
 }
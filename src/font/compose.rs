@@ -0,0 +1,33 @@
+//! Composition of Latin letters with combining diacritical marks, e.g. 'e' followed by U+0301 Combining Acute Accent into 'é'.
+
+// For each combining mark, a string of pairs formed by a letter and the precomposed letter with that mark.
+// This covers the letters of the Latin-1 Supplement and Latin Extended-A blocks.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const COMPOSITIONS: [(char, &str); 13] = [
+    ('\u{0300}', "AÀEÈIÌOÒUÙaàeèiìoòuù"), // combining grave accent
+    ('\u{0301}', "AÁEÉIÍOÓUÚYÝaáeéiíoóuúyýCĆcćLĹlĺNŃnńRŔrŕSŚsśZŹzź"), // combining acute accent
+    ('\u{0302}', "AÂEÊIÎOÔUÛaâeêiîoôuûCĈcĉGĜgĝHĤhĥJĴjĵSŜsŝWŴwŵYŶyŷ"), // combining circumflex accent
+    ('\u{0303}', "AÃNÑOÕaãnñoõIĨiĩUŨuũ"), // combining tilde
+    ('\u{0304}', "AĀaāEĒeēIĪiīOŌoōUŪuū"), // combining macron
+    ('\u{0306}', "AĂaăEĔeĕGĞgğIĬiĭOŎoŏUŬuŭ"), // combining breve
+    ('\u{0307}', "CĊcċEĖeėGĠgġIİZŻzż"), // combining dot above
+    ('\u{0308}', "AÄEËIÏOÖUÜaäeëiïoöuüyÿYŸ"), // combining diaeresis
+    ('\u{030A}', "AÅaåUŮuů"), // combining ring above
+    ('\u{030B}', "OŐoőUŰuű"), // combining double acute accent
+    ('\u{030C}', "CČcčDĎdďEĚeěLĽlľNŇnňRŘrřSŠsšTŤtťZŽzž"), // combining caron
+    ('\u{0327}', "CÇcçGĢgģKĶkķLĻlļNŅnņRŖrŗSŞsşTŢtţ"), // combining cedilla
+    ('\u{0328}', "AĄaąEĘeęIĮiįUŲuų"), // combining ogonek
+];
+
+/// Returns the precomposed letter for `base` followed by the combining diacritical mark `mark` (e.g. 'é' for 'e' and U+0301),
+/// or `None` if there is no such letter in the Latin-1 Supplement and Latin Extended-A blocks.
+pub fn compose_accent(base: char, mark: char) -> Option<char> {
+    let (_, pairs) = COMPOSITIONS.iter().find(|(combining, _)| *combining == mark)?;
+    let mut pairs = pairs.chars();
+    while let (Some(letter), Some(composed)) = (pairs.next(), pairs.next()) {
+        if letter == base {
+            return Some(composed);
+        }
+    }
+    None
+}
@@ -11,18 +11,24 @@ pub mod backend;
 #[deny(missing_docs)]
 pub mod common;
 mod style;
+mod compose;
 //mod unit;
 
 pub use unicode_math::TexSymbolType;
-pub use style::style_symbol;
+pub use style::{style_symbol, unstyle_symbol};
+pub use compose::compose_accent;
 
 
 pub use crate::font::common::{Direction, VariantGlyph};
 
 use crate::{font::common::GlyphId};
 use crate::dimensions::Unit;
-use crate::dimensions::units::{Em, FUnit, Ratio};
+use crate::dimensions::units::{Em, FUnit, Px, Ratio};
 use crate::error::FontError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use self::kerning::Corner;
 
@@ -43,20 +49,65 @@ pub trait MathFont : Sized {
     // the } before "wat?" is too short for the last 2 fonts but not the first
     // maybe this is a problem, maybe this is meant to be
     fn vert_variant(&self, gid: GlyphId, height: Unit<FUnit>) -> VariantGlyph;
+    /// Returns the bigger versions of the glyph that the font lists, from smallest to largest (the glyph itself may be among them).
+    /// Fonts which do not provide this list return an empty vector, which is the default.
+    fn vert_variant_glyphs(&self, _gid: GlyphId) -> Vec<GlyphId> {
+        Vec::new()
+    }
+    /// Returns the wider versions of the glyph that the font lists, from narrowest to widest (the glyph itself may be among them).
+    /// Fonts which do not provide this list return an empty vector, which is the default.
+    fn horz_variant_glyphs(&self, _gid: GlyphId) -> Vec<GlyphId> {
+        Vec::new()
+    }
+    /// Draws the outline of the glyph with `builder`, in font units and with positive Y values above the baseline (cf [`MathFont::font_units_to_em`] to convert to ems).
+    /// Fonts which cannot provide outlines draw nothing, which is the default.
+    fn outline(&self, _gid: GlyphId, _builder: &mut dyn OutlineBuilder) {}
+}
+
+/// Receives the commands drawing the outline of a glyph, cf [`MathFont::outline`].
+/// This mirrors `ttf_parser`'s trait of the same name, so that backends can fill glyph paths whatever the font parser.
+pub trait OutlineBuilder {
+    /// Starts a new contour at the given point
+    fn move_to(&mut self, x: f32, y: f32);
+    /// Draws a straight line to the given point
+    fn line_to(&mut self, x: f32, y: f32);
+    /// Draws a quadratic Bézier curve with control point (`x1`, `y1`) to the given point
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32);
+    /// Draws a cubic Bézier curve with control points (`x1`, `y1`) and (`x2`, `y2`) to the given point
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32);
+    /// Closes the current contour
+    fn close(&mut self);
 }
 
 pub struct FontContext<'f, F> {
     pub font: &'f F,
     pub constants: Constants,
     pub units_per_em: Unit<Ratio<FUnit, Em>>,
+    /// Vertical variants already computed with this font, keyed by glyph and exact target height (as the bits of the height in font units).
+    /// Holds at most [`VERT_VARIANT_CACHE_CAPACITY`] entries.
+    vert_variant_cache: Mutex<HashMap<(GlyphId, u64), VariantGlyph>>,
+    /// Metrics of the glyphs already queried from the font, e.g. the entries of a large matrix which all use the same few glyphs
+    glyph_cache: Mutex<HashMap<GlyphId, Glyph<'f, F>>>,
+    /// Number of glyphs whose metrics were queried from the font, i.e. not found in `glyph_cache`
+    #[cfg(test)]
+    glyph_font_lookups: AtomicUsize,
 }
 
+/// Largest number of vertical variants that a [`FontContext`] keeps in its cache.
+/// Heights vary with the contents of each delimiter, so that a long-lived context would otherwise keep a variant for every height it has seen ;
+/// once full, the cache is emptied and filled again.
+pub const VERT_VARIANT_CACHE_CAPACITY : usize = 4096;
+
 impl<'f, F> Clone for FontContext<'f, F> {
     fn clone(&self) -> Self {
         Self {
             font:         self.font,
             constants:    self.constants.clone(),
             units_per_em: self.units_per_em,
+            vert_variant_cache: Default::default(),
+            glyph_cache: Default::default(),
+            #[cfg(test)]
+            glyph_font_lookups: Default::default(),
         }
     }
 }
@@ -70,7 +121,11 @@ impl<'f, F : MathFont> FontContext<'f, F> {
         FontContext {
             font,
             units_per_em,
-            constants
+            constants,
+            vert_variant_cache: Default::default(),
+            glyph_cache: Default::default(),
+            #[cfg(test)]
+            glyph_font_lookups: Default::default(),
         }
     }
 
@@ -81,73 +136,253 @@ impl<'f, F : MathFont> FontContext<'f, F> {
 
 
 
+    /// Same as [`FontContext::glyph`], except that if the font has no glyph for a styled letter (e.g. '𝕏' from `\mathbb{X}`), 
+    /// the glyph of the unstyled letter ('X') is used instead and a warning is logged.
+    pub fn glyph_or_unstyled(&self, codepoint: char) -> Result<Glyph<'f, F>, FontError> {
+        match self.glyph(codepoint) {
+            Err(error @ FontError::MissingGlyphCodepoint(_)) => {
+                let unstyled = unstyle_symbol(codepoint).ok_or(error)?;
+                warn!("no glyph for '{}' (U+{:X}) in font, falling back to '{}'", codepoint, codepoint as u32, unstyled);
+                self.glyph(unstyled)
+            },
+            result => result,
+        }
+    }
+
+    /// Returns a version of the glyph for `codepoint` at least `height` tall, either a bigger replacement glyph or a construction from glyph parts.
+    /// The result is cached, so that repeated lookups for the same delimiter around contents of the same size do not walk the font's MATH variant tables again.
+    /// The cache is keyed on the exact height, so that the variant is always the one the font would give, and is bounded (cf [`VERT_VARIANT_CACHE_CAPACITY`], [`FontContext::clear_caches`]).
     pub fn vert_variant(&self, codepoint: char, height: Unit<FUnit>) -> Result<VariantGlyph, FontError> {
+        Ok(self.cached_vert_variant(codepoint, height)?.0)
+    }
+
+    /// Same as [`FontContext::vert_variant`], also telling whether the variant was found in the cache (cf [`crate::layout::engine::layout_with_stats`]).
+    pub(crate) fn cached_vert_variant(&self, codepoint: char, height: Unit<FUnit>) -> Result<(VariantGlyph, bool), FontError> {
+        let gid = self.font.glyph_index(codepoint).ok_or(FontError::MissingGlyphCodepoint(codepoint))?;
+
+        let key = (gid, height.unitless(FUnit).to_bits());
+        if let Some(variant) = self.vert_variant_cache.lock().unwrap().get(&key) {
+            return Ok((variant.clone(), true));
+        }
+
+        let variant = self.font.vert_variant(gid, height);
+        let mut cache = self.vert_variant_cache.lock().unwrap();
+        if cache.len() >= VERT_VARIANT_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(key, variant.clone());
+        Ok((variant, false))
+    }
+
+    /// Empties the caches of vertical variants and of glyph metrics of this context, e.g. to free their memory after laying out many formulas.
+    /// Later layouts fill them again: the caches only save lookups in the font, they do not change the layout.
+    pub fn clear_caches(&self) {
+        self.vert_variant_cache.lock().unwrap().clear();
+        self.glyph_cache.lock().unwrap().clear();
+    }
+    /// Returns the `n`-th enlarged version (starting from 1) of the glyph for `codepoint`, as listed by the font, 
+    /// or the largest one if the font has fewer than `n` of them. 
+    /// Returns `None` if the font lists no glyph bigger than the glyph itself.
+    pub fn nth_vert_variant(&self, codepoint: char, n: usize) -> Result<Option<VariantGlyph>, FontError> {
         let gid = self.font.glyph_index(codepoint).ok_or(FontError::MissingGlyphCodepoint(codepoint))?;
-        Ok(self.font.vert_variant(gid, height))
+        let enlarged : Vec<GlyphId> = self.font.vert_variant_glyphs(gid)
+            .into_iter()
+            .filter(|&variant| variant != gid)
+            .collect();
+
+        let index = n.saturating_sub(1).min(enlarged.len().saturating_sub(1));
+        Ok(enlarged.get(index).map(|&variant| VariantGlyph::Replacement(variant)))
     }
     pub fn horz_variant(&self, codepoint: char, width: Unit<FUnit>) -> Result<VariantGlyph, FontError> {
         let gid = self.font.glyph_index(codepoint).ok_or(FontError::MissingGlyphCodepoint(codepoint))?;
         Ok(self.font.horz_variant(gid, width))
     }
 
+    /// Same as [`FontContext::horz_variant`], for wide accents like `\widehat{..}`: 
+    /// if the glyph itself is wide enough, the narrowest wider version that the font lists is used instead, 
+    /// so that a wide accent is never smaller than the corresponding accent.
+    /// Fonts without wider versions of the glyph fall back on the glyph itself.
+    pub fn wide_accent_variant(&self, codepoint: char, width: Unit<FUnit>) -> Result<VariantGlyph, FontError> {
+        let gid = self.font.glyph_index(codepoint).ok_or(FontError::MissingGlyphCodepoint(codepoint))?;
+        let variant = self.horz_variant(codepoint, width)?;
+        if !matches!(variant, VariantGlyph::Replacement(replacement) if replacement == gid) {
+            return Ok(variant);
+        }
+
+        let narrowest_enlarged = self.font.horz_variant_glyphs(gid)
+            .into_iter()
+            .find(|&variant| variant != gid);
+        Ok(VariantGlyph::Replacement(narrowest_enlarged.unwrap_or(gid)))
+    }
+
+    /// Returns the glyph with the given id. Glyph metrics are cached: the font is only queried the first time a glyph is asked for.
     pub fn glyph_from_gid(&self, gid: GlyphId) -> Result<Glyph<'f, F>, FontError> {
-        self.font.glyph_from_gid(gid)
+        if let Some(glyph) = self.glyph_cache.lock().unwrap().get(&gid) {
+            return Ok(glyph.clone());
+        }
+
+        #[cfg(test)]
+        self.glyph_font_lookups.fetch_add(1, Ordering::Relaxed);
+        let glyph = self.font.glyph_from_gid(gid)?;
+        self.glyph_cache.lock().unwrap().insert(gid, glyph.clone());
+        Ok(glyph)
+    }
+
+    /// Returns the number of glyphs whose metrics were queried from the font so far with this context, cf [`FontContext::glyph_from_gid`].
+    #[cfg(test)]
+    pub(crate) fn glyph_font_lookups(&self) -> usize {
+        self.glyph_font_lookups.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of font units in one em, i.e. the unit in which the font's outlines and metrics are given.
+    pub fn units_per_em(&self) -> Unit<Ratio<FUnit, Em>> {
+        self.units_per_em
+    }
+
+    /// Returns the constants of the font's MATH table, with lengths in em, independently of any font size.
+    pub fn constants(&self) -> &Constants {
+        &self.constants
+    }
+
+    /// Returns the constants of the font's MATH table, with lengths in pixels for a font size of `font_size` pixels per em.
+    pub fn constants_in_px(&self, font_size: f64) -> Constants<Px> {
+        self.constants.to_px(Unit::new(font_size))
     }
 }
 
 
+/// Constants of the font's MATH table, which specify the placement of scripts, fractions, radicals, etc.
+/// Lengths are in em (cf [`FontContext::constants`]) or, after conversion, in pixels (cf [`FontContext::constants_in_px`]).
 #[derive(Clone)]
-pub struct Constants {
-    pub subscript_shift_down: Unit<Em>,
-    pub subscript_top_max: Unit<Em>,
-    pub subscript_baseline_drop_min: Unit<Em>,
-
-    pub superscript_baseline_drop_max: Unit<Em>,
-    pub superscript_bottom_min: Unit<Em>,
-    pub superscript_shift_up_cramped: Unit<Em>,
-    pub superscript_shift_up: Unit<Em>,
-    pub sub_superscript_gap_min: Unit<Em>,
-
-    pub upper_limit_baseline_rise_min: Unit<Em>,
-    pub upper_limit_gap_min: Unit<Em>,
-    pub lower_limit_gap_min: Unit<Em>,
-    pub lower_limit_baseline_drop_min: Unit<Em>,
-
-    pub fraction_rule_thickness: Unit<Em>,
-    pub fraction_numerator_display_style_shift_up: Unit<Em>,
-    pub fraction_denominator_display_style_shift_down: Unit<Em>,
-    pub fraction_num_display_style_gap_min: Unit<Em>,
-    pub fraction_denom_display_style_gap_min: Unit<Em>,
-    pub fraction_numerator_shift_up: Unit<Em>,
-    pub fraction_denominator_shift_down: Unit<Em>,
-    pub fraction_numerator_gap_min: Unit<Em>,
-    pub fraction_denominator_gap_min: Unit<Em>,
-
-    pub axis_height: Unit<Em>,
-    pub accent_base_height: Unit<Em>,
-
-    pub delimited_sub_formula_min_height: Unit<Em>,
-    pub display_operator_min_height: Unit<Em>,
-
-    pub radical_display_style_vertical_gap: Unit<Em>,
-    pub radical_vertical_gap: Unit<Em>,
-    pub radical_rule_thickness: Unit<Em>,
-    pub radical_extra_ascender: Unit<Em>,
-
-    pub stack_display_style_gap_min: Unit<Em>,
-    pub stack_top_display_style_shift_up: Unit<Em>,
-    pub stack_top_shift_up: Unit<Em>,
-    pub stack_bottom_shift_down: Unit<Em>,
-    pub stack_gap_min: Unit<Em>,
+pub struct Constants<U = Em> {
+    pub subscript_shift_down: Unit<U>,
+    pub subscript_top_max: Unit<U>,
+    pub subscript_baseline_drop_min: Unit<U>,
+
+    pub superscript_baseline_drop_max: Unit<U>,
+    pub superscript_bottom_min: Unit<U>,
+    pub superscript_shift_up_cramped: Unit<U>,
+    pub superscript_shift_up: Unit<U>,
+    pub sub_superscript_gap_min: Unit<U>,
+
+    pub upper_limit_baseline_rise_min: Unit<U>,
+    pub upper_limit_gap_min: Unit<U>,
+    pub lower_limit_gap_min: Unit<U>,
+    pub lower_limit_baseline_drop_min: Unit<U>,
+
+    pub fraction_rule_thickness: Unit<U>,
+    pub fraction_numerator_display_style_shift_up: Unit<U>,
+    pub fraction_denominator_display_style_shift_down: Unit<U>,
+    pub fraction_num_display_style_gap_min: Unit<U>,
+    pub fraction_denom_display_style_gap_min: Unit<U>,
+    pub fraction_numerator_shift_up: Unit<U>,
+    pub fraction_denominator_shift_down: Unit<U>,
+    pub fraction_numerator_gap_min: Unit<U>,
+    pub fraction_denominator_gap_min: Unit<U>,
+
+    pub axis_height: Unit<U>,
+    pub accent_base_height: Unit<U>,
+
+    pub delimited_sub_formula_min_height: Unit<U>,
+    pub display_operator_min_height: Unit<U>,
+
+    pub radical_display_style_vertical_gap: Unit<U>,
+    pub radical_vertical_gap: Unit<U>,
+    pub radical_rule_thickness: Unit<U>,
+    pub radical_extra_ascender: Unit<U>,
+    pub radical_kern_before_degree: Unit<U>,
+    pub radical_kern_after_degree: Unit<U>,
+    pub radical_degree_bottom_raise_percent: f64,
+
+    pub overbar_vertical_gap: Unit<U>,
+    pub overbar_rule_thickness: Unit<U>,
+    pub overbar_extra_ascender: Unit<U>,
+
+    pub underbar_vertical_gap: Unit<U>,
+    pub underbar_rule_thickness: Unit<U>,
+    pub underbar_extra_descender: Unit<U>,
+
+    pub stack_display_style_gap_min: Unit<U>,
+    pub stack_top_display_style_shift_up: Unit<U>,
+    pub stack_top_shift_up: Unit<U>,
+    pub stack_bottom_shift_down: Unit<U>,
+    pub stack_gap_min: Unit<U>,
 
     pub delimiter_factor: f64,
-    pub delimiter_short_fall: Unit<Em>,
-    pub null_delimiter_space: Unit<Em>,
+    pub delimiter_short_fall: Unit<U>,
+    pub null_delimiter_space: Unit<U>,
 
     pub script_percent_scale_down: f64,
     pub script_script_percent_scale_down: f64,
 }
 
+impl Constants<Em> {
+    /// Converts the lengths to pixels, for a font size of `font_size` ; ratios (e.g. [`Constants::script_percent_scale_down`]) are left unchanged.
+    pub fn to_px(&self, font_size: Unit<Ratio<Px, Em>>) -> Constants<Px> {
+        Constants {
+            subscript_shift_down:                          self.subscript_shift_down * font_size,
+            subscript_top_max:                             self.subscript_top_max * font_size,
+            subscript_baseline_drop_min:                   self.subscript_baseline_drop_min * font_size,
+
+            superscript_baseline_drop_max:                 self.superscript_baseline_drop_max * font_size,
+            superscript_bottom_min:                        self.superscript_bottom_min * font_size,
+            superscript_shift_up_cramped:                  self.superscript_shift_up_cramped * font_size,
+            superscript_shift_up:                          self.superscript_shift_up * font_size,
+            sub_superscript_gap_min:                       self.sub_superscript_gap_min * font_size,
+
+            upper_limit_baseline_rise_min:                 self.upper_limit_baseline_rise_min * font_size,
+            upper_limit_gap_min:                           self.upper_limit_gap_min * font_size,
+            lower_limit_gap_min:                           self.lower_limit_gap_min * font_size,
+            lower_limit_baseline_drop_min:                 self.lower_limit_baseline_drop_min * font_size,
+
+            fraction_rule_thickness:                       self.fraction_rule_thickness * font_size,
+            fraction_numerator_display_style_shift_up:     self.fraction_numerator_display_style_shift_up * font_size,
+            fraction_denominator_display_style_shift_down: self.fraction_denominator_display_style_shift_down * font_size,
+            fraction_num_display_style_gap_min:            self.fraction_num_display_style_gap_min * font_size,
+            fraction_denom_display_style_gap_min:          self.fraction_denom_display_style_gap_min * font_size,
+            fraction_numerator_shift_up:                   self.fraction_numerator_shift_up * font_size,
+            fraction_denominator_shift_down:               self.fraction_denominator_shift_down * font_size,
+            fraction_numerator_gap_min:                    self.fraction_numerator_gap_min * font_size,
+            fraction_denominator_gap_min:                  self.fraction_denominator_gap_min * font_size,
+
+            axis_height:                                   self.axis_height * font_size,
+            accent_base_height:                            self.accent_base_height * font_size,
+
+            delimited_sub_formula_min_height:              self.delimited_sub_formula_min_height * font_size,
+            display_operator_min_height:                   self.display_operator_min_height * font_size,
+
+            radical_display_style_vertical_gap:            self.radical_display_style_vertical_gap * font_size,
+            radical_vertical_gap:                          self.radical_vertical_gap * font_size,
+            radical_rule_thickness:                        self.radical_rule_thickness * font_size,
+            radical_extra_ascender:                        self.radical_extra_ascender * font_size,
+            radical_kern_before_degree:                    self.radical_kern_before_degree * font_size,
+            radical_kern_after_degree:                     self.radical_kern_after_degree * font_size,
+            radical_degree_bottom_raise_percent:           self.radical_degree_bottom_raise_percent,
+
+            overbar_vertical_gap:                          self.overbar_vertical_gap * font_size,
+            overbar_rule_thickness:                        self.overbar_rule_thickness * font_size,
+            overbar_extra_ascender:                        self.overbar_extra_ascender * font_size,
+
+            underbar_vertical_gap:                         self.underbar_vertical_gap * font_size,
+            underbar_rule_thickness:                       self.underbar_rule_thickness * font_size,
+            underbar_extra_descender:                      self.underbar_extra_descender * font_size,
+
+            stack_display_style_gap_min:                   self.stack_display_style_gap_min * font_size,
+            stack_top_display_style_shift_up:              self.stack_top_display_style_shift_up * font_size,
+            stack_top_shift_up:                            self.stack_top_shift_up * font_size,
+            stack_bottom_shift_down:                       self.stack_bottom_shift_down * font_size,
+            stack_gap_min:                                 self.stack_gap_min * font_size,
+
+            delimiter_factor:                              self.delimiter_factor,
+            delimiter_short_fall:                          self.delimiter_short_fall * font_size,
+            null_delimiter_space:                          self.null_delimiter_space * font_size,
+
+            script_percent_scale_down:                     self.script_percent_scale_down,
+            script_script_percent_scale_down:              self.script_script_percent_scale_down,
+        }
+    }
+}
+
 
 pub struct Glyph<'f, F> {
     pub font: &'f F,
@@ -159,6 +394,21 @@ pub struct Glyph<'f, F> {
     pub italics: Unit<FUnit>,
     pub attachment: Unit<FUnit>,
 }
+
+// Not derived, as that would require `F : Clone`
+impl<'f, F> Clone for Glyph<'f, F> {
+    fn clone(&self) -> Self {
+        Self {
+            font:       self.font,
+            gid:        self.gid,
+            bbox:       self.bbox,
+            advance:    self.advance,
+            lsb:        self.lsb,
+            italics:    self.italics,
+            attachment: self.attachment,
+        }
+    }
+}
 impl<'f, F> Glyph<'f, F> {
     pub fn height(&self) -> Unit<FUnit> {
         self.bbox.3
@@ -87,6 +87,37 @@ pub fn style_symbol(codepoint: char, style: Style) -> char {
     std::char::from_u32(cp).unwrap()
 }
 
+/// Inverse of [`style_symbol`]: takes a styled mathematical codepoint (e.g. 'U+1D54F Mathematical Double-Struck Capital X') 
+/// and returns the codepoint it was obtained from (e.g. 'U+0058 Latin Capital Letter X'). Returns `None` if the codepoint is not styled.
+pub fn unstyle_symbol(codepoint: char) -> Option<char> {
+    let mut codepoint = codepoint as u32;
+    // Some styled symbols (e.g. 'ℝ') are not in the Mathematical Alphanumeric block, but take the place of a reserved codepoint there
+    for (original, replacement) in unicode_math::MATH_ALPHANUMERIC_TABLE_RESERVED_REPLACEMENTS.iter() {
+        if codepoint == *replacement {
+            codepoint = *original;
+            break;
+        }
+    }
+
+    let groups : [(&[u32], u32, u32); 5] = [
+        (&LATIN_UPPER_LUT, UPPER_A,     UPPER_Z),
+        (&LATIN_LOWER_LUT, LOWER_A,     LOWER_Z),
+        (&GREEK_UPPER_LUT, UPPER_ALPHA, UPPER_OMEGA),
+        (&GREEK_LOWER_LUT, LOWER_ALPHA, LOWER_OMEGA),
+        (&DIGIT_LUT,       DIGIT_0,     DIGIT_9),
+    ];
+    for (lut, first, last) in groups {
+        for offset in lut.iter().filter(|offset| **offset != 0) {
+            if let Some(unstyled) = codepoint.checked_sub(*offset) {
+                if (first ..= last).contains(&unstyled) {
+                    return std::char::from_u32(unstyled);
+                }
+            }
+        }
+    }
+    None
+}
+
 fn style_lookup(lut: &[u32], codepoint: u32, style: Style) -> u32 {
     let y = style.family as usize;
     let x = style.weight as usize;
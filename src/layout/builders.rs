@@ -272,3 +272,26 @@ pub fn color<'a, F>(layout: Layout<'a, F>, color: &nodes::Color) -> LayoutNode<'
         }),
     }
 }
+
+/// A smashed node is drawn like its content, but its height and/or depth (as specified by `smash`) are zero,
+/// so that the nodes around it ignore how far its content extends up or down.
+pub fn smash<'a, F>(layout: Layout<'a, F>, smash: &nodes::Smash) -> LayoutNode<'a, F> {
+    let mut node = layout.as_node();
+    if smash.top {
+        node.height = Unit::ZERO;
+    }
+    if smash.bottom {
+        node.depth = Unit::ZERO;
+    }
+    node
+}
+
+/// A phantom is an invisible node: it reserves the dimensions of its content (in the directions specified by `phantom`) but nothing is drawn.
+pub fn phantom<'a, F>(layout: Layout<'a, F>, phantom: &nodes::Phantom) -> LayoutNode<'a, F> {
+    LayoutNode {
+        width:  if phantom.horizontal { layout.width  } else { Unit::ZERO },
+        height: if phantom.vertical   { layout.height } else { Unit::ZERO },
+        depth:  if phantom.vertical   { layout.depth  } else { Unit::ZERO },
+        node:   LayoutVariant::Kern,
+    }
+}
@@ -43,8 +43,23 @@ pub const STRUT_DEPTH       : f64 = 0.3;         // \strutbox depth  = 0.3\basel
 /// From Lamport - LateX a document preparation system (2end edition) - p. 207  
 pub const COLUMN_SEP        : Unit<Pt> = Unit::<Pt>::new(5.0) ;  // \arraycolsep
 
+// Values from the definition of `smallmatrix` in amsmath, where `\ex@` is 0.2326ex, i.e. 0.1em
+/// Half the space between two columns of `\begin{smallmatrix} .. \end{smallmatrix}`, which are separated by `\thickspace` instead of twice [`COLUMN_SEP`]
+pub const SMALL_MATRIX_COLUMN_SEP : Unit<Em> = Unit::<Em>::new(5. / 36.);
+/// Desired distance between two baselines of `\begin{smallmatrix} .. \end{smallmatrix}` (`6\ex@`), instead of [`BASELINE_SKIP`]
+pub const SMALL_MATRIX_BASELINE_SKIP : Unit<Em> = Unit::<Em>::new(0.6);
+/// Space between rows of `\begin{smallmatrix} .. \end{smallmatrix}` whose contents would otherwise be too close (`1.5\ex@`), instead of [`LINE_SKIP_ARRAY`]
+pub const SMALL_MATRIX_LINE_SKIP : Unit<Em> = Unit::<Em>::new(0.15);
+
 /// Width of the vertical bar that separates columns
 pub const RULE_WIDTH        : Unit<Pt> = Unit::<Pt>::new(0.4) ;  // \arrayrulewidth
 
 /// Space between two consecutive vertical bars in an array (e.g. `\begin{array}{c||c} .. \end{array}`)
 pub const DOUBLE_RULE_SEP   : Unit<Pt> = Unit::<Pt>::new(2.0) ;  // \doublerulesep
+
+/// Horizontal space added to the widest label of an extensible arrow (e.g. `\xrightarrow{..}`) to get the length of the arrow.
+/// amsmath adds 5mu on one side of the labels and 9mu on the other.
+pub const EXTENSIBLE_ARROW_PADDING : Unit<Em> = Unit::<Em>::new(14. / 18.);
+
+/// Space between the contents of `\boxed{..}` and its frame, corresponds to LaTeX's `\fboxsep`
+pub const FBOX_SEP          : Unit<Pt> = Unit::<Pt>::new(3.0) ;  // \fboxsep
@@ -11,7 +11,12 @@ use super::{LayoutNode, LayoutVariant, LayoutGlyph};
 use crate::parser::nodes::Rule;
 use crate::error::LayoutResult;
 
+/// Conversion of glyphs, rules and extended glyphs into layout nodes.
+/// 
+/// For instance, a delimiter stretched to a given height with [`FontContext::vert_variant`](crate::font::FontContext::vert_variant) 
+/// can be laid out on its own with [`AsLayoutNode::as_layout`] and drawn with [`Renderer::render`](crate::Renderer::render).
 pub trait AsLayoutNode<'f, F> {
+    /// Lays out `self` with the font size and style of `config`
     fn as_layout<'a>(&self, config: LayoutSettings<'a, 'f, F>) -> LayoutResult<LayoutNode<'f, F>>;
 }
 
@@ -90,7 +95,11 @@ impl<'f, F : MathFont> AsLayoutNode<'f, F> for VariantGlyph {
 
 impl<'a, 'f, F> LayoutSettings<'a, 'f, F> {
     fn scale_factor(&self) -> f64 {
-        match self.style {
+        self.scale_factor_at(self.style)
+    }
+
+    fn scale_factor_at(&self, style: Style) -> f64 {
+        match style {
             Style::Display |
             Style::DisplayCramped |
             Style::Text |
@@ -110,6 +119,12 @@ impl<'a, 'f, F> LayoutSettings<'a, 'f, F> {
         length * (self.font_size / self.ctx.units_per_em).unlift()
     }
 
+    /// Returns the font size, in pixels per em, that glyphs are laid out with in the given style.
+    /// This is the starting font size, scaled down by the font's `script_percent_scale_down` and `script_script_percent_scale_down` in script and scriptscript styles.
+    pub fn font_size_at(&self, style: Style) -> f64 {
+        self.font_size.to_unitless() * self.scale_factor_at(style)
+    }
+
     /// Convert a length given in pixels to a length in font units. The resulting value depends on the selected font size.
     pub fn to_font(&self, length: Unit<Px>) -> Unit<FUnit> {
         length  * (self.ctx.units_per_em / self.font_size).unlift()
@@ -139,7 +154,8 @@ impl Scaled for AnyUnit {
     fn scaled<F>(self, config: LayoutSettings<F>) -> Unit<Px> {
         let length = match self {
             AnyUnit::Em(em) => Unit::<Em>::new(em) * config.font_size,
-            AnyUnit::Px(px) => Unit::<Px>::new(px)
+            AnyUnit::Px(px) => Unit::<Px>::new(px),
+            AnyUnit::Ex(ex) => config.ctx.constants.accent_base_height.scale(ex) * config.font_size,
         };
         length.scale(config.scale_factor())
     }
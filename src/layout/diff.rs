@@ -0,0 +1,143 @@
+//! Structural comparison of two layouts, cf [`Layout::diff`]
+
+use std::fmt;
+
+use crate::dimensions::Unit;
+use crate::dimensions::units::Px;
+use super::{Layout, LayoutNode, LayoutVariant};
+
+/// A difference between two layouts, as reported by [`Layout::diff`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutDiff {
+    /// Position of the node where the layouts differ: the index of the node in the contents of the layout,
+    /// followed by the index of the child node in the contents of that node, etc.
+    /// An empty path designates the layouts themselves.
+    pub path:  Vec<usize>,
+    /// Name of the field that differs (e.g. `"width"`, `"gid"`, `"kind"`)
+    pub field: &'static str,
+    /// Value of the field in the first layout
+    pub left:  String,
+    /// Value of the field in the second layout
+    pub right: String,
+}
+
+impl fmt::Display for LayoutDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}: {} differs ({} vs {})", self.path, self.field, self.left, self.right)
+    }
+}
+
+impl<'f, F> Layout<'f, F> {
+    /// Walks this layout and `other` in lockstep and returns the differences in dimensions, positions and glyphs between the two.
+    /// When two nodes are of different kinds or have a different number of children, this is reported as a single difference and their children are not compared.
+    /// Two identical layouts have no differences.
+    pub fn diff(&self, other: &Layout<'f, F>) -> Vec<LayoutDiff> {
+        let mut diffs = DiffCollector { path: Vec::new(), diffs: Vec::new() };
+        diffs.length("width",  self.width,  other.width);
+        diffs.length("height", self.height, other.height);
+        diffs.length("depth",  self.depth,  other.depth);
+        diffs.length("offset", self.offset, other.offset);
+        diffs.debug("alignment", &self.alignment, &other.alignment);
+        diffs.children(self.contents.iter(), other.contents.iter());
+        diffs.diffs
+    }
+}
+
+struct DiffCollector {
+    path:  Vec<usize>,
+    diffs: Vec<LayoutDiff>,
+}
+
+impl DiffCollector {
+    fn push(&mut self, field: &'static str, left: String, right: String) {
+        self.diffs.push(LayoutDiff { path: self.path.clone(), field, left, right });
+    }
+
+    fn length(&mut self, field: &'static str, left: Unit<Px>, right: Unit<Px>) {
+        if left != right {
+            self.push(field, left.unitless(Px).to_string(), right.unitless(Px).to_string());
+        }
+    }
+
+    fn debug<T : fmt::Debug + PartialEq>(&mut self, field: &'static str, left: &T, right: &T) {
+        if left != right {
+            self.push(field, format!("{:?}", left), format!("{:?}", right));
+        }
+    }
+
+    fn children<'a, 'f : 'a, F : 'f>(
+        &mut self,
+        left:  impl ExactSizeIterator<Item = &'a LayoutNode<'f, F>>,
+        right: impl ExactSizeIterator<Item = &'a LayoutNode<'f, F>>,
+    ) {
+        if left.len() != right.len() {
+            self.push("contents", format!("{} nodes", left.len()), format!("{} nodes", right.len()));
+            return;
+        }
+        for (i, (left, right)) in left.zip(right).enumerate() {
+            self.path.push(i);
+            self.node(left, right);
+            self.path.pop();
+        }
+    }
+
+    fn node<F>(&mut self, left: &LayoutNode<F>, right: &LayoutNode<F>) {
+        self.length("width",  left.width,  right.width);
+        self.length("height", left.height, right.height);
+        self.length("depth",  left.depth,  right.depth);
+
+        match (&left.node, &right.node) {
+            (LayoutVariant::Glyph(left), LayoutVariant::Glyph(right)) => {
+                self.debug("gid", &left.gid, &right.gid);
+                self.length("size",       left.size,       right.size);
+                self.length("offset",     left.offset,     right.offset);
+                self.length("attachment", left.attachment, right.attachment);
+                self.length("italics",    left.italics,    right.italics);
+            },
+            (LayoutVariant::HorizontalBox(left), LayoutVariant::HorizontalBox(right)) => {
+                self.length("offset", left.offset, right.offset);
+                self.debug("alignment", &left.alignment, &right.alignment);
+                self.children(left.contents.iter(), right.contents.iter());
+            },
+            (LayoutVariant::VerticalBox(left), LayoutVariant::VerticalBox(right)) => {
+                self.length("offset", left.offset, right.offset);
+                self.debug("alignment", &left.alignment, &right.alignment);
+                self.children(left.contents.iter(), right.contents.iter());
+            },
+            (LayoutVariant::Color(left), LayoutVariant::Color(right)) => {
+                self.debug("color", &left.color, &right.color);
+                self.children(left.inner.iter(), right.inner.iter());
+            },
+            (LayoutVariant::Grid(left), LayoutVariant::Grid(right)) => {
+                self.debug("columns", &left.columns, &right.columns);
+                self.debug("rows",    &left.rows,    &right.rows);
+                let left_cells  : Vec<_> = left.contents.keys().collect();
+                let right_cells : Vec<_> = right.contents.keys().collect();
+                if left_cells != right_cells {
+                    self.debug("cells", &left_cells, &right_cells);
+                    return;
+                }
+                self.children(left.contents.values(), right.contents.values());
+            },
+            (LayoutVariant::Diagonal(left), LayoutVariant::Diagonal(right)) => {
+                self.length("thickness", left.thickness, right.thickness);
+                self.debug("ascending", &left.ascending, &right.ascending);
+            },
+            (LayoutVariant::Rule, LayoutVariant::Rule) | (LayoutVariant::Kern, LayoutVariant::Kern) => (),
+            (left, right) => self.push("kind", kind(left).to_string(), kind(right).to_string()),
+        }
+    }
+}
+
+fn kind<F>(variant: &LayoutVariant<F>) -> &'static str {
+    match variant {
+        LayoutVariant::Grid(_)          => "grid",
+        LayoutVariant::HorizontalBox(_) => "horizontal box",
+        LayoutVariant::VerticalBox(_)   => "vertical box",
+        LayoutVariant::Glyph(_)         => "glyph",
+        LayoutVariant::Color(_)         => "color",
+        LayoutVariant::Rule             => "rule",
+        LayoutVariant::Diagonal(_)      => "diagonal",
+        LayoutVariant::Kern             => "kern",
+    }
+}
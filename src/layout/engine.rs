@@ -5,23 +5,24 @@
 
 
 use std::io::Write;
-use std::unimplemented;
+use std::sync::atomic::AtomicUsize;
 
 use super::builders;
 use super::convert::AsLayoutNode;
-use super::{Alignment, Layout, LayoutNode, LayoutSettings, LayoutVariant, Style};
+use super::{Alignment, Diagonal, Layout, LayoutNode, LayoutSettings, LayoutVariant, Style};
 
 use crate::font::MathFont;
 use crate::font::{
     kerning::{superscript_kern, subscript_kern},
     VariantGlyph,
-    TexSymbolType
+    TexSymbolType,
+    compose_accent,
 };
 use crate::layout::builders::{HBox, VBox};
-use crate::layout::constants::{BASELINE_SKIP, COLUMN_SEP, DOUBLE_RULE_SEP, JOT, LINE_SKIP_ARRAY, LINE_SKIP_LIMIT_ARRAY, RULE_WIDTH, STRUT_DEPTH, STRUT_HEIGHT};
+use crate::layout::constants::{BASELINE_SKIP, DOUBLE_RULE_SEP, EXTENSIBLE_ARROW_PADDING, FBOX_SEP, JOT, LINE_SKIP_ARRAY, LINE_SKIP_LIMIT_ARRAY, RULE_WIDTH, SMALL_MATRIX_BASELINE_SKIP, SMALL_MATRIX_COLUMN_SEP, SMALL_MATRIX_LINE_SKIP, STRUT_DEPTH, STRUT_HEIGHT};
 use super::convert::Scaled;
 use super::spacing::{atom_space, Spacing};
-use crate::parser::nodes::{Accent, Array, ArrayColumnAlign, ArrayColumnsFormatting, BarThickness, ColSeparator, Delimited, ExtendedDelimiter, GenFraction, MathStyle, ParseNode, PlainText, Radical, Scripts, Stack};
+use crate::parser::nodes::{as_multicolumn, cell_span, Accent, Array, ArrayColumnAlign, ArrayColumnsFormatting, BarThickness, Boxed, Cancel, CancelDirection, CellContent, ColSeparator, Delimited, ExtendedDelimiter, ExtensibleArrow, GenFraction, Line, LinePlacement, MakeBox, MathStyle, ParseNode, PlainText, Radical, Scripts, Stack};
 use crate::parser::symbols::Symbol;
 use crate::dimensions::{AnyUnit, Unit};
 use crate::dimensions::units::{Px, Em, Pt, FUnit};
@@ -30,11 +31,57 @@ use crate::error::{LayoutResult, LayoutError};
 
 /// Entry point to our recursive algorithm
 pub fn layout<'a, 'f: 'a, F : MathFont>(nodes: &[ParseNode], config: LayoutSettings<'a, 'f, F>) -> LayoutResult<Layout<'f, F>> {
-    layout_recurse(nodes, config, TexSymbolType::Transparent)
+    let mut layout = layout_recurse(nodes, config, TexSymbolType::Transparent, true)?;
+    layout.axis_height = config.ctx.constants.axis_height.scaled(config);
+    Ok(layout)
+}
+
+/// Same as [`layout`], for the parts of a formula (e.g. the numerator of a fraction), which are never broken into lines
+fn layout_nested<'a, 'f: 'a, F : MathFont>(nodes: &[ParseNode], config: LayoutSettings<'a, 'f, F>) -> LayoutResult<Layout<'f, F>> {
+    let mut layout = layout_recurse(nodes, config, TexSymbolType::Transparent, false)?;
+    layout.axis_height = config.ctx.constants.axis_height.scaled(config);
+    Ok(layout)
+}
+
+/// Some counts gathered while laying out a formula, cf [`layout_with_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutStats {
+    /// Number of glyphs in the resulting layout
+    pub glyphs: usize,
+    /// Number of lookups of vertical variants (e.g. for delimiters, radicals, large operators)
+    pub vert_variants: usize,
+    /// Number of lookups of horizontal variants (e.g. for accents)
+    pub horz_variants: usize,
+    /// Number of lookups of vertical variants which were answered from the font context's cache, without consulting the font
+    pub vert_variant_cache_hits: usize,
+}
+
+/// Counts of the variant lookups of a single layout, shared by all the settings derived from those given to [`layout_with_stats`].
+#[derive(Debug, Default)]
+pub(crate) struct LookupCounters {
+    pub(crate) vert_variants: AtomicUsize,
+    pub(crate) horz_variants: AtomicUsize,
+    pub(crate) vert_variant_cache_hits: AtomicUsize,
+}
+
+/// Same as [`layout`] but additionally returns some counts about the layout process, useful for performance tuning.
+/// The counts only cover this layout, even if other layouts share the same font context.
+pub fn layout_with_stats<'a, 'f: 'a, F : MathFont>(nodes: &[ParseNode], config: LayoutSettings<'a, 'f, F>) -> LayoutResult<(Layout<'f, F>, LayoutStats)> {
+    let lookups = LookupCounters::default();
+    let layout = layout(nodes, LayoutSettings { lookups: Some(&lookups), ..config })?;
+
+    let stats = LayoutStats {
+        glyphs:        layout.glyph_count(),
+        vert_variants: lookups.vert_variants.into_inner(),
+        horz_variants: lookups.horz_variants.into_inner(),
+        vert_variant_cache_hits: lookups.vert_variant_cache_hits.into_inner(),
+    };
+    Ok((layout, stats))
 }
 
 /// This method takes the parsing nodes and layouts them to layout nodes.
-fn layout_recurse<'a, 'f: 'a, F : MathFont>(nodes: &[ParseNode], mut config: LayoutSettings<'a, 'f, F>, parent_next: TexSymbolType) -> LayoutResult<Layout<'f, F>> {
+/// The [`Layout::break_points`] are only recorded if `top_level` is set.
+fn layout_recurse<'a, 'f: 'a, F : MathFont>(nodes: &[ParseNode], mut config: LayoutSettings<'a, 'f, F>, parent_next: TexSymbolType, top_level: bool) -> LayoutResult<Layout<'f, F>> {
     let mut layout = Layout::new();
     let mut prev = None;
     let mut italic_correction = None;
@@ -91,6 +138,11 @@ fn layout_recurse<'a, 'f: 'a, F : MathFont>(nodes: &[ParseNode], mut config: Lay
 
 
 
+        // A formula may be broken before a relation, but not between two consecutive relations (e.g. `:=`)
+        if top_level && current == TexSymbolType::Relation && matches!(prev, Some(prev) if prev != TexSymbolType::Relation) {
+            layout.break_points.push(layout.contents.len());
+        }
+
         match *node {
             ParseNode::Style(sty) => config.style = sty,
             ParseNode::Symbol(symbol) => {
@@ -123,11 +175,20 @@ fn must_apply_italic_correction_before(node: &ParseNode) -> bool {
     true
 }
 
-// TODO: this should return layout result
-fn layout_node<'a, 'f: 'a, F : MathFont>(node: &ParseNode, config: LayoutSettings<'a, 'f, F>) -> Layout<'f, F> {
+/// Lays out a single parse node, e.g. to embed it in a layout built by other means.
+/// The nodes returned are those the parse node lays out into, before any spacing is added between atoms:
+/// unlike [`layout`], no kern is inserted to separate the parse node from its neighbours (e.g. around a binary operator).
+pub fn layout_node<'a, 'f: 'a, F : MathFont>(node: &ParseNode, config: LayoutSettings<'a, 'f, F>) -> LayoutResult<Vec<LayoutNode<'f, F>>> {
+    Ok(layout_single_node(node, config)?.contents)
+}
+
+fn layout_single_node<'a, 'f: 'a, F : MathFont>(node: &ParseNode, config: LayoutSettings<'a, 'f, F>) -> LayoutResult<Layout<'f, F>> {
     let mut layout = Layout::new();
-    layout.dispatch(config, node, TexSymbolType::Transparent);
-    layout.finalize()
+    // A style change on its own affects no node
+    if !matches!(node, ParseNode::Style(_)) {
+        layout.dispatch(config, node, TexSymbolType::Transparent)?;
+    }
+    Ok(layout.finalize())
 }
 
 impl<'f, F : MathFont> Layout<'f, F> {
@@ -135,8 +196,13 @@ impl<'f, F : MathFont> Layout<'f, F> {
     fn dispatch<'a>(&mut self, config: LayoutSettings<'a, 'f, F>, node: &ParseNode, next: TexSymbolType) -> LayoutResult<()> {
         match *node {
             ParseNode::Symbol(symbol) => self.add_node(self.symbol(symbol, config)?),
+            ParseNode::SmallOperator(symbol) => self.add_node(self.largeop(symbol, false, config)?),
             ParseNode::Scripts(ref script) => self.scripts(script, config)?,
             ParseNode::Radical(ref rad) => self.radical(rad, config)?,
+            ParseNode::Line(ref line) => self.line(line, config)?,
+            ParseNode::Cancel(ref cancel) => self.cancel(cancel, config)?,
+            ParseNode::Boxed(ref boxed) => self.boxed(boxed, config)?,
+            ParseNode::ExtensibleArrow(ref arrow) => self.extensible_arrow(arrow, config)?,
             ParseNode::Delimited(ref delim) => self.delimited(delim, config)?,
             ParseNode::ExtendedDelimiter(ref delim) => self.extended_delimiter(delim, config)?,
             ParseNode::Accent(ref acc) => self.accent(acc, config)?,
@@ -144,31 +210,63 @@ impl<'f, F : MathFont> Layout<'f, F> {
             ParseNode::Stack(ref stack) => self.substack(stack, config)?,
             ParseNode::Array(ref arr) => self.array(arr, config)?,
 
-            ParseNode::AtomChange(ref ac) => self.add_node(layout(&ac.inner, config)?.as_node()),
-            ParseNode::Group(ref gp) => self.add_node(layout(gp, config)?.as_node()),
+            ParseNode::AtomChange(ref ac) => self.add_node(layout_nested(&ac.inner, config)?.as_node()),
+            ParseNode::Group(ref gp) => self.add_node(layout_nested(gp, config)?.as_node()),
+            ParseNode::MultiColumn(ref mc) => self.add_node(layout_nested(&mc.inner, config)?.as_node()),
+            ParseNode::MakeBox(ref mb) => self.makebox(mb, config)?,
             ParseNode::Rule(rule) => self.add_node(rule.as_layout(config)?),
             ParseNode::Kerning(kern) => self.add_node(kern!(horz: kern.scaled(config))),
+            // Lines are stripped of their trailing kerns when a formula is broken (cf `Layout::wrapped`) ; wrapping the kern in a box keeps it.
+            ParseNode::FixedKerning(kern) => {
+                let mut hbox = HBox::new();
+                hbox.add_node(kern!(horz: kern.scaled(config)));
+                self.add_node(hbox.build())
+            },
 
             ParseNode::Color(ref clr) => {
-                let inner = layout_recurse(&clr.inner, config, next)?;
+                let inner = layout_recurse(&clr.inner, config, next, false)?;
                 self.add_node(builders::color(inner, clr))
             }
 
+            ParseNode::Phantom(ref phantom) => {
+                let inner = layout_nested(&phantom.inner, config)?;
+                self.add_node(builders::phantom(inner, phantom))
+            }
+
+            ParseNode::Smash(ref smash) => {
+                let inner = layout_nested(&smash.inner, config)?;
+                self.add_node(builders::smash(inner, smash))
+            }
+
             ParseNode::DummyNode(_) => (),
 
             ParseNode::PlainText(PlainText {ref text}) => {
-                for character in text.chars() {
+                // Characters are drawn as is, without the remapping that math symbols undergo (cf [`crate::font::style_symbol`]):
+                // e.g. '-' remains a hyphen, rather than becoming a minus sign.
+                let mut characters = text.chars().peekable();
+                while let Some(character) = characters.next() {
                     if character.is_ascii_whitespace() {
-                        self.add_node(kern![horz : Spacing::Medium.to_length().scaled(config)])
-                    }
-                    else {
-                        self.add_node(config.ctx.glyph(character)?.as_layout(config)?);
+                        self.add_node(kern![horz : Spacing::Medium.to_length().scaled(config)]);
+                        continue;
                     }
+                    // A letter followed by a combining mark (e.g. 'e' followed by U+0301) is drawn with the precomposed glyph ('é') if the font has one.
+                    // Otherwise, the mark is drawn after the letter: combining marks have no advance width and are drawn over the preceding glyph.
+                    let composed = characters.peek()
+                        .and_then(|&mark| compose_accent(character, mark))
+                        .and_then(|composed| config.ctx.glyph(composed).ok());
+                    let glyph = match composed {
+                        Some(glyph) => {
+                            characters.next();
+                            glyph
+                        },
+                        None => config.ctx.glyph(character)?,
+                    };
+                    self.add_node(glyph.as_layout(config)?);
                 }
             },
 
-            // TODO: understand whether this is needed anywhere
-            ParseNode::Style(_)     => unimplemented!(),
+            // Style changes affect the nodes following them, cf [`layout_recurse`] ; on their own, they lay out nothing
+            ParseNode::Style(_)     => (),
         }
         Ok(())
     }
@@ -177,16 +275,17 @@ impl<'f, F : MathFont> Layout<'f, F> {
         // Operators are handled specially.  We may need to find a larger
         // symbol and vertical center it.
         match sym.atom_type {
-            TexSymbolType::Operator(_) => self.largeop(sym, config),
-            _ => config.ctx.glyph(sym.codepoint)?.as_layout(config)
+            TexSymbolType::Operator(_) => self.largeop(sym, true, config),
+            _ => config.ctx.glyph_or_unstyled(sym.codepoint)?.as_layout(config)
         }
     }
 
-    fn largeop<'a>(&self, sym: Symbol, config: LayoutSettings<'a, 'f, F>) -> LayoutResult<LayoutNode<'f, F>> {
+    /// Lays out an operator symbol, which is enlarged in display style if `enlarged` (e.g. `\int`, but not `\smallint`)
+    fn largeop<'a>(&self, sym: Symbol, enlarged: bool, config: LayoutSettings<'a, 'f, F>) -> LayoutResult<LayoutNode<'f, F>> {
         let glyph = config.ctx.glyph(sym.codepoint)?;
-        if config.style > Style::Text {
+        if enlarged && config.style > Style::Text {
             let axis_offset = config.ctx.constants.axis_height.scaled(config);
-            let largeop = config.ctx.vert_variant(sym.codepoint, config.ctx.constants.display_operator_min_height * config.ctx.units_per_em)?
+            let largeop = config.vert_variant(sym.codepoint, config.ctx.constants.display_operator_min_height * config.ctx.units_per_em)?
                 .as_layout(config)?;
             let shift = (largeop.height + largeop.depth).scale(0.5) - axis_offset;
             Ok(vbox!(offset: shift; largeop))
@@ -199,9 +298,14 @@ impl<'f, F : MathFont> Layout<'f, F> {
         // [ ] The width of the selfing box is the width of the base.
         // [ ] Bottom accents: vertical placement is directly below nucleus,
         //       no correction takes place.
-        // [ ] WideAccent vs Accent: Don't expand Accent types.
-        let base = layout(&acc.nucleus, config.cramped())?;
-        let accent_variant = config.ctx.horz_variant(acc.symbol.codepoint, config.to_font(base.width))?;
+        // [x] WideAccent vs Accent: Don't expand Accent types.
+        let base = layout_nested(&acc.nucleus, config.cramped())?;
+        let accent_variant = if acc.extend {
+            config.wide_accent_variant(acc.symbol.codepoint, config.to_font(base.width))?
+        } else {
+            let gid = config.ctx.glyph(acc.symbol.codepoint)?.gid;
+            VariantGlyph::Replacement(gid)
+        };
         let accent = accent_variant.as_layout(config)?;
 
         // Attachment points for accent & base are calculated by
@@ -244,9 +348,14 @@ impl<'f, F : MathFont> Layout<'f, F> {
 
         // By not placing an offset on this vbox, we are assured that the
         // baseline will match the baseline of `base.as_node()`
-        self.add_node(vbox!(hbox!(kern!(horz: base_offset - acc_offset), accent),
-                            kern!(vert: delta),
-                            base.as_node()));
+        let base_width = base.width;
+        let mut node = vbox!(hbox!(kern!(horz: base_offset - acc_offset), accent),
+                             kern!(vert: delta),
+                             base.as_node());
+        // As in TeX, the accented node is as wide as its base: an accent sticking out of the base
+        // must not change the spacing with the following nodes
+        node.width = base_width;
+        self.add_node(node);
         
         Ok(())
     }
@@ -257,7 +366,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
         let mut max_height = Unit::ZERO;
         let mut min_depth  = Unit::ZERO;
         for inner_parse_nodes in delim.inners() {
-            let inner = layout(inner_parse_nodes.as_slice(), config)?.as_node();
+            let inner = layout_nested(inner_parse_nodes.as_slice(), config)?.as_node();
             max_height = Unit::max(max_height, inner.height);
             min_depth  = Unit::min(min_depth,  inner.depth);
             inners.push(inner);
@@ -277,27 +386,52 @@ impl<'f, F : MathFont> Layout<'f, F> {
     fn scripts<'a>(&mut self, scripts: &Scripts, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
         // See: https://tug.org/TUGboat/tb27-1/tb86jackowski.pdf
         //      https://www.tug.org/tugboat/tb30-1/tb94vieth.pdf
-        let base = match scripts.base {
-            Some(ref base) => layout_node(base, config),
+        let mut base = match scripts.base {
+            Some(ref base) => layout_single_node(base, config)?,
             None => Layout::new(),
         };
+        let mut base_node = scripts.base.as_deref();
+        let mut superscript = scripts.superscript.as_deref();
+
+        // The glyphs of primes are already raised by the font: a superscript starting with primes (e.g. `f''^2`) 
+        // has them drawn at the size of the base, right after it, and the rest of the superscript is placed after them.
+        let n_primes = superscript.map_or(0, |sup| sup.iter().take_while(|node| node.is_prime()).count());
+        if n_primes > 0 {
+            let sup = superscript.unwrap_or_default();
+            let italics = base.is_symbol().map_or(Unit::ZERO, |sym| sym.italics);
+            let mut with_primes = Layout::new();
+            with_primes.add_node(base.as_node());
+            if !italics.is_zero() {
+                with_primes.add_node(kern!(horz: italics));
+            }
+            with_primes.add_node(layout_nested(&sup[.. n_primes], config)?.as_node());
+            base = with_primes.finalize();
+            base_node = None;
+            superscript = Some(&sup[n_primes ..]).filter(|rest| !rest.is_empty());
+
+            if superscript.is_none() && scripts.subscript.is_none() {
+                self.add_node(base.as_node());
+                return Ok(());
+            }
+        }
 
-        let mut sup = match scripts.superscript {
-            Some(ref sup) => layout(sup, config.superscript_variant())?,
+        let mut sup = match superscript {
+            Some(sup) => layout_nested(sup, config.superscript_variant())?,
             None => Layout::new(),
         };
 
         let mut sub = match scripts.subscript {
-            Some(ref sub) => layout(sub, config.subscript_variant())?,
+            Some(ref sub) => layout_nested(sub, config.subscript_variant())?,
             None => Layout::new(),
         };
 
         // We use a different algoirthm for handling scripts for operators with limits.
         // This is where he handle Operators with limits.
-        if let Some(ref b) = scripts.base {
+        if let Some(b) = base_node {
             if TexSymbolType::Operator(true) == b.atom_type() {
-                self.operator_limits(base, sup, sub, config);
-                return Ok(());
+                let sup = superscript.is_some().then_some(sup);
+                let sub = scripts.subscript.is_some().then_some(sub);
+                return self.operator_limits(base, sup, sub, config);
             }
         }
 
@@ -308,7 +442,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
         let mut sup_kern = Unit::ZERO;
         let mut sub_kern = Unit::ZERO;
 
-        if scripts.superscript.is_some() {
+        if superscript.is_some() {
             // Use default font values for first iteration of vertical height.
             adjust_up = match config.style.is_cramped() {
                 true => config.ctx.constants.superscript_shift_up_cramped,
@@ -318,11 +452,11 @@ impl<'f, F : MathFont> Layout<'f, F> {
 
             // TODO: These checks should be recursive?
             let mut height = base.height;
-            if let Some(ref b) = scripts.base {
+            if let Some(b) = base_node {
                 if b.atom_type() != TexSymbolType::Operator(false) {
                     // For accents whose base is a simple symbol we do not take
                     // the accent into account while positioning the superscript.
-                    if let ParseNode::Accent(ref acc) = **b {
+                    if let ParseNode::Accent(ref acc) = *b {
                         use crate::parser::is_symbol;
                         if let Some(sym) = is_symbol(&acc.nucleus) {
                             height = config.ctx.glyph(sym.codepoint)?.height().scaled(config);
@@ -359,7 +493,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
 
             // Provided that the base and subscript are symbols, we apply
             // kerning values found in the kerning font table
-            if let Some(ref b) = scripts.base {
+            if let Some(b) = base_node {
                 if let Some(base_sym) = base.is_symbol() {
                     if TexSymbolType::Operator(false) == b.atom_type() {
                         // This recently changed in LuaTeX.  See `nolimitsmode`.
@@ -377,7 +511,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
         }
 
         // TODO: lazy gap fix; see BottomMaxWithSubscript
-        if scripts.subscript.is_some() && scripts.superscript.is_some() {
+        if scripts.subscript.is_some() && superscript.is_some() {
             let sup_bot = adjust_up + sup.depth;
             let sub_top = sub.height - adjust_down;
             let gap_min = config.ctx.constants.sub_superscript_gap_min.scaled(config);
@@ -389,7 +523,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
         }
 
         let mut contents = builders::VBox::new();
-        if scripts.superscript.is_some() {
+        if superscript.is_some() {
             if !sup_kern.is_zero() {
                 sup.contents.insert(0, kern!(horz: sup_kern));
                 sup.width += sup_kern;
@@ -415,7 +549,9 @@ impl<'f, F : MathFont> Layout<'f, F> {
         Ok(())
     }
 
-    fn operator_limits<'a>(&mut self, base: Layout<'f, F>, sup: Layout<'f, F>, sub: Layout<'f, F>, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
+    /// Places `sup` and `sub` as limits above and below `base`. 
+    /// As in TeX (rule 13a of Appendix G of the TeXbook), absent limits take no room: there is no gap for them either.
+    fn operator_limits<'a>(&mut self, base: Layout<'f, F>, sup: Option<Layout<'f, F>>, sub: Option<Layout<'f, F>>, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
         // Provided that the operator is a simple symbol, we need to account
         // for the italics correction of the symbol.  This how we "center"
         // the superscript and subscript of the limits.
@@ -424,48 +560,58 @@ impl<'f, F : MathFont> Layout<'f, F> {
             None => Unit::ZERO
         };
 
-        // Next we calculate the kerning required to separate the superscript
-        // and subscript (respectively) from the base.
-        let sup_kern = Unit::max(config.ctx.constants.upper_limit_baseline_rise_min.scaled(config),
-                        config.ctx.constants.upper_limit_gap_min.scaled(config) - sup.depth);
-        let sub_kern = Unit::max(config.ctx.constants.lower_limit_gap_min.scaled(config),
-                        config.ctx.constants.lower_limit_baseline_drop_min.scaled(config) - sub.height) -
-                    base.depth;
-
-        // We need to preserve the baseline of the operator when
-        // attaching the scripts.  Since the base should already
-        // be aligned, we only need to offset by the addition of
-        // subscripts.
-        let offset = sub.height + sub_kern;
-
         // We will construct a vbox containing the superscript/base/subscript.
         // We will all of these nodes, so we widen each to the largest.
-        let width = max!(base.width, sub.width + delta.scale(0.5), sup.width + delta.scale(0.5));
+        let width = max!(
+            base.width, 
+            sub.as_ref().map_or(Unit::ZERO, |sub| sub.width + delta.scale(0.5)), 
+            sup.as_ref().map_or(Unit::ZERO, |sup| sup.width + delta.scale(0.5))
+        );
+
+        let mut vbox = builders::VBox::new();
+        let base_depth = base.depth;
 
-        self.add_node(vbox![
-            offset: offset;
-            hbox![align: Alignment::Centered(sup.width);
+        // Next we calculate the kerning required to separate the superscript
+        // and subscript (respectively) from the base.
+        if let Some(sup) = sup {
+            let sup_kern = Unit::max(config.ctx.constants.upper_limit_baseline_rise_min.scaled(config),
+                            config.ctx.constants.upper_limit_gap_min.scaled(config) - sup.depth);
+            vbox.add_node(hbox![align: Alignment::Centered(sup.width);
                 width: width;
                 kern![horz: delta.scale(0.5)],
                 sup.as_node()
-            ],
+            ]);
+            vbox.add_node(kern!(vert: sup_kern));
+        }
+
+        vbox.add_node(base.centered(width).as_node());
 
-            kern!(vert: sup_kern),
-            base.centered(width).as_node(),
-            kern!(vert: sub_kern),
+        if let Some(sub) = sub {
+            let sub_kern = Unit::max(config.ctx.constants.lower_limit_gap_min.scaled(config),
+                            config.ctx.constants.lower_limit_baseline_drop_min.scaled(config) - sub.height) -
+                        base_depth;
 
-            hbox![align: Alignment::Centered(sub.width);
+            // We need to preserve the baseline of the operator when
+            // attaching the scripts.  Since the base should already
+            // be aligned, we only need to offset by the addition of
+            // subscripts.
+            vbox.set_offset(sub.height + sub_kern);
+            vbox.add_node(kern!(vert: sub_kern));
+            vbox.add_node(hbox![align: Alignment::Centered(sub.width);
                 width: width;
                 kern![horz: -delta.scale(0.5)],
                 sub.as_node()
-            ]
-        ]);
+            ]);
+        }
+
+        self.add_node(vbox.build());
         
         Ok(())
     }
 
     fn frac<'a>(&mut self, frac: &GenFraction, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
         let config = match frac.style {
+            MathStyle::NoChange if config.always_display_fractions => config.with_display(),
             MathStyle::NoChange => config.clone(),
             MathStyle::Display => config.with_display(),
             MathStyle::Text => config.with_text(),
@@ -477,14 +623,28 @@ impl<'f, F : MathFont> Layout<'f, F> {
             BarThickness::Unit(u) => u.scaled(config),
         };
 
-        let mut n = layout(&frac.numerator, config.numerator())?;
-        let mut d = layout(&frac.denominator, config.denominator())?;
+        let numerator_config = match frac.numerator_style {
+            MathStyle::NoChange => config.numerator(),
+            MathStyle::Display  => config.with_display(),
+            MathStyle::Text     => config.with_text(),
+        };
+        let denominator_config = match frac.denominator_style {
+            MathStyle::NoChange => config.denominator(),
+            MathStyle::Display  => config.with_display(),
+            MathStyle::Text     => config.with_text(),
+        };
+        let mut n = layout_nested(&frac.numerator, numerator_config)?;
+        let mut d = layout_nested(&frac.denominator, denominator_config)?;
 
         if n.width > d.width {
             d.alignment = Alignment::Centered(d.width);
             d.width = n.width;
         } else {
-            n.alignment = Alignment::Centered(n.width);
+            n.alignment = match frac.numerator_alignment {
+                ArrayColumnAlign::Centered => Alignment::Centered(n.width),
+                ArrayColumnAlign::Left     => Alignment::Left,
+                ArrayColumnAlign::Right    => Alignment::Right(n.width),
+            };
             n.width = d.width;
         }
 
@@ -525,29 +685,26 @@ impl<'f, F : MathFont> Layout<'f, F> {
         let null_delimiter_space = config.ctx.constants.null_delimiter_space * config.font_size;
         let axis_height = config.ctx.constants.axis_height * config.font_size;
         // Enclose fraction with delimiters if provided, otherwise with a NULL_DELIMITER_SPACE.
-        let left = match frac.left_delimiter {
-            None => kern!(horz: null_delimiter_space),
-            Some(sym) => {
-                let clearance = Unit::max(inner.height - axis_height, axis_height - inner.depth).scale(2.0);
-                let clearance = Unit::max(clearance, config.ctx.constants.delimited_sub_formula_min_height * config.font_size);
-
-                config.ctx.vert_variant(sym.codepoint, config.to_font(clearance))?
-                    .as_layout(config)?
-                    .centered(axis_height.scaled(config))
-            }
-        };
-
-        let right = match frac.right_delimiter {
-            None => kern!(horz: null_delimiter_space),
-            Some(sym) => {
-                let clearance = Unit::max(inner.height - axis_height, axis_height - inner.depth).scale(2.0);
-                let clearance = Unit::max(clearance, config.ctx.constants.delimited_sub_formula_min_height * config.font_size);
+        let delimiter = |delimiter : Option<Symbol>| -> LayoutResult<LayoutNode<'f, F>> {
+            match (delimiter, frac.delimiter_size) {
+                (None, _) => Ok(kern!(horz: null_delimiter_space)),
+                // Delimiters of fixed size are laid out as with `\big(`, `\Big(`, etc.
+                (Some(sym), Some(size)) => {
+                    let fixed = ExtendedDelimiter::new(sym, AnyUnit::from(size.to_size())).with_size(size);
+                    Ok(layout_single_node(&ParseNode::ExtendedDelimiter(fixed), config)?.as_node())
+                },
+                (Some(sym), None) => {
+                    let clearance = Unit::max(inner.height - axis_height, axis_height - inner.depth).scale(2.0);
+                    let clearance = Unit::max(clearance, config.ctx.constants.delimited_sub_formula_min_height * config.font_size);
 
-                config.ctx.vert_variant(sym.codepoint, config.to_font(clearance))?
-                    .as_layout(config)?
-                    .centered(axis_height.scaled(config))
+                    Ok(config.vert_variant(sym.codepoint, config.to_font(clearance))?
+                        .as_layout(config)?
+                        .centered(axis_height.scaled(config)))
+                },
             }
         };
+        let left  = delimiter(frac.left_delimiter)?;
+        let right = delimiter(frac.right_delimiter)?;
 
         self.add_node(left);
         self.add_node(inner);
@@ -557,7 +714,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
     }
     fn radical<'a>(&mut self, rad: &Radical, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
         // reference rule 11 from pg 443 of TeXBook
-        let contents = layout(&rad.inner, config.cramped())?.as_node();
+        let contents = layout_nested(&rad.inner, config.cramped())?.as_node();
 
         // obtain minimum clearange between radicand and radical bar
         // and cache other sizes that will be needed
@@ -571,7 +728,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
 
         // determine size of radical glyph
         let inner_height = (contents.height - contents.depth) + gap + rule_thickness;
-        let sqrt = config.ctx.vert_variant('√', config.to_font(inner_height))?.as_layout(config)?;
+        let sqrt = config.vert_variant('√', config.to_font(inner_height))?.as_layout(config)?;
 
         // pad between radicand and radical bar
         let delta = (sqrt.height - sqrt.depth - inner_height).scale(0.5) + rule_thickness;
@@ -585,6 +742,25 @@ impl<'f, F : MathFont> Layout<'f, F> {
         // TODO: This is unclear
         let top_padding = rule_ascender - rule_thickness;
 
+        // The index, if any, sits in scriptscript style in the kink of the radical sign,
+        // its bottom raised above the bottom of the radical sign by a percentage of the sign's height
+        if let Some(ref index) = rad.index {
+            let index = layout_nested(index, config.layout_style(Style::ScriptScript))?.as_node();
+            let kern_before = config.ctx.constants.radical_kern_before_degree.scaled(config);
+            let kern_after  = config.ctx.constants.radical_kern_after_degree.scaled(config);
+            let raise_ratio = config.ctx.constants.radical_degree_bottom_raise_percent;
+
+            let sqrt_bottom = sqrt.depth - offset;
+            let raise = sqrt_bottom + (sqrt.height - sqrt.depth).scale(raise_ratio) - index.depth;
+
+            // the index may overlap the radical sign but not stick out to the left of the formula
+            let kern_before = Unit::max(kern_before, -(index.width + kern_after));
+
+            self.add_node(kern!(horz: kern_before));
+            self.add_node(vbox![offset: -raise; index]);
+            self.add_node(kern!(horz: kern_after));
+        }
+
         self.add_node(vbox![offset: offset; sqrt]);
         self.add_node(vbox![kern!(vert: top_padding),
                             rule!(width:  contents.width, height: rule_thickness),
@@ -594,6 +770,137 @@ impl<'f, F : MathFont> Layout<'f, F> {
         Ok(())
     }
 
+    fn makebox<'a>(&mut self, makebox: &MakeBox, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
+        let mut content = layout_nested(&makebox.inner, config)?;
+        if let Some(width) = makebox.width {
+            // The content may overflow the box, in which case it sticks out on one or both sides
+            content.alignment = match makebox.alignment {
+                ArrayColumnAlign::Centered => Alignment::Centered(content.width),
+                ArrayColumnAlign::Left     => Alignment::Left,
+                ArrayColumnAlign::Right    => Alignment::Right(content.width),
+            };
+            // The content of the box is in text style, and so is the unit of its width
+            content.width = width.scaled(config.with_text());
+        }
+        self.add_node(content.as_node());
+        Ok(())
+    }
+
+    fn line<'a>(&mut self, line: &Line, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
+        // reference rules 9 and 10 from pg 443 of TeXBook
+        // Tight lines (`\overbar`, `\underbar`) are separated from the nodes by their own thickness only
+        match line.placement {
+            LinePlacement::Over => {
+                let contents = layout_nested(&line.inner, config.cramped())?.as_node();
+                let rule_thickness = config.ctx.constants.overbar_rule_thickness.scaled(config);
+                let gap = if line.tight { rule_thickness } else { config.ctx.constants.overbar_vertical_gap.scaled(config) };
+                let rule_ascender  = config.ctx.constants.overbar_extra_ascender.scaled(config);
+
+                self.add_node(vbox![kern!(vert: rule_ascender),
+                                    rule!(width: contents.width, height: rule_thickness),
+                                    kern!(vert: gap),
+                                    contents]);
+            },
+            LinePlacement::Under => {
+                let contents = layout_nested(&line.inner, config)?.as_node();
+                let rule_thickness  = config.ctx.constants.underbar_rule_thickness.scaled(config);
+                let gap = if line.tight { rule_thickness } else { config.ctx.constants.underbar_vertical_gap.scaled(config) };
+                let rule_descender  = config.ctx.constants.underbar_extra_descender.scaled(config);
+
+                // The depth of the contents is not accounted for by the vertical box, hence the extra kern
+                let gap = gap - contents.depth;
+                let offset = gap + rule_thickness + rule_descender;
+                let width  = contents.width;
+                self.add_node(vbox![offset: offset;
+                                    contents,
+                                    kern!(vert: gap),
+                                    rule!(width: width, height: rule_thickness),
+                                    kern!(vert: rule_descender)]);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn cancel<'a>(&mut self, cancel: &Cancel, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
+        // The lines are drawn corner to corner over the contents, which keep their dimensions
+        let contents  = layout_nested(&cancel.inner, config)?.as_node();
+        let thickness = config.ctx.constants.fraction_rule_thickness.scaled(config);
+        let (width, height, depth) = (contents.width, contents.height, contents.depth);
+        let diagonal = |ascending| LayoutNode {
+            width, height, depth,
+            node: LayoutVariant::Diagonal(Diagonal { thickness, ascending }),
+        };
+
+        let mut hbox = builders::HBox::new();
+        hbox.add_node(contents);
+        if matches!(cancel.direction, CancelDirection::Forward | CancelDirection::Cross) {
+            hbox.add_node(kern!(horz: -width));
+            hbox.add_node(diagonal(true));
+        }
+        if matches!(cancel.direction, CancelDirection::Backward | CancelDirection::Cross) {
+            hbox.add_node(kern!(horz: -width));
+            hbox.add_node(diagonal(false));
+        }
+        self.add_node(hbox.build());
+        Ok(())
+    }
+
+    fn boxed<'a>(&mut self, boxed: &Boxed, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
+        // As in amsmath, the contents are in display style
+        let contents  = layout_nested(&boxed.inner, config.with_display())?;
+        let thickness = config.ctx.constants.fraction_rule_thickness.scaled(config);
+        let margin    = FBOX_SEP * Unit::standard_pt_to_px();
+
+        let inner_width = contents.width + margin.scale(2.);
+        let depth = contents.depth - margin - thickness;
+        let total_height = contents.height + margin + thickness - depth;
+
+        // The top and bottom lines of the frame, with the contents in between
+        let mut middle = builders::VBox::new();
+        middle.add_node(rule!(width: inner_width, height: thickness));
+        middle.add_node(kern!(vert: margin));
+        let contents_depth = contents.depth;
+        middle.add_node(hbox![kern!(horz: margin), contents.as_node(), kern!(horz: margin)]);
+        middle.add_node(kern!(vert: margin - contents_depth));
+        middle.add_node(rule!(width: inner_width, height: thickness));
+        middle.set_offset(-depth);
+
+        // The left and right lines of the frame
+        let side = || vbox![offset: -depth; rule!(width: thickness, height: total_height)];
+
+        self.add_node(side());
+        self.add_node(middle.build());
+        self.add_node(side());
+        Ok(())
+    }
+
+    fn extensible_arrow<'a>(&mut self, arrow: &ExtensibleArrow, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
+        // The labels are placed like the limits of an operator ; absent labels take no room
+        let over  = match arrow.over.is_empty() {
+            true  => None,
+            false => Some(layout_nested(&arrow.over, config.superscript_variant())?),
+        };
+        let under = match arrow.under.is_empty() {
+            true  => None,
+            false => Some(layout_nested(&arrow.under, config.subscript_variant())?),
+        };
+
+        // The arrow is stretched to the width of the widest label, plus some padding
+        let labels_width = Unit::max(
+            over.as_ref().map_or(Unit::ZERO, |over| over.width),
+            under.as_ref().map_or(Unit::ZERO, |under| under.width),
+        );
+        let width = labels_width + EXTENSIBLE_ARROW_PADDING.scaled(config);
+        let glyph = config.horz_variant(arrow.symbol.codepoint, config.to_font(width))?.as_layout(config)?;
+
+        // if the font cannot make the arrow long enough, we keep the padding around the labels
+        let width = Unit::max(width, glyph.width);
+        let mut base = Layout::new();
+        base.add_node(glyph);
+        self.operator_limits(base.centered(width), over, under, config)
+    }
+
     fn substack<'a>(&mut self, stack: &Stack, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
         // Don't bother constructing a new node if there is nothing.
         if stack.lines.len() == 0 {
@@ -605,7 +912,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
         let mut widest = Unit::ZERO;
         let mut widest_idx = 0;
         for (n, line) in stack.lines.iter().enumerate() {
-            let line = layout(line, config)?;
+            let line = layout_nested(line, config)?;
             if line.width > widest {
                 widest = line.width;
                 widest_idx = n;
@@ -657,8 +964,9 @@ impl<'f, F : MathFont> Layout<'f, F> {
             }
         }
 
-        // Vertically center the stack to the axis
-        let offset = (vbox.height + vbox.depth).scale(0.5) - config.ctx.constants.axis_height.scaled(config);
+        // Vertically center the stack to the axis, or to the baseline
+        let center = if stack.centered_on_axis { config.ctx.constants.axis_height.scaled(config) } else { Unit::ZERO };
+        let offset = (vbox.height + vbox.depth).scale(0.5) - center;
         vbox.set_offset(offset);
         self.add_node(vbox.build());
         
@@ -666,7 +974,15 @@ impl<'f, F : MathFont> Layout<'f, F> {
     }
 
     fn extended_delimiter<'a>(&mut self, delim: &ExtendedDelimiter, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
-        let ExtendedDelimiter { symbol, height_enclosed_content }  = delim; 
+        let ExtendedDelimiter { symbol, height_enclosed_content, size }  = delim; 
+
+        if let (true, Some(size)) = (config.delimiter_sizes_from_font, size) {
+            if let Some(variant) = config.nth_vert_variant(symbol.codepoint, size.rank())? {
+                let axis = config.ctx.constants.axis_height * config.font_size;
+                self.add_node(variant.as_layout(config)?.centered(axis));
+                return Ok(());
+            }
+        }
 
         let height_enclosed_content = height_enclosed_content.scaled(config);
 
@@ -678,15 +994,25 @@ impl<'f, F : MathFont> Layout<'f, F> {
     fn array<'a>(&mut self, array: &Array, config: LayoutSettings<'a, 'f, F>) -> Result<(), LayoutError> {
         let cell_layout_settings = config.layout_style(array.cell_layout_style);
         let normal_baseline_skip = BASELINE_SKIP; 
-        let strut_height     = normal_baseline_skip.scale(STRUT_HEIGHT) * config.font_size; 
-        let strut_depth      = - normal_baseline_skip.scale(STRUT_DEPTH)  * config.font_size; 
+        let mut strut_height     = normal_baseline_skip.scale(STRUT_HEIGHT) * config.font_size; 
+        let mut strut_depth      = - normal_baseline_skip.scale(STRUT_DEPTH)  * config.font_size; 
 
         let jot = if array.extra_row_sep { JOT } else { Unit::ZERO };
-        let baseline_skip = normal_baseline_skip * config.font_size + jot * Unit::standard_pt_to_px();
-        let line_skip = (LINE_SKIP_ARRAY + jot) * Unit::standard_pt_to_px();
-        let line_skip_limit = (LINE_SKIP_LIMIT_ARRAY + jot)  * Unit::standard_pt_to_px();
-
-        let half_col_sep     = COLUMN_SEP      * Unit::standard_pt_to_px(); 
+        let mut baseline_skip = normal_baseline_skip * config.font_size + jot * Unit::standard_pt_to_px();
+        let mut line_skip = (LINE_SKIP_ARRAY + jot) * Unit::standard_pt_to_px();
+        let mut line_skip_limit = (LINE_SKIP_LIMIT_ARRAY + jot)  * Unit::standard_pt_to_px();
+
+        let mut half_col_sep     = config.column_sep * Unit::standard_pt_to_px();
+
+        // Like amsmath's `smallmatrix`, rows have no strut and are only separated by the line skip when their contents are close
+        if array.tight_spacing {
+            strut_height    = Unit::ZERO;
+            strut_depth     = Unit::ZERO;
+            baseline_skip   = SMALL_MATRIX_BASELINE_SKIP * config.font_size;
+            line_skip       = SMALL_MATRIX_LINE_SKIP * config.font_size;
+            line_skip_limit = line_skip;
+            half_col_sep    = SMALL_MATRIX_COLUMN_SEP * config.font_size;
+        }
         let rule_width       = RULE_WIDTH      * Unit::standard_pt_to_px();
         let double_rule_sep  = DOUBLE_RULE_SEP * Unit::standard_pt_to_px();
 
@@ -695,11 +1021,25 @@ impl<'f, F : MathFont> Layout<'f, F> {
 
         // Don't bother constructing a new node if there is nothing.
         let num_rows = array.rows.len();
-        let num_columns = array.rows.iter().map(Vec::len).max().unwrap_or(0);
-        if num_columns == 0 {
+        // The parser gives the column format at least as many columns as the widest row
+        let num_columns = array.col_format.alignment.len();
+        if num_rows == 0 || num_columns == 0 {
             return Ok(());
         }
 
+        // Cells spanning several columns shift the cells after them to the right
+        // `grid[j][i]` is the cell starting at column `i` in row `j`, if any
+        let grid : Vec<Vec<Option<&CellContent>>> = array.rows.iter().map(|row| {
+            let mut grid_row = Vec::with_capacity(num_columns);
+            for cell in row {
+                grid_row.push(Some(cell));
+                for _ in 1 .. cell_span(cell) {
+                    grid_row.push(None);
+                }
+            }
+            grid_row
+        }).collect();
+
         // -- LAY OUT ALL NODES OF ARRAY
         // Columns of an array may be separated by @-expressions
         // We treat @-expressions are ordinary columns, except for the fact that
@@ -718,13 +1058,17 @@ impl<'f, F : MathFont> Layout<'f, F> {
         let mut columns : Vec<Vec<Layout<'f, F>>> = Vec::with_capacity(num_columns_at);
         let mut n_vertical_bars : Vec<u8> = Vec::with_capacity(num_columns_at + 1);
         let mut current_n_vertical_bars = 0;
+        // index of each real column among all columns (including @-expr columns)
+        let mut real_column_indices : Vec<usize> = Vec::with_capacity(num_columns);
+        // cells from `\multicolumn` are laid out separately: they don't count towards the width of the columns they span
+        let mut spanning_cells : Vec<SpanningCell<'f, F>> = Vec::new();
 
         for separator in &all_separators[0] {
             match separator {
                 ColSeparator::VerticalBars(n_bars) => 
                     current_n_vertical_bars += n_bars,
                 ColSeparator::AtExpression(nodes) => {
-                    let node = layout(&nodes, cell_layout_settings)?;
+                    let node = layout_nested(nodes, cell_layout_settings)?;
                     let mut column = Vec::with_capacity(num_rows);
                     for _ in 0 .. num_rows {
                         column.push(node.clone());
@@ -738,17 +1082,34 @@ impl<'f, F : MathFont> Layout<'f, F> {
         for (i, separators) in all_separators[1..].iter().enumerate() {
             // first comes the real column
             let mut column = Vec::with_capacity(num_rows);
-            for j in 0 .. num_rows {
-                let cell_node = array.rows
-                    .get(j)
-                    .and_then(|row| row.get(i))
+            for (j, grid_row) in grid.iter().enumerate() {
+                let cell_node = grid_row
+                    .get(i)
+                    .copied()
+                    .flatten()
                 ;  
-                let layout = match cell_node {
-                    Some(cell_node) => layout(&cell_node, cell_layout_settings)?,
+                let layout = match cell_node.map(|cell_node| (cell_node, as_multicolumn(cell_node))) {
+                    Some((_, Some(multicolumn))) => {
+                        let content = layout_nested(&multicolumn.inner, cell_layout_settings)?;
+                        // the placeholder only reserves the height and depth of the content
+                        let mut placeholder = Layout::new();
+                        placeholder.height = content.height;
+                        placeholder.depth  = content.depth;
+                        spanning_cells.push(SpanningCell {
+                            row: j,
+                            first_column: i,
+                            last_column: usize::min(i + multicolumn.span, all_separators.len() - 1) - 1,
+                            alignment: multicolumn.alignment,
+                            content,
+                        });
+                        placeholder
+                    },
+                    Some((cell_node, None)) => layout_nested(cell_node, cell_layout_settings)?,
                     None => Layout::new(),
                 };
                 column.push(layout);
             }
+            real_column_indices.push(columns.len());
             columns.push(column);
             alignments.push(Some(array.col_format.alignment[i]));
             n_vertical_bars.push(std::mem::replace(&mut current_n_vertical_bars, 0));
@@ -759,7 +1120,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
                     ColSeparator::VerticalBars(n_bars) => 
                         current_n_vertical_bars += n_bars,
                     ColSeparator::AtExpression(nodes) => {
-                        let node = layout(&nodes, cell_layout_settings)?;
+                        let node = layout_nested(nodes, cell_layout_settings)?;
                         let mut column = Vec::with_capacity(num_rows);
                         for _ in 0 .. num_rows {
                             column.push(node.clone());
@@ -797,25 +1158,81 @@ impl<'f, F : MathFont> Layout<'f, F> {
         }
         debug_assert_eq!(col_widths.len(), num_columns_at);
 
+        // `p{..}` columns have a fixed width, wider content overflows into the next column
+        for (i, width) in array.col_format.widths.iter().enumerate() {
+            if let (Some(width), Some(&i_col)) = (width, real_column_indices.get(i)) {
+                col_widths[i_col] = width.scaled(config);
+            }
+        }
+
+        // Horizontal distance between the end of a column and the start of the next one
+        let column_gap = |i_col : usize| {
+            let mut gap = Unit::ZERO;
+            if alignments[i_col].is_some() && alignments[i_col + 1].is_some() {
+                gap += half_col_sep.scale(2.);
+            }
+            let n_bars = n_vertical_bars[i_col + 1];
+            if n_bars != 0 {
+                gap += rule_width.scale(n_bars as f64) + double_rule_sep.scale((n_bars - 1) as f64);
+            }
+            gap
+        };
+        let span_width = |col_widths : &[Unit<Px>], first : usize, last : usize| {
+            col_widths[first ..= last].iter().cloned().sum::<Unit<Px>>()
+            + (first .. last).map(column_gap).sum::<Unit<Px>>()
+        };
+
+        // If a spanning cell is wider than the columns it spans, the last of these columns is widened
+        for cell in spanning_cells.iter() {
+            let first = real_column_indices[cell.first_column];
+            let last  = real_column_indices[cell.last_column];
+            let available_width = span_width(&col_widths, first, last);
+            if cell.content.width > available_width {
+                col_widths[last] += cell.content.width - available_width;
+            }
+        }
+
+        // The content of a spanning cell is placed in the first column it spans and overflows into the next ones
+        for cell in spanning_cells {
+            let first = real_column_indices[cell.first_column];
+            let last  = real_column_indices[cell.last_column];
+            let mut content = cell.content;
+            content.alignment = match cell.alignment {
+                ArrayColumnAlign::Centered => Alignment::Centered(content.width),
+                ArrayColumnAlign::Left     => Alignment::Left,
+                ArrayColumnAlign::Right    => Alignment::Right(content.width),
+            };
+            content.width = span_width(&col_widths, first, last);
+
+            let mut spanning_layout = Layout::new();
+            spanning_layout.add_node(content.as_node());
+            spanning_layout.width = col_widths[first];
+            columns[first][cell.row] = spanning_layout;
+        }
+
 
         // baseline_dists[0] is dist from top of first line to first baseline (e.g. as though it was preceded by a line of zero-depth)
         // baseline_dists[i] is the dist from row indexed i and row indexed i+1
         let mut baseline_dists = Vec::with_capacity(num_rows);
         let mut prev_depth = Unit::ZERO;
+        // same as `prev_depth`, but without taking the strut into account
+        let mut prev_content_depth = Unit::ZERO;
 
         for i_row in 0 .. num_rows {
-            let mut max_height = strut_height;
-            let mut max_depth  = strut_depth;
+            let mut content_height = Unit::ZERO;
+            let mut content_depth  = Unit::ZERO;
 
             for column in columns.iter() {
                 let cell = &column[i_row];
 
-                max_height = Unit::max(max_height, cell.height);
-                max_depth  = Unit::min(max_depth, cell.depth); // depth are negative
+                content_height = Unit::max(content_height, cell.height);
+                content_depth  = Unit::min(content_depth, cell.depth); // depth are negative
             }
+            let max_height = Unit::max(strut_height, content_height);
+            let max_depth  = Unit::min(strut_depth,  content_depth);
 
             let box_separation = max_height - prev_depth;
-            let baseline_dist = 
+            let mut baseline_dist = 
                 if i_row == 0 {
                     box_separation
                 }
@@ -826,8 +1243,16 @@ impl<'f, F : MathFont> Layout<'f, F> {
                     baseline_skip
                 }
             ;
+            // Extra space requested with `\\[dimen]` after the previous row ; 
+            // if negative, rows are brought closer together, but never so close that their contents overlap
+            let extra_space = i_row.checked_sub(1)
+                .and_then(|i_prev_row| array.row_spacing.get(i_prev_row).copied().flatten());
+            if let Some(extra_space) = extra_space {
+                baseline_dist = Unit::max(baseline_dist + extra_space.scaled(config), content_height - prev_content_depth);
+            }
             baseline_dists.push(baseline_dist);
             prev_depth = max_depth;
+            prev_content_depth = content_depth;
         }
         let last_depth = prev_depth;
         debug_assert_eq!(baseline_dists.len(), num_rows);
@@ -955,7 +1380,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
                             height - config.ctx.constants.delimiter_short_fall * config.font_size);
 
         if let Some(left) = array.left_delimiter {
-            let left = config.ctx.vert_variant(left.codepoint, config.to_font(clearance))?
+            let left = config.vert_variant(left.codepoint, config.to_font(clearance))?
                 .as_layout(config)?
                 .centered(axis);
             hbox.add_node(left);
@@ -963,7 +1388,7 @@ impl<'f, F : MathFont> Layout<'f, F> {
 
         hbox.add_node(vbox);
         if let Some(right) = array.right_delimiter {
-            let right = config.ctx.vert_variant(right.codepoint, config.to_font(clearance))?
+            let right = config.vert_variant(right.codepoint, config.to_font(clearance))?
                 .as_layout(config)?
                 .centered(axis);
             hbox.add_node(right);
@@ -983,6 +1408,15 @@ struct RuleMeasurements {
     double_rule_sep: Unit<Px>
 }
 
+/// A cell created by `\multicolumn{..}{..}{..}`, spanning the real columns `first_column ..= last_column`
+struct SpanningCell<'f, F> {
+    row:          usize,
+    first_column: usize,
+    last_column:  usize,
+    alignment:    ArrayColumnAlign,
+    content:      Layout<'f, F>,
+}
+
 #[inline]
 fn draw_vertical_bars<F>(hbox: &mut builders::HBox<F>, n_bars: u8, rule_measurements: RuleMeasurements) {
     if n_bars != 0 {
@@ -1028,7 +1462,7 @@ fn extend_delimiter<'a, 'f, F : MathFont>(
         let clearance = config.to_font(clearance_px);
 
         Ok(
-            config.ctx
+            config
             .vert_variant(symbol.codepoint, clearance)?
             .as_layout(config)?
             .centered(axis)
@@ -22,15 +22,22 @@ mod convert;
 pub mod engine;
 pub mod spacing;
 pub mod constants;
+mod diff;
+
+pub use self::diff::LayoutDiff;
+pub use self::convert::AsLayoutNode;
 
 use crate::font::common::GlyphId;
 use crate::parser::color::RGBA;
-use crate::font::FontContext;
+use crate::font::{FontContext, MathFont, VariantGlyph};
+use crate::error::FontError;
+use self::engine::LookupCounters;
 use std::ops::Deref;
+use std::sync::atomic::Ordering;
 use std::fmt;
 use std::collections::BTreeMap;
 use crate::dimensions::Unit;
-use crate::dimensions::units::{Px, Em, FontSize, Ratio};
+use crate::dimensions::units::{Px, Em, Pt, FUnit, FontSize, Ratio};
 
 /// Contains a set of [`LayoutNode`s](crate::layout::LayoutNode) that defines the position of glyphs and rules (i.e. filled rectangles) and certain measurements useful for rendering.
 /// It serves as input to [`Renderer::render`](crate::render::Renderer::render).
@@ -50,6 +57,11 @@ pub struct Layout<'f, F> {
     pub offset:    Unit<Px>,
     /// How to horizontally lay out children nodes
     pub alignment: Alignment,
+    /// Height of the math axis above the baseline, i.e. the line on which fraction bars and operators are centered (cf [`Renderer::draw_baseline_and_axis`](crate::Renderer::draw_baseline_and_axis))
+    pub axis_height: Unit<Px>,
+    /// Indices in `contents` where the formula may be broken into several lines, cf [`Layout::wrapped`].
+    /// These are the positions of the relations (e.g. `=`, `<`) at the top level of the formula.
+    pub break_points: Vec<usize>,
 }
 
 impl<'f, F> Clone for Layout<'f, F> {
@@ -60,7 +72,9 @@ impl<'f, F> Clone for Layout<'f, F> {
             height:    self.height,
             depth:     self.depth,
             offset:    self.offset,
-            alignment: self.alignment 
+            alignment: self.alignment,
+            axis_height: self.axis_height,
+            break_points: self.break_points.clone(),
         }
     }
 }
@@ -75,6 +89,8 @@ impl<'f, F> Default for Layout<'f, F> {
             depth:     Unit::ZERO,
             offset:    Unit::ZERO,
             alignment: Alignment::default(),
+            axis_height: Unit::ZERO,
+            break_points: Vec::new(),
         }
     }
 }
@@ -129,7 +145,97 @@ impl<'f, F> Layout<'f, F> {
         self
     }
 
+    /// Enlarges the layout so that its dimensions are at least `dimensions` (e.g. the [`Layout::size`] of another formula), leaving its contents in place.
+    /// The width is extended to the right, the height upwards and the depth downwards.
+    /// This allows aligning formulas of different sizes on a common box, as `\phantom` does within a formula.
+    pub fn with_reserved_box(mut self, dimensions: LayoutDimensions) -> Layout<'f, F> {
+        self.width  = Unit::max(self.width,  Unit::new(dimensions.width));
+        self.height = Unit::max(self.height, Unit::new(dimensions.height));
+        self.depth  = Unit::min(self.depth,  Unit::new(dimensions.depth));
+        self
+    }
+
+    /// Breaks the formula into several lines, each at most `max_width` wide if possible, and stacks them vertically.
+    /// Lines are only broken before the top-level relations of the formula (cf [`Layout::break_points`]), filling each line with as many pieces as fit.
+    /// Continuation lines are indented so that their leading relation lines up with the first relation of the formula, 
+    /// unless that relation lies beyond the middle of `max_width`.
+    ///
+    /// The baseline of the first line remains the baseline of the layout. 
+    /// If the formula fits in `max_width` or cannot be broken, the layout is returned unchanged.
+    pub fn wrapped(&self, max_width: Unit<Px>) -> Layout<'f, F> {
+        if self.width <= max_width || self.break_points.is_empty() {
+            return self.clone();
+        }
+
+        // Split the contents at break points into pieces which may not be broken further
+        let mut pieces = Vec::with_capacity(self.break_points.len() + 1);
+        let mut start = 0;
+        for &end in self.break_points.iter().chain(std::iter::once(&self.contents.len())) {
+            let piece = &self.contents[start .. end];
+            let width = piece.iter().map(|node| node.width).fold(Unit::ZERO, |a, b| a + b);
+            pieces.push((piece, width));
+            start = end;
+        }
+
+        let indent = pieces[0].1;
+        let indent = if indent.scale(2.) < max_width { indent } else { Unit::ZERO };
+
+        // Fill lines greedily
+        let mut lines : Vec<Vec<LayoutNode<'f, F>>> = Vec::new();
+        let mut current = Vec::new();
+        let mut current_width = Unit::ZERO;
+        for (piece, width) in pieces {
+            if !current.is_empty() && current_width + width > max_width {
+                lines.push(std::mem::take(&mut current));
+                current.push(kern!(horz: indent));
+                current_width = indent;
+            }
+            current.extend(piece.iter().cloned());
+            current_width += width;
+        }
+        lines.push(current);
+
+        if lines.len() == 1 {
+            return self.clone();
+        }
+
+        // Stack the lines, leaving a gap of the axis height between the bottom of each line and the top of the next.
+        // A vertical box only moves down by the height of each node, so the depth of the previous line goes in the kern.
+        let mut vbox = builders::VBox::new();
+        let mut first_line_height = Unit::ZERO;
+        let mut previous_depth = Unit::ZERO;
+        for (i, mut line) in lines.into_iter().enumerate() {
+            // spaces at the end of a line are not needed, except those of `\hspace*{..}` which are laid out in a box
+            while matches!(line.last(), Some(LayoutNode { node: LayoutVariant::Kern, .. })) {
+                line.pop();
+            }
+            let mut hbox = builders::HBox::new();
+            for node in line {
+                hbox.add_node(node);
+            }
+            let hbox = hbox.build();
+            if i == 0 {
+                first_line_height = hbox.height;
+            }
+            else {
+                vbox.add_node(kern!(vert: self.axis_height - previous_depth));
+            }
+            previous_depth = hbox.depth;
+            vbox.add_node(hbox);
+        }
+        let offset = vbox.height - first_line_height;
+        vbox.set_offset(offset);
+
+        let mut layout = Layout::new();
+        layout.add_node(vbox.build());
+        layout.axis_height = self.axis_height;
+        layout
+    }
+
     /// Returns [`LayoutDimensions`] dimensions for the given layout, in pixels. 
+    /// 
+    /// The layout of an empty formula (e.g. `""` or `{}`) has all dimensions equal to zero. 
+    /// Backends which cannot create empty surfaces may use [`LayoutDimensions::at_least`] to get usable dimensions.
     pub fn size(&self) -> LayoutDimensions {
         LayoutDimensions {
             width  : self.width.unitless(Px),
@@ -138,30 +244,57 @@ impl<'f, F> Layout<'f, F> {
         }
     }
 
+    /// Returns the number of glyphs drawn by the layout.
+    pub fn glyph_count(&self) -> usize {
+        self.contents.iter().map(LayoutNode::glyph_count).sum()
+    }
+
+    /// Returns true if rendering the layout draws nothing (no glyph, no rule), e.g. the layout of `\phantom{..}`.
+    pub fn is_empty(&self) -> bool {
+        self.contents.iter().all(LayoutNode::is_empty)
+    }
+
 }
 
 impl<'f, F> Layout<'f, F> {
 
-    fn is_symbol(&self) -> Option<LayoutGlyph<'f, F>> {
-        if self.contents.len() != 1 {
-            return None;
-        }
-        self.contents[0].is_symbol()
+    /// If the layout consists of a single glyph, possibly wrapped in boxes or colors, returns that glyph (cf [`is_symbol`]).
+    pub fn is_symbol(&self) -> Option<LayoutGlyph<'f, F>> {
+        is_symbol(&self.contents)
+    }
+
+    /// Returns the glyph that the layout consists of, if it is made of a single glyph (e.g. the layout of `x` but not of `x+y`).
+    /// This is the same as [`Layout::is_symbol`].
+    pub fn single_glyph(&self) -> Option<LayoutGlyph<'f, F>> {
+        self.is_symbol()
     }
 }
 
 /// A struct containing various measures for a Layout in pixel units.
 // Should not be used internally, the unitless types are "unsafe"
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct LayoutDimensions {
-    /// distance from baseline to top of the formula (positive if top of formula above baseline, typically positive)
+    /// width of formula
     pub width  : f64,
-    /// distance from baseline to bottom of the formula (negative if bottom of formula below baseline, typically negative)
+    /// distance from baseline to top of the formula (positive if top of formula above baseline, typically positive)
     pub height : f64,
-    /// width of formula
+    /// distance from baseline to bottom of the formula (negative if bottom of formula below baseline, typically negative)
     pub depth  : f64,
 }
 
+impl LayoutDimensions {
+    /// Returns dimensions with a width and a total height (`height - depth`) of at least `min_size`, 
+    /// e.g. so as to create a surface of positive size for an empty formula.
+    /// If needed, the height is increased, so that the baseline remains at the same distance from the bottom.
+    pub fn at_least(self, min_size: f64) -> Self {
+        Self {
+            width:  self.width.max(min_size),
+            height: self.height.max(self.depth + min_size),
+            depth:  self.depth,
+        }
+    }
+}
+
 /// A sub-part of the layout hierarchy: can contain other nodes and may be contained in other nodes.
 pub struct LayoutNode<'f, F> {
     /// Type of node
@@ -174,6 +307,32 @@ pub struct LayoutNode<'f, F> {
     pub depth: Unit<Px>,
 }
 
+impl<'f, F> LayoutNode<'f, F> {
+    /// Number of glyphs drawn by this node and its children
+    fn glyph_count(&self) -> usize {
+        match self.node {
+            LayoutVariant::Grid(ref grid)          => grid.contents.values().map(LayoutNode::glyph_count).sum(),
+            LayoutVariant::HorizontalBox(ref hbox) => hbox.contents.iter().map(LayoutNode::glyph_count).sum(),
+            LayoutVariant::VerticalBox(ref vbox)   => vbox.contents.iter().map(LayoutNode::glyph_count).sum(),
+            LayoutVariant::Color(ref clr)          => clr.inner.iter().map(LayoutNode::glyph_count).sum(),
+            LayoutVariant::Glyph(_)                => 1,
+            LayoutVariant::Rule | LayoutVariant::Diagonal(_) | LayoutVariant::Kern => 0,
+        }
+    }
+
+    /// Whether this node and its children draw nothing
+    fn is_empty(&self) -> bool {
+        match self.node {
+            LayoutVariant::Grid(ref grid)          => grid.contents.values().all(LayoutNode::is_empty),
+            LayoutVariant::HorizontalBox(ref hbox) => hbox.contents.iter().all(LayoutNode::is_empty),
+            LayoutVariant::VerticalBox(ref vbox)   => vbox.contents.iter().all(LayoutNode::is_empty),
+            LayoutVariant::Color(ref clr)          => clr.inner.iter().all(LayoutNode::is_empty),
+            LayoutVariant::Glyph(_) | LayoutVariant::Rule | LayoutVariant::Diagonal(_) => false,
+            LayoutVariant::Kern                    => true,
+        }
+    }
+}
+
 impl<'f, F> Clone for LayoutNode<'f, F> {
     fn clone(&self) -> Self {
         Self {
@@ -199,6 +358,8 @@ pub enum LayoutVariant<'f, F> {
     Color(ColorChange<'f, F>),
     /// A filled rectangle
     Rule,
+    /// A straight line joining two opposite corners of the node
+    Diagonal(Diagonal),
     /// Some (possibly negative) spacing
     Kern,
 }
@@ -212,11 +373,21 @@ impl<'f, F> Clone for LayoutVariant<'f, F> {
             LayoutVariant::Glyph(glyph)           => LayoutVariant::Glyph(glyph.clone()),
             LayoutVariant::Color(color_change)    => LayoutVariant::Color(color_change.clone()),
             LayoutVariant::Rule                   => LayoutVariant::Rule,
+            LayoutVariant::Diagonal(diagonal)     => LayoutVariant::Diagonal(*diagonal),
             LayoutVariant::Kern                   => LayoutVariant::Kern,
         }
     }
 }
 
+/// A line drawn across the box of the node, e.g. to strike through some nodes (`\cancel{..}`)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Diagonal {
+    /// thickness of the line
+    pub thickness: Unit<Px>,
+    /// whether the line goes from the bottom left to the top right corner, rather than from the top left to the bottom right corner
+    pub ascending: bool,
+}
+
 /// All children of this node will use the [ColorChange::color] as a fill color
 pub struct ColorChange<'f, F> {
     /// Color to use
@@ -427,6 +598,7 @@ impl<'f, F> fmt::Debug for LayoutNode<'f, F> {
             LayoutVariant::VerticalBox(ref vb) => write!(f, "VBox({:?})", vb.contents),
             LayoutVariant::Glyph(ref gly) => write!(f, "Glyph({:?})", gly),
             LayoutVariant::Rule => write!(f, "Rule()"),
+            LayoutVariant::Diagonal(ref diagonal) => write!(f, "{:?}", diagonal),
             LayoutVariant::Kern => {
                 let kern = if self.width.is_zero() {
                     self.height
@@ -486,7 +658,8 @@ impl<'f, F> LayoutNode<'f, F> {
         self
     }
 
-    fn is_symbol(&self) -> Option<LayoutGlyph<'f, F>> {
+    /// If the node is a glyph, or a box or color containing only a glyph, returns that glyph.
+    pub fn is_symbol(&self) -> Option<LayoutGlyph<'f, F>> {
         match self.node {
             LayoutVariant::Glyph(gly) => Some(gly),
             LayoutVariant::HorizontalBox(ref hb) => is_symbol(&hb.contents),
@@ -497,7 +670,8 @@ impl<'f, F> LayoutNode<'f, F> {
     }
 }
 
-/// Determines if a set of nodes is a singleton set containing a symbol node
+/// Determines if a set of nodes is a singleton set containing a symbol node, and if so returns the glyph of that symbol.
+/// Boxes and colors containing a single node are looked through, so that e.g. the contents of the layout of `{x}` or `\color{red}{x}` count as a symbol.
 pub fn is_symbol<'a, 'b: 'a, F>(contents: &'a [LayoutNode<'b, F>]) -> Option<LayoutGlyph<'b, F>> {
     if contents.len() != 1 {
         return None;
@@ -615,6 +789,14 @@ pub struct LayoutSettings<'a, 'f, F> {
     pub style: Style,
     /// Font size in pixels per em (this is private: all user-facing interfaces should use a more conventional pt . em-1 unit)
     font_size: Unit<Ratio<Px, Em>>,
+    /// Half the space between two columns of an array (LaTeX's `\arraycolsep`)
+    column_sep: Unit<Pt>,
+    /// Whether `\big`, `\Big`, etc. pick the font's n-th enlarged delimiter rather than one fitting a fixed height
+    delimiter_sizes_from_font: bool,
+    /// Whether fractions without a style of their own (e.g. `\frac`, unlike `\tfrac`) are laid out in display style, like `\dfrac`
+    always_display_fractions: bool,
+    /// Where to count the variant lookups of the layout, if anywhere (cf [`engine::layout_with_stats`])
+    lookups: Option<&'a LookupCounters>,
 }
 
 
@@ -624,6 +806,10 @@ impl<'a, 'f, F> Clone for LayoutSettings<'a, 'f, F> {
             ctx :       self.ctx,
             font_size : self.font_size,
             style :     self.style.clone(),
+            column_sep: self.column_sep,
+            delimiter_sizes_from_font: self.delimiter_sizes_from_font,
+            always_display_fractions: self.always_display_fractions,
+            lookups:    self.lookups,
         }
     }
 }
@@ -642,6 +828,10 @@ impl<'a, 'f, F> LayoutSettings<'a, 'f, F> {
             ctx,
             font_size: Self::DEFAULT_FONT_SIZE * Unit::standard_pt_to_px().lift(),
             style : Style::default(),
+            column_sep: constants::COLUMN_SEP,
+            delimiter_sizes_from_font: false,
+            always_display_fractions: false,
+            lookups: None,
         }
     }
 
@@ -652,6 +842,28 @@ impl<'a, 'f, F> LayoutSettings<'a, 'f, F> {
         self
     }
 
+    /// Sets half the horizontal space between two columns of arrays and matrices, unit is pt (this is LaTeX's `\arraycolsep`).
+    /// Defaults to [`constants::COLUMN_SEP`].
+    pub fn column_sep(mut self, column_sep: f64) -> Self {
+        self.column_sep = Unit::<Pt>::new(column_sep);
+        self
+    }
+
+    /// If set, `\big`, `\Big`, `\bigg` and `\Bigg` use the first, second, third and fourth enlarged version of the delimiter listed in the font,
+    /// as TeX does with its discrete delimiter sizes, instead of the smallest version taller than a fixed multiple of [`constants::BIG_HEIGHT`].
+    /// Fonts which list no enlarged version of a delimiter fall back to the latter. Defaults to false.
+    pub fn delimiter_sizes_from_font(mut self, enabled: bool) -> Self {
+        self.delimiter_sizes_from_font = enabled;
+        self
+    }
+
+    /// If set, fractions which do not specify a style (e.g. `\frac`, `\binom`, but not `\tfrac`) are laid out in display style, as if they were written with `\dfrac`,
+    /// even in an inline formula or in scripts. Defaults to false.
+    pub fn always_display_fractions(mut self, enabled: bool) -> Self {
+        self.always_display_fractions = enabled;
+        self
+    }
+
     /// Sets the starting style of the layout (e.g. text style, display style). Cf [`Style`] for explanation of what a style is.
     pub fn layout_style(mut self, style : Style) -> Self {
         self.style = style;
@@ -709,14 +921,105 @@ impl<'a, 'f, F> LayoutSettings<'a, 'f, F> {
     }
 }
 
+impl<'a, 'f, F : MathFont> LayoutSettings<'a, 'f, F> {
+    /// Same as [`FontContext::vert_variant`], counting the lookup if the layout is gathering statistics
+    fn vert_variant(&self, codepoint: char, height: Unit<FUnit>) -> Result<VariantGlyph, FontError> {
+        let (variant, cached) = self.ctx.cached_vert_variant(codepoint, height)?;
+        if let Some(lookups) = self.lookups {
+            lookups.vert_variants.fetch_add(1, Ordering::Relaxed);
+            if cached {
+                lookups.vert_variant_cache_hits.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Ok(variant)
+    }
+
+    /// Same as [`FontContext::nth_vert_variant`], counting the lookup if the layout is gathering statistics
+    fn nth_vert_variant(&self, codepoint: char, n: usize) -> Result<Option<VariantGlyph>, FontError> {
+        if let Some(lookups) = self.lookups {
+            lookups.vert_variants.fetch_add(1, Ordering::Relaxed);
+        }
+        self.ctx.nth_vert_variant(codepoint, n)
+    }
+
+    /// Same as [`FontContext::horz_variant`], counting the lookup if the layout is gathering statistics
+    fn horz_variant(&self, codepoint: char, width: Unit<FUnit>) -> Result<VariantGlyph, FontError> {
+        self.count_horz_variant();
+        self.ctx.horz_variant(codepoint, width)
+    }
+
+    /// Same as [`FontContext::wide_accent_variant`], counting the lookup if the layout is gathering statistics
+    fn wide_accent_variant(&self, codepoint: char, width: Unit<FUnit>) -> Result<VariantGlyph, FontError> {
+        self.count_horz_variant();
+        self.ctx.wide_accent_variant(codepoint, width)
+    }
+
+    fn count_horz_variant(&self) {
+        if let Some(lookups) = self.lookups {
+            lookups.horz_variants.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{dimensions::{Unit, units::{FUnit, Ratio, FontSize, Px, Em}}, parser::parse, font::{backend::ttf_parser::TtfMathFont, FontContext}, layout::{LayoutSettings, engine::layout, Style}};
+    use crate::{dimensions::{Unit, units::{FUnit, Ratio, FontSize, Px, Pt, Em}}, parser::parse, font::{backend::ttf_parser::TtfMathFont, FontContext, MathFont}, layout::{Layout, LayoutNode, LayoutGlyph, LayoutDimensions, LayoutSettings, LayoutVariant, Alignment, engine::layout, Style}};
+    use std::sync::OnceLock;
+
+    const XITS_FONT_BYTES : &[u8] = include_bytes!("../../resources/XITS_Math.otf");
+
+    /// The XITS Math font, which most tests are laid out with
+    fn xits_font() -> TtfMathFont<'static> {
+        TtfMathFont::new(ttf_parser::Face::parse(XITS_FONT_BYTES, 0).unwrap()).unwrap()
+    }
+
+    /// A font context for XITS Math, with caches of its own
+    fn xits_context() -> FontContext<'static, TtfMathFont<'static>> {
+        static XITS : OnceLock<TtfMathFont<'static>> = OnceLock::new();
+        FontContext::new(XITS.get_or_init(xits_font))
+    }
+
+    fn layout_of<'a, 'f : 'a, F : MathFont>(formula : &str, config : LayoutSettings<'a, 'f, F>) -> Layout<'f, F> {
+        layout(&parse(formula).unwrap(), config).unwrap()
+    }
+
+    fn size_of<'a, 'f : 'a, F : MathFont>(formula : &str, config : LayoutSettings<'a, 'f, F>) -> LayoutDimensions {
+        layout_of(formula, config).size()
+    }
+
+    fn width_of<'a, 'f : 'a, F : MathFont>(formula : &str, config : LayoutSettings<'a, 'f, F>) -> f64 {
+        size_of(formula, config).width
+    }
+
+    /// The nodes of a layout and, recursively, those of the boxes, grids and colored groups in it, each before its contents
+    fn all_nodes<'l, 'f, F>(nodes : &'l [LayoutNode<'f, F>]) -> Vec<&'l LayoutNode<'f, F>> {
+        let mut all = Vec::new();
+        for node in nodes {
+            all.push(node);
+            match node.node {
+                LayoutVariant::HorizontalBox(ref hbox) => all.extend(all_nodes(&hbox.contents)),
+                LayoutVariant::VerticalBox(ref vbox)   => all.extend(all_nodes(&vbox.contents)),
+                LayoutVariant::Grid(ref grid)          => all.extend(grid.contents.values().flat_map(|cell| all_nodes(std::slice::from_ref(cell)))),
+                LayoutVariant::Color(ref clr)          => all.extend(all_nodes(&clr.inner)),
+                _ => (),
+            }
+        }
+        all
+    }
+
+    /// The glyphs of a layout in the order they are drawn, with the nodes holding them
+    fn glyphs<'l, 'f, F>(layout : &'l Layout<'f, F>) -> Vec<(&'l LayoutNode<'f, F>, &'l LayoutGlyph<'f, F>)> {
+        all_nodes(&layout.contents).into_iter()
+            .filter_map(|node| match node.node {
+                LayoutVariant::Glyph(ref glyph) => Some((node, glyph)),
+                _ => None,
+            })
+            .collect()
+    }
 
 
     #[test]
     fn dimension_size_px_character() {
-        const XITS_FONT_BYTES : &[u8] = include_bytes!("../../resources/XITS_Math.otf");
         // Making sure the file is the same the measures below were taken from
         let signature = XITS_FONT_BYTES.iter().cloned().fold(0_u8, |a, b| a.wrapping_add(b));
         assert_eq!(signature, 198);
@@ -727,9 +1030,7 @@ mod tests {
 
         // we
         let nodes = parse("1").unwrap();
-        let font = ttf_parser::Face::parse(XITS_FONT_BYTES, 0).unwrap();
-        let font = TtfMathFont::new(font).unwrap();
-        let ctx = FontContext::new(&font);
+        let ctx = xits_context();
 
         // 10pt layout
         let font_size = Unit::<FontSize>::new(10.);
@@ -754,4 +1055,1613 @@ mod tests {
         );
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn nested_matrix() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+
+        let contents = size_of(r"\begin{matrix}a&b\\c&d\end{matrix}", config);
+        let inner = layout_of(r"\begin{pmatrix}a&b\\c&d\end{pmatrix}", config);
+        let outer = layout_of(r"\begin{matrix}\begin{pmatrix}a&b\\c&d\end{pmatrix}\end{matrix}", config);
+
+        // The single cell of the outer matrix is sized by the inner matrix, delimiters included
+        assert!(outer.size().width  >= inner.size().width);
+        assert_close!(outer.size().height, inner.size().height, 1e-5);
+        assert_close!(outer.size().depth,  inner.size().depth,  1e-5);
+
+        // The delimiters are drawn at the same size as around the inner matrix alone, which covers its contents
+        let delimiters = |layout| {
+            let glyphs = glyphs(layout);
+            assert_eq!(glyphs.len(), 6);
+            [glyphs[0], glyphs[5]].map(|(node, glyph)| (glyph.gid, (node.height - node.depth).unitless(Px)))
+        };
+        assert_eq!(delimiters(&outer), delimiters(&inner));
+        for (_, height) in delimiters(&outer) {
+            assert!(height >= contents.height - contents.depth);
+        }
+    }
+
+    #[test]
+    fn delimiters_size_to_vphantom() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+
+        let phantom  = size_of(r"\left(\vphantom{\frac ab}x\right)", config);
+        let fraction = size_of(r"\left(\frac ab\right)", config);
+        let plain    = size_of(r"\left(x\right)", config);
+
+        assert_close!(phantom.height, fraction.height, 1e-5);
+        assert_close!(phantom.depth,  fraction.depth,  1e-5);
+        assert!(phantom.height > plain.height);
+        // the phantom has no width
+        assert_close!(size_of(r"\vphantom{\frac ab}", config).width, 0., 1e-5);
+    }
+
+    #[test]
+    fn column_sep_widens_matrix() {
+        let ctx = xits_context();
+        let nodes = parse(r"\begin{matrix}a&b&c\end{matrix}").unwrap();
+
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let default = layout(&nodes, config).unwrap().size();
+        let wide    = layout(&nodes, config.column_sep(10.)).unwrap().size();
+
+        // 3 columns, hence 6 half column separations, each 5pt wider
+        let expected = Unit::<Pt>::new(6. * 5.) * Unit::standard_pt_to_px();
+        assert_close!(wide.width - default.width, expected.unitless(Px), 1e-5);
+        assert_close!(wide.height, default.height, 1e-5);
+    }
+
+    #[test]
+    fn multicolumn_spans_columns() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // a narrow spanning cell does not widen the columns it spans
+        let narrow = size_of(r"\begin{array}{ccc}\multicolumn{2}{c}{a}&c\\a&b&c\end{array}", config);
+        let plain  = size_of(r"\begin{array}{ccc}a&b&c\\a&b&c\end{array}", config);
+        assert_close!(narrow.width, plain.width, 1e-5);
+        assert_close!(narrow.height, plain.height, 1e-5);
+
+        // a wide spanning cell takes up as much room as a single column with the same content
+        let wide   = size_of(r"\begin{array}{ccc}\multicolumn{2}{c}{abcdefghij}&c\\a&b&c\end{array}", config);
+        let merged = size_of(r"\begin{array}{cc}abcdefghij&c\\a&c\end{array}", config);
+        assert_close!(wide.width, merged.width, 1e-5);
+    }
+
+    #[test]
+    fn ragged_arrays_are_laid_out() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // by default, an extra cell goes in a centered column of its own
+        let extra = size_of(r"\begin{array}{c}a&b\end{array}", config);
+        let full  = size_of(r"\begin{array}{cc}a&b\end{array}", config);
+        assert_close!(extra.width, full.width, 1e-5);
+        assert_close!(
+            size_of(r"\begin{array}{l}a\\bbb&c\end{array}", config).width,
+            size_of(r"\begin{array}{lc}a\\bbb&c\end{array}", config).width,
+            1e-5
+        );
+
+        // and missing cells are left empty, the column keeping its place
+        let missing = size_of(r"\begin{array}{ccc}a&b\end{array}", config);
+        assert!(missing.width > full.width);
+    }
+
+    #[test]
+    fn empty_column_repetition() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // `*{0}{..}` adds no column, so that the cells go in implicit columns
+        assert_close!(
+            size_of(r"\begin{array}{*{0}{c}}x\end{array}", config).width,
+            size_of(r"\begin{array}{c}x\end{array}", config).width,
+            1e-5
+        );
+        assert_eq!(size_of(r"\begin{array}{*{0}{c}}\end{array}", config).width, 0.);
+    }
+
+    #[test]
+    fn negative_row_spacing() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |length : Unit<Em>| (length * config.font_size).unitless(Px);
+
+        let normal  = size_of(r"\begin{matrix}a\\b\end{matrix}", config);
+        let tighter = size_of(r"\begin{matrix}a\\[-0.3em]b\end{matrix}", config);
+        let looser  = size_of(r"\begin{matrix}a\\[0.3em]b\end{matrix}", config);
+        let total_height = |size : &super::LayoutDimensions| size.height - size.depth;
+
+        assert_close!(total_height(&tighter), total_height(&normal) - px(Unit::new(0.3)), 1e-5);
+        assert_close!(total_height(&looser),  total_height(&normal) + px(Unit::new(0.3)), 1e-5);
+
+        // rows may touch but never overlap: `b` sits right below `a`
+        let a = size_of(r"a", config);
+        let b = size_of(r"b", config);
+        let clamped = size_of(r"\begin{matrix}a\\[-10em]b\end{matrix}", config);
+        let single_row = size_of(r"\begin{matrix}a\end{matrix}", config);
+        assert_close!(total_height(&clamped) - total_height(&single_row), b.height - a.depth, 1e-5);
+    }
+
+    #[test]
+    fn smallint_is_not_enlarged() {
+        let ctx = xits_context();
+        let display = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+        let text    = display.layout_style(Style::Text);
+
+        let int      = size_of(r"\int", display);
+        let smallint = size_of(r"\smallint", display);
+        let text_int = size_of(r"\int", text);
+
+        assert!(int.height - int.depth > smallint.height - smallint.depth);
+        assert_close!(smallint.height, text_int.height, 1e-5);
+        assert_close!(smallint.depth,  text_int.depth,  1e-5);
+
+        // Limits and scripts are placed as for an integral sign in text style
+        let smallint = size_of(r"\smallint_0^1", display);
+        let text_int = size_of(r"\int_0^1", text);
+        assert_close!(smallint.width,  text_int.width,  1e-5);
+        assert_close!(smallint.height, text_int.height, 1e-5);
+    }
+
+    #[test]
+    fn layout_stats() {
+        use crate::layout::engine::layout_with_stats;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let (_, stats) = layout_with_stats(&parse(r"\frac{a}{b}").unwrap(), config).unwrap();
+        assert_eq!(stats.glyphs, 2);
+        assert_eq!(stats.vert_variants, 0);
+        assert_eq!(stats.horz_variants, 0);
+
+        let (_, stats) = layout_with_stats(&parse(r"\left(\widehat{x}\right)").unwrap(), config).unwrap();
+        assert_eq!(stats.vert_variants, 2);
+        assert_eq!(stats.horz_variants, 1);
+    }
+
+    #[test]
+    fn nested_over_and_underline() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |length : Unit<Em>| (length * config.font_size).unitless(Px);
+
+        let x      = size_of(r"x", config);
+        let nested = size_of(r"\overline{\underline{x}}", config);
+        let constants = &ctx.constants;
+
+        // bar above: gap, rule and extra ascender on top of the glyph
+        let above = px(constants.overbar_vertical_gap + constants.overbar_rule_thickness + constants.overbar_extra_ascender);
+        assert_close!(nested.height, x.height + above, 1e-5);
+
+        // bar below: gap, rule and extra descender under the glyph
+        let below = px(constants.underbar_vertical_gap + constants.underbar_rule_thickness + constants.underbar_extra_descender);
+        assert_close!(nested.depth, x.depth - below, 1e-5);
+
+        assert_close!(nested.width, x.width, 1e-5);
+    }
+
+    #[test]
+    fn font_size_at_style() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(10.);
+        let base_size = (Unit::<FontSize>::new(10.) * Unit::standard_pt_to_px().lift()).to_unitless();
+
+        assert_close!(config.font_size_at(Style::Display), base_size, 1e-9);
+        assert_close!(config.font_size_at(Style::TextCramped), base_size, 1e-9);
+        assert_close!(config.font_size_at(Style::Script), base_size * ctx.constants.script_percent_scale_down, 1e-9);
+        assert_close!(config.font_size_at(Style::ScriptScript), base_size * ctx.constants.script_script_percent_scale_down, 1e-9);
+    }
+
+    #[test]
+    fn text_superscript_is_raised_and_reduced() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |length : Unit<Em>| (length * config.font_size).unitless(Px);
+
+        let four   = size_of(r"\text{4}", config);
+        let th     = size_of(r"\text{th}", config);
+        let fourth = size_of(r"\text{4\textsuperscript{th}}", config);
+        let constants = &ctx.constants;
+
+        // "th" is shifted up by the superscript shift, at script size
+        let shift_up = px(constants.superscript_shift_up);
+        assert_close!(fourth.height, th.height * constants.script_percent_scale_down + shift_up, 1e-5);
+        assert!(fourth.height > size_of(r"\text{4th}", config).height);
+
+        assert_close!(fourth.width, four.width + th.width * constants.script_percent_scale_down, 1e-5);
+    }
+
+    #[test]
+    fn glyph_count_and_emptiness() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let phantom = layout_of(r"\phantom{abc}", config);
+        assert_eq!(phantom.glyph_count(), 0);
+        assert!(phantom.is_empty());
+        assert_close!(phantom.size().width, layout_of(r"abc", config).size().width, 1e-5);
+
+        let abc = layout_of(r"abc", config);
+        assert_eq!(abc.glyph_count(), 3);
+        assert!(!abc.is_empty());
+
+        // `\hphantom` only reserves the width of its content, `\vphantom` only its height and depth
+        let hphantom = layout_of(r"\hphantom{\frac{abc}{d}}", config);
+        let vphantom = layout_of(r"\vphantom{\frac{abc}{d}}", config);
+        let fraction = layout_of(r"\frac{abc}{d}", config).size();
+        assert_eq!(hphantom.glyph_count(), 0);
+        assert_close!(hphantom.size().width,  fraction.width, 1e-5);
+        assert_close!(hphantom.size().height, 0., 1e-5);
+        assert_close!(hphantom.size().depth,  0., 1e-5);
+        assert_eq!(vphantom.glyph_count(), 0);
+        assert_close!(vphantom.size().width,  0., 1e-5);
+        assert_close!(vphantom.size().height, fraction.height, 1e-5);
+        assert_close!(vphantom.size().depth,  fraction.depth,  1e-5);
+
+        // a fraction bar is drawn, though it is not a glyph
+        let frac = layout_of(r"\frac{\phantom{a}}{\phantom{b}}", config);
+        assert_eq!(frac.glyph_count(), 0);
+        assert!(!frac.is_empty());
+    }
+
+    #[test]
+    fn missing_styled_glyph_falls_back_to_unstyled() {
+        use crate::font::{MathFont, Glyph, Constants, VariantGlyph, common::GlyphId, kerning::Corner};
+
+        /// XITS, with the double-struck capital X removed
+        struct NoDoubleStruckX<'a>(TtfMathFont<'a>);
+
+        impl<'a> MathFont for NoDoubleStruckX<'a> {
+            fn glyph_index(&self, codepoint: char) -> Option<GlyphId> {
+                if codepoint == '𝕏' { None } else { self.0.glyph_index(codepoint) }
+            }
+            fn glyph_from_gid<'f>(&'f self, glyph_id : GlyphId) -> Result<Glyph<'f, Self>, crate::error::FontError> {
+                let Glyph { gid, bbox, advance, lsb, italics, attachment, .. } = self.0.glyph_from_gid(glyph_id)?;
+                Ok(Glyph { font: self, gid, bbox, advance, lsb, italics, attachment })
+            }
+            fn kern_for(&self, glyph_id : GlyphId, height : Unit<FUnit>, side : Corner) -> Option<Unit<FUnit>> { self.0.kern_for(glyph_id, height, side) }
+            fn italics(&self, glyph_id : GlyphId) -> i16 { self.0.italics(glyph_id) }
+            fn attachment(&self, glyph_id : GlyphId) -> i16 { self.0.attachment(glyph_id) }
+            fn constants(&self, font_units_to_em: Unit<Ratio<Em, FUnit>>) -> Constants { self.0.constants(font_units_to_em) }
+            fn font_units_to_em(&self) -> Unit<Ratio<Em, FUnit>> { self.0.font_units_to_em() }
+            fn horz_variant(&self, gid: GlyphId, width: Unit<FUnit>) -> VariantGlyph { self.0.horz_variant(gid, width) }
+            fn vert_variant(&self, gid: GlyphId, height: Unit<FUnit>) -> VariantGlyph { self.0.vert_variant(gid, height) }
+        }
+
+        let font = NoDoubleStruckX(xits_font());
+        let ctx = FontContext::new(&font);
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        assert_eq!(crate::font::unstyle_symbol('𝕏'), Some('X'));
+        assert_eq!(crate::font::unstyle_symbol('ℝ'), Some('R'));
+        assert_eq!(crate::font::unstyle_symbol('X'), None);
+
+        let fallback = layout_of(r"\mathbb{X}", config);
+        let plain    = layout_of(r"\mathrm{X}", config);
+        assert_eq!(fallback.glyph_count(), 1);
+        assert_close!(fallback.size().width, plain.size().width, 1e-5);
+
+        // other letters are unaffected
+        let y = layout_of(r"\mathbb{Y}", config);
+        let expected = ctx.glyph('𝕐').unwrap().advance * (config.font_size / ctx.units_per_em).unlift();
+        assert_close!(y.size().width, expected.unitless(Px), 1e-5);
+    }
+
+    #[test]
+    fn atom_type_override_changes_spacing() {
+        use crate::parser::{parse_with_settings, ParserSettings, macros::CommandCollection};
+        use crate::font::TexSymbolType;
+        use super::spacing::Spacing;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |length : Unit<Em>| (length * config.font_size).unitless(Px);
+
+        let commands = CommandCollection::new();
+        let settings = ParserSettings::new().atom_type('/', TexSymbolType::Binary);
+        let ordinary = layout_of(r"a/b", config).size();
+        let binary   = layout(&parse_with_settings(r"a/b", &commands, &settings).unwrap(), config).unwrap().size();
+
+        // medium space on both sides of the binary operator
+        assert_close!(binary.width - ordinary.width, 2. * px(Spacing::Medium.to_length()), 1e-5);
+
+        // overrides also apply inside groups
+        let grouped = layout(&parse_with_settings(r"{a/b}", &commands, &settings).unwrap(), config).unwrap().size();
+        assert_close!(grouped.width, binary.width, 1e-5);
+    }
+
+    #[test]
+    fn overline_uses_overbar_constants() {
+        let face = ttf_parser::Face::parse(XITS_FONT_BYTES, 0).unwrap();
+        let math_constants = face.tables().math.unwrap().constants.unwrap();
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |length : Unit<Em>| (length * config.font_size).unitless(Px);
+        let constants = &ctx.constants;
+
+        // constants are read from the MATH table
+        let gap_funit = Unit::<FUnit>::new(math_constants.overbar_vertical_gap().value.into());
+        assert_close!((constants.overbar_vertical_gap * ctx.units_per_em).unitless(FUnit), gap_funit.unitless(FUnit), 1e-9);
+
+        let x = size_of(r"x", config);
+        let overline = size_of(r"\overline{x}", config);
+        let above = px(constants.overbar_vertical_gap + constants.overbar_rule_thickness + constants.overbar_extra_ascender);
+        assert_close!(overline.height, x.height + above, 1e-5);
+    }
+
+    #[test]
+    fn vphantom_and_mathstrut_equalize_heights() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // `a\vphantom{X}` must be as high and as deep as `aX` in every context, while taking up the width of `a` only
+        let contexts = [
+            (r"\frac{#}{c}",                       "fraction numerator"),
+            (r"\frac{c}{#}",                       "fraction denominator"),
+            (r"\sqrt{#}",                          "radical"),
+            (r"\begin{matrix}#\\b\end{matrix}",    "array row"),
+            (r"\left(#\right)",                    "delimiters"),
+        ];
+        for (context, name) in contexts {
+            let phantom = size_of(&context.replace('#', r"a\vphantom{\frac{g}{h}}"), config);
+            let visible = size_of(&context.replace('#', r"a\frac{g}{h}"), config);
+            let alone   = size_of(&context.replace('#', r"a"), config);
+            assert!((phantom.height - visible.height).abs() < 1e-5, "height in {}", name);
+            assert!((phantom.depth  - visible.depth).abs()  < 1e-5, "depth in {}", name);
+            assert!(phantom.height - phantom.depth > alone.height - alone.depth, "{}", name);
+        }
+
+        // fractions and radicals do not widen because of the phantom
+        assert_close!(size_of(r"\frac{a\vphantom{bbbb}}{c}", config).width, size_of(r"\frac{a}{c}", config).width, 1e-5);
+        assert_close!(size_of(r"\sqrt{a\vphantom{bbbb}}", config).width, size_of(r"\sqrt{a}", config).width, 1e-5);
+
+        // `\mathstrut` gives radicals with different contents the same size
+        let strut = size_of(r"\mathstrut", config);
+        let paren = size_of(r"(", config);
+        assert_close!(strut.width, 0., 1e-5);
+        assert_close!(strut.height, paren.height, 1e-5);
+        assert_close!(strut.depth,  paren.depth,  1e-5);
+        let sqrt_a = size_of(r"\sqrt{\mathstrut a}", config);
+        let sqrt_b = size_of(r"\sqrt{\mathstrut b}", config);
+        assert_close!(sqrt_a.height, sqrt_b.height, 1e-5);
+        assert_close!(sqrt_a.depth,  sqrt_b.depth,  1e-5);
+    }
+
+    #[test]
+    fn vert_variants_are_cached_per_font() {
+        use crate::layout::engine::layout_with_stats;
+
+        const FIRA_FONT_BYTES : &[u8] = include_bytes!("../../resources/FiraMath_Regular.otf");
+        let fira = TtfMathFont::new(ttf_parser::Face::parse(FIRA_FONT_BYTES, 0).unwrap()).unwrap();
+        let xits_ctx = xits_context();
+        let fira_ctx = FontContext::new(&fira);
+
+        let nodes = parse(r"\left(\frac{a}{b}\right)").unwrap();
+        let config = LayoutSettings::new(&xits_ctx).font_size(12.);
+
+        let (first, stats) = layout_with_stats(&nodes, config).unwrap();
+        assert_eq!(stats.vert_variants, 2);
+        assert_eq!(stats.vert_variant_cache_hits, 0);
+
+        let (second, stats) = layout_with_stats(&nodes, config).unwrap();
+        assert_eq!(stats.vert_variants, 2);
+        assert_eq!(stats.vert_variant_cache_hits, 2);
+        assert_eq!(first.height, second.height);
+        assert_eq!(first.depth,  second.depth);
+        assert_eq!(first.width,  second.width);
+
+        // A context for another font does not share the cache
+        let config = LayoutSettings::new(&fira_ctx).font_size(12.);
+        let (_, stats) = layout_with_stats(&nodes, config).unwrap();
+        assert_eq!(stats.vert_variant_cache_hits, 0);
+
+        // Clearing the caches does not change the layout
+        fira_ctx.clear_caches();
+        let (cleared, stats) = layout_with_stats(&nodes, config).unwrap();
+        assert_eq!(stats.vert_variant_cache_hits, 0);
+        assert!(cleared.diff(&layout(&nodes, config).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn vert_variant_cache_is_bounded() {
+        use crate::font::VERT_VARIANT_CACHE_CAPACITY;
+
+        const FIRA_FONT_BYTES : &[u8] = include_bytes!("../../resources/FiraMath_Regular.otf");
+        let fira = TtfMathFont::new(ttf_parser::Face::parse(FIRA_FONT_BYTES, 0).unwrap()).unwrap();
+        let ctx = FontContext::new(&fira);
+        let height = |i : usize| Unit::<FUnit>::new(1000. + i as f64);
+
+        for i in 0 .. VERT_VARIANT_CACHE_CAPACITY {
+            assert!(!ctx.cached_vert_variant('(', height(i)).unwrap().1);
+        }
+        assert!(ctx.cached_vert_variant('(', height(0)).unwrap().1);
+
+        // a full cache is emptied before a new variant is added
+        assert!(!ctx.cached_vert_variant('(', height(VERT_VARIANT_CACHE_CAPACITY)).unwrap().1);
+        assert!(!ctx.cached_vert_variant('(', height(0)).unwrap().1);
+        assert!(ctx.cached_vert_variant('(', height(VERT_VARIANT_CACHE_CAPACITY)).unwrap().1);
+    }
+
+    #[test]
+    fn layout_stats_only_count_their_own_layout() {
+        use crate::layout::engine::layout_with_stats;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let delimited = parse(r"\left(\widehat{x}\right)").unwrap();
+        let fraction  = parse(r"\frac{a}{b}").unwrap();
+
+        // Lookups of an earlier layout, with or without statistics, are not counted
+        layout(&delimited, config).unwrap();
+        let (_, stats) = layout_with_stats(&fraction, config).unwrap();
+        assert_eq!((stats.vert_variants, stats.horz_variants), (0, 0));
+        let (_, stats) = layout_with_stats(&delimited, config).unwrap();
+        assert_eq!((stats.vert_variants, stats.horz_variants), (2, 1));
+        let (_, stats) = layout_with_stats(&fraction, config).unwrap();
+        assert_eq!((stats.vert_variants, stats.horz_variants), (0, 0));
+
+    }
+
+    #[test]
+    fn layout_stats_of_concurrent_layouts() {
+        use crate::layout::engine::layout_with_stats;
+        use crate::font::{MathFont, Glyph, Constants, VariantGlyph, common::GlyphId, kerning::Corner};
+        use std::sync::Barrier;
+
+        /// XITS, where looking up a vertical variant waits until another thread does the same
+        struct Synchronized<'a>(TtfMathFont<'a>, Barrier);
+
+        impl<'a> MathFont for Synchronized<'a> {
+            fn glyph_index(&self, codepoint: char) -> Option<GlyphId> { self.0.glyph_index(codepoint) }
+            fn glyph_from_gid<'f>(&'f self, glyph_id : GlyphId) -> Result<Glyph<'f, Self>, crate::error::FontError> {
+                let Glyph { gid, bbox, advance, lsb, italics, attachment, .. } = self.0.glyph_from_gid(glyph_id)?;
+                Ok(Glyph { font: self, gid, bbox, advance, lsb, italics, attachment })
+            }
+            fn kern_for(&self, glyph_id : GlyphId, height : Unit<FUnit>, side : Corner) -> Option<Unit<FUnit>> { self.0.kern_for(glyph_id, height, side) }
+            fn italics(&self, glyph_id : GlyphId) -> i16 { self.0.italics(glyph_id) }
+            fn attachment(&self, glyph_id : GlyphId) -> i16 { self.0.attachment(glyph_id) }
+            fn constants(&self, font_units_to_em: Unit<Ratio<Em, FUnit>>) -> Constants { self.0.constants(font_units_to_em) }
+            fn font_units_to_em(&self) -> Unit<Ratio<Em, FUnit>> { self.0.font_units_to_em() }
+            fn horz_variant(&self, gid: GlyphId, width: Unit<FUnit>) -> VariantGlyph { self.0.horz_variant(gid, width) }
+            fn vert_variant(&self, gid: GlyphId, height: Unit<FUnit>) -> VariantGlyph {
+                self.1.wait();
+                self.0.vert_variant(gid, height)
+            }
+        }
+
+        let font = Synchronized(xits_font(), Barrier::new(2));
+        let ctx = FontContext::new(&font);
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // Both layouts are halfway through when either looks up its delimiters: each only counts its own lookups
+        let parens   = parse(r"\left(\frac{a}{b}\right)").unwrap();
+        let brackets = parse(r"\left[\frac{a}{b}\right]").unwrap();
+        let (parens_stats, brackets_stats) = std::thread::scope(|scope| {
+            let parens   = scope.spawn(|| layout_with_stats(&parens, config).unwrap().1);
+            let brackets = scope.spawn(|| layout_with_stats(&brackets, config).unwrap().1);
+            (parens.join().unwrap(), brackets.join().unwrap())
+        });
+        assert_eq!(parens_stats.vert_variants, 2);
+        assert_eq!(brackets_stats.vert_variants, 2);
+    }
+
+    #[test]
+    fn cached_vert_variants_are_those_of_the_font() {
+        let ctx = xits_context();
+        let gid = ctx.font.glyph_index('(').unwrap();
+
+        // Heights fall on both sides of the heights of the replacement glyphs, which are whole numbers of font units,
+        // and the second pass is answered from the cache
+        for _ in 0 .. 2 {
+            for half_units in 1000 .. 8000 {
+                let height = Unit::<FUnit>::new(half_units as f64 / 2.);
+                assert_eq!(
+                    format!("{:?}", ctx.vert_variant('(', height).unwrap()),
+                    format!("{:?}", ctx.font.vert_variant(gid, height)),
+                    "variant for height {:?}", height,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn big_delimiters_from_font_variants() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).delimiter_sizes_from_font(true);
+
+        // XITS lists several enlarged parentheses of distinct sizes
+        let paren = ctx.font.glyph_index('(').unwrap();
+        let variants : Vec<_> = ctx.font.vert_variant_glyphs(paren).into_iter().filter(|&gid| gid != paren).collect();
+        assert!(variants.len() >= 2);
+
+        let glyph_total_height = |gid| {
+            let glyph = ctx.glyph_from_gid(gid).unwrap();
+            ((glyph.height() - glyph.depth()) * (config.font_size / ctx.units_per_em).unlift()).unitless(Px)
+        };
+        let total_height = |formula : &str| {
+            let layout = layout_of(formula, config);
+            (layout.height - layout.depth).unitless(Px)
+        };
+
+        assert_close!(total_height(r"\big("), glyph_total_height(variants[0]), 1e-5);
+        assert_close!(total_height(r"\Big("), glyph_total_height(variants[1]), 1e-5);
+        assert!(total_height(r"\big(") < total_height(r"\Big("));
+
+        // the variants listed by the font count as lookups of vertical variants
+        let (_, stats) = crate::layout::engine::layout_with_stats(&parse(r"\big( x \Big)").unwrap(), config).unwrap();
+        assert_eq!(stats.vert_variants, 2);
+    }
+
+    #[test]
+    fn text_hyphen_is_not_math_minus() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let gids = |formula : &str| glyphs(&layout_of(formula, config)).iter().map(|(_, glyph)| glyph.gid).collect::<Vec<_>>();
+
+        let text_gids = gids(r"\text{a-b}");
+        let math_gids = gids(r"a-b");
+        assert_eq!(text_gids.len(), 3);
+        assert_eq!(math_gids.len(), 3);
+
+        assert_ne!(text_gids[1], math_gids[1]);
+        assert_eq!(Some(text_gids[1]), ctx.font.glyph_index('-'));
+        assert_eq!(Some(math_gids[1]), ctx.font.glyph_index('\u{2212}'));
+    }
+
+    #[test]
+    fn missing_codepoint_error() {
+        use crate::error::{FontError, LayoutError};
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let error = match layout(&parse(r"\text{a中b}").unwrap(), config) {
+            Err(error) => error,
+            Ok(_) => panic!("XITS Math unexpectedly has a glyph for '中'"),
+        };
+        match error {
+            LayoutError::Font(FontError::MissingGlyphCodepoint(codepoint)) => assert_eq!(codepoint, '中'),
+            _ => panic!("unexpected error {:?}", error),
+        }
+        assert_eq!(error.font_error().and_then(FontError::missing_codepoint), Some('中'));
+        assert_eq!(error.font_error().and_then(FontError::missing_gid), None);
+    }
+
+    #[test]
+    fn spacing_commands_in_text() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |length : Unit<Em>| (length * config.font_size).unitless(Px);
+
+        let ab = size_of(r"\text{ab}", config);
+        assert_close!(size_of(r"\text{a\,b}", config).width,    ab.width + px(Unit::new(3. / 18.)), 1e-5);
+        assert_close!(size_of(r"\text{a\quad b}", config).width, ab.width + px(Unit::new(1.)),       1e-5);
+
+        // the space is proportional to the font size
+        let script_ab = size_of(r"x^{\text{ab}}", config).width;
+        assert_close!(
+            size_of(r"x^{\text{a\,b}}", config).width, 
+            script_ab + px(Unit::new(3. / 18.)) * ctx.constants.script_percent_scale_down, 
+            1e-5
+        );
+    }
+
+    #[test]
+    fn over_and_underset() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // An empty annotation changes nothing
+        assert_eq!(size_of(r"\overset{}{x}", config), size_of(r"x", config));
+        assert_eq!(size_of(r"a\underset{}{=}b", config), size_of(r"a=b", config));
+
+        // An annotation only extends the side it is on
+        let x = size_of(r"x", config);
+        let overset  = size_of(r"\overset{a}{x}", config);
+        let underset = size_of(r"\underset{a}{x}", config);
+        assert!(overset.height > x.height);
+        assert_close!(overset.depth, x.depth, 1e-5);
+        assert!(underset.depth < x.depth);
+        assert_close!(underset.height, x.height, 1e-5);
+
+        // Relations remain relations
+        let eq = size_of(r"a=b", config);
+        let stacked_eq = size_of(r"a\overset{!}{=}b", config);
+        assert_close!(stacked_eq.width - size_of(r"\overset{!}{=}", config).width, eq.width - size_of(r"=", config).width, 1e-5);
+        let defined_eq = size_of(r"a\overset{\text{def}}{=}b", config);
+        assert_close!(defined_eq.width - size_of(r"\overset{\text{def}}{=}", config).width, eq.width - size_of(r"=", config).width, 1e-5);
+
+        // So do binary operators
+        let plus = size_of(r"a+b", config);
+        let stacked_plus = size_of(r"a\underset{n}{+}b", config);
+        assert_close!(stacked_plus.width - size_of(r"\underset{n}{+}", config).width, plus.width - size_of(r"+", config).width, 1e-5);
+
+        // `\stackrel` spaces its result as a relation, whatever the base
+        let rel_spacing = eq.width - size_of(r"=", config).width - size_of(r"ab", config).width;
+        assert!(rel_spacing > 0.);
+        for base in ["=", "x", "+"] {
+            let stacked = size_of(&format!(r"a\stackrel{{?}}{{{}}}b", base), config);
+            let spacing = stacked.width - size_of(&format!(r"\stackrel{{?}}{{{}}}", base), config).width - size_of(r"ab", config).width;
+            assert_close!(spacing, rel_spacing, 1e-5);
+        }
+    }
+
+    #[test]
+    fn overbrace_takes_limits() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let brace = size_of(r"\overbrace{x}", config);
+        let both  = size_of(r"\overbrace{x}^{a}_{b}", config);
+        let above = size_of(r"\overbrace{x}^{a}", config);
+        let below = size_of(r"\overbrace{x}_{b}", config);
+
+        // 'a' sits above the brace, 'b' below 'x', not beside them
+        let script_a = size_of(r"\scriptstyle a", config);
+        assert!(above.height > brace.height + script_a.height - script_a.depth);
+        assert!(below.depth < brace.depth);
+        assert!(both.width < brace.width + script_a.width);
+
+        assert_close!(both.height, above.height, 1e-5);
+        assert_close!(both.depth,  below.depth,  1e-5);
+        assert_close!(above.depth, brace.depth,  1e-5);
+        assert_close!(below.height, brace.height, 1e-5);
+    }
+
+    #[test]
+    fn mbox_ignores_script_size() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let abc = size_of(r"\text{abc}", config);
+        assert_eq!(size_of(r"\mbox{abc}", config), abc);
+
+        // Both scripts are placed the same way, only the size of "abc" differs
+        let difference = size_of(r"x^{\mbox{abc}}", config).width - size_of(r"x^{\text{abc}}", config).width;
+        assert_close!(difference, abc.width * (1. - ctx.constants.script_percent_scale_down), 1e-5);
+    }
+
+    #[test]
+    fn makebox_has_given_width() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |l: Unit<Em>| (l * config.font_size).unitless(Px);
+
+        let x_width = layout_of(r"\text{x}", config).width;
+        let makebox = layout_of(r"\makebox[3em]{x}", config);
+        assert_close!(makebox.width.unitless(Px), px(Unit::new(3.)), 1e-5);
+
+        // the content keeps its own width, and is centered within the box
+        let LayoutVariant::HorizontalBox(ref hbox) = makebox.contents[0].node else { panic!("\\makebox should produce a box") };
+        assert_eq!(hbox.alignment, Alignment::Centered(x_width));
+
+        let left = layout_of(r"\makebox[3em][l]{x}", config);
+        let LayoutVariant::HorizontalBox(ref hbox) = left.contents[0].node else { panic!("\\makebox should produce a box") };
+        assert_eq!(hbox.alignment, Alignment::Left);
+
+        // without a width, the box is as wide as its content
+        assert_eq!(layout_of(r"\makebox{x}", config).width, x_width);
+    }
+
+    #[test]
+    fn phantom_operator_takes_limits() {
+        use crate::font::TexSymbolType;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+
+        let phantom = parse(r"\phantom{\sum}").unwrap();
+        assert_eq!(phantom[0].atom_type(), TexSymbolType::Operator(true));
+
+        // The limits are placed above and below the invisible operator, which has the size of the visible one
+        let with_limits = size_of(r"\sum_0^1", config);
+        assert_eq!(size_of(r"\phantom{\sum}_0^1", config), with_limits);
+        assert!(with_limits.height > size_of(r"\sum", config).height);
+        assert!(with_limits.width  < size_of(r"\sum\nolimits_0^1", config).width);
+        // only the limits are drawn
+        assert_eq!(layout_of(r"\phantom{\sum}_0^1", config).glyph_count(), 2);
+    }
+
+    #[test]
+    fn infix_fraction_with_delimiters() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let binom = layout_of(r"\binom{a}{b}", config);
+        let infix = layout_of(r"{a \atopwithdelims () b}", config);
+        assert_eq!(infix.size(), binom.size());
+        // two parentheses around a and b
+        assert_eq!(infix.glyph_count(), 4);
+        assert!(infix.width > layout_of(r"{a \atop b}", config).width);
+    }
+
+    #[test]
+    fn text_shrinks_in_scripts() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let sizes = |formula : &str| glyphs(&layout_of(formula, config)).iter().map(|(_, glyph)| glyph.size.unitless(Px)).collect::<Vec<_>>();
+
+        let em = (Unit::<Em>::new(1.) * config.font_size).unitless(Px);
+        assert_eq!(sizes(r"\text{n}"), vec![em]);
+
+        // "n" in the superscript has the size of the script, like any math content there, unlike the base "x"
+        let script = sizes(r"x^{\text{n}}");
+        assert_eq!(script.len(), 2);
+        assert_close!(script[0], em, 1e-9);
+        assert_close!(script[1], em * ctx.constants.script_percent_scale_down, 1e-9);
+        assert_eq!(sizes(r"x^{n}")[1], script[1]);
+    }
+
+    #[test]
+    fn bars_are_tighter_than_lines() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // The rule is drawn with the same thickness, but closer to "x"
+        let underbar  = size_of(r"\underbar{x}", config);
+        let underline = size_of(r"\underline{x}", config);
+        assert_eq!(underbar.height, underline.height);
+        assert!(underbar.depth > underline.depth);
+
+        let overbar  = size_of(r"\overbar{x}", config);
+        let overline = size_of(r"\overline{x}", config);
+        assert_eq!(overbar.depth, overline.depth);
+        assert!(overbar.height < overline.height);
+        assert!(overbar.height > size_of("x", config).height);
+    }
+
+    #[test]
+    fn diff_points_at_moved_glyph() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let original = layout_of(r"a+b", config);
+        assert!(original.diff(&original.clone()).is_empty());
+
+        let mut moved = original.clone();
+        match moved.contents[2].node {
+            LayoutVariant::Glyph(ref mut glyph) => glyph.offset = Unit::new(1.5),
+            _ => panic!("expected a glyph"),
+        }
+        let diffs = original.diff(&moved);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].path, vec![2]);
+        assert_eq!(diffs[0].field, "offset");
+        assert_eq!(diffs[0].right, "1.5");
+
+        // Different structures are reported once
+        let other = layout_of(r"a+b+c", config);
+        assert!(original.diff(&other).iter().any(|diff| diff.path.is_empty() && diff.field == "contents"));
+    }
+
+    #[test]
+    fn layout_single_frac_node() {
+        use super::{LayoutVariant, engine::layout_node};
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let frac = parse(r"\frac{a}{b}").unwrap();
+        assert_eq!(frac.len(), 1);
+        let nodes = layout_node(&frac[0], config).unwrap();
+
+        // null delimiter space, the stacked numerator and denominator, null delimiter space
+        assert_eq!(nodes.len(), 3);
+        assert!(matches!(nodes[0].node, LayoutVariant::Kern));
+        assert!(matches!(nodes[1].node, LayoutVariant::VerticalBox(_)));
+        assert!(matches!(nodes[2].node, LayoutVariant::Kern));
+
+        let width : f64 = nodes.iter().map(|node| node.width.unitless(Px)).sum();
+        assert_close!(width, layout(&frac, config).unwrap().width.unitless(Px), 1e-9);
+    }
+
+    #[test]
+    fn angle_brackets_grow_around_fraction() {
+        use crate::font::TexSymbolType;
+        use crate::parser::{nodes::ParseNode, symbols::Symbol};
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+
+        let nodes = parse(r"\left\langle\frac{\frac ab}{c}\right\rangle").unwrap();
+        match nodes.as_slice() {
+            [ParseNode::Delimited(delimited)] => assert_eq!(delimited.delimiters(), &[
+                Symbol { codepoint: '\u{27E8}', atom_type: TexSymbolType::Open },
+                Symbol { codepoint: '\u{27E9}', atom_type: TexSymbolType::Close },
+            ]),
+            _ => panic!("expected a delimited group"),
+        }
+
+        let delimited = layout(&nodes, config).unwrap();
+        let fraction  = layout_of(r"\frac{\frac ab}{c}", config);
+        let gids : Vec<_> = glyphs(&delimited).iter().map(|(_, glyph)| glyph.gid).collect();
+
+        // Larger variants of the brackets are used, which cover the fraction
+        assert_ne!(gids.first(), Some(&ctx.glyph('\u{27E8}').unwrap().gid));
+        assert_ne!(gids.last(),  Some(&ctx.glyph('\u{27E9}').unwrap().gid));
+        assert!(delimited.height >= fraction.height);
+        assert!(delimited.depth  <= fraction.depth);
+    }
+
+    #[test]
+    fn accent_does_not_widen_base() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // "y" is placed at the same distance from "x", whether "x" is decorated or not
+        for decorated in [r"\hat{x}", r"\tilde{x}", r"\widehat{x}", r"\overline{x}", r"\underline{x}", r"\vec{l}"] {
+            let base = if decorated.ends_with("{l}") { "l" } else { "x" };
+            assert_close!(size_of(&format!("{}y", decorated), config).width, size_of(&format!("{}y", base), config).width, 1e-9);
+            assert_close!(size_of(decorated, config).width, size_of(base, config).width, 1e-9);
+        }
+    }
+
+    #[test]
+    fn constants_in_px_scale_with_font_size() {
+        let ctx = xits_context();
+
+        let small = ctx.constants_in_px(16.);
+        let large = ctx.constants_in_px(32.);
+        assert_close!(small.axis_height.unitless(Px), 16. * ctx.constants().axis_height.to_unitless(), 1e-9);
+        assert_close!(large.axis_height.unitless(Px),             2. * small.axis_height.unitless(Px),             1e-9);
+        assert_close!(large.fraction_rule_thickness.unitless(Px), 2. * small.fraction_rule_thickness.unitless(Px), 1e-9);
+        assert_close!(large.subscript_shift_down.unitless(Px),    2. * small.subscript_shift_down.unitless(Px),    1e-9);
+        // ratios do not depend on the font size
+        assert_eq!(large.script_percent_scale_down, small.script_percent_scale_down);
+        assert!(ctx.units_per_em().to_unitless() > 0.);
+    }
+
+    #[test]
+    fn thin_space_before_text_operator() {
+        use super::spacing::Spacing;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |length : Unit<Em>| (length * config.font_size).unitless(Px);
+
+        // In "2x", the ordinary symbols are set solid ; in "2\sin x", the operator is preceded by a thin space
+        assert_close!(width_of("2x", config) - width_of("2", config) - width_of("x", config), 0., 1e-5);
+        assert_close!(width_of(r"2\sin x", config) - width_of("2", config) - width_of(r"\sin x", config), px(Spacing::Thin.to_length()), 1e-5);
+    }
+
+    #[test]
+    fn delimited_group_is_spaced_as_inner_atom() {
+        use super::spacing::Spacing;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let px = |spacing : Spacing| (spacing.to_length() * config.font_size).unitless(Px);
+        let delimited = width_of(r"\left(b\right)", config);
+
+        // cf TeXbook, p. 170: thin space between an inner atom and an ordinary atom, on either side
+        assert_close!(width_of(r"1\left(b\right)2", config) - width_of("1", config) - delimited - width_of("2", config), 2. * px(Spacing::Thin), 1e-5);
+        // thick space next to a relation, medium space next to a binary operator
+        assert_close!(width_of(r"=\left(b\right)", config) - width_of("=", config) - delimited, px(Spacing::Thick), 1e-5);
+        assert_close!(width_of(r"\left(b\right)=", config) - width_of("=", config) - delimited, px(Spacing::Thick), 1e-5);
+        assert_close!(width_of(r"1+\left(b\right)", config) - width_of("1+", config) - delimited, px(Spacing::Medium), 1e-5);
+        // no space between an opening symbol and an inner atom
+        assert_close!(width_of(r"(\left(b\right)", config) - width_of("(", config) - delimited, 0., 1e-5);
+    }
+
+    #[test]
+    fn always_display_fractions() {
+        let ctx = xits_context();
+        let inline  = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Text);
+        let display = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+
+        let formula = r"\frac{a}{b}";
+        let displayed = size_of(formula, display);
+        assert!(size_of(formula, inline).height < displayed.height);
+
+        let forced = size_of(formula, inline.always_display_fractions(true));
+        assert_close!(forced.width,  displayed.width,  1e-9);
+        assert_close!(forced.height, displayed.height, 1e-9);
+        assert_close!(forced.depth,  displayed.depth,  1e-9);
+
+        // fractions with a style of their own are left as is
+        assert_close!(size_of(r"\tfrac{a}{b}", inline.always_display_fractions(true)).height, size_of(r"\tfrac{a}{b}", inline).height, 1e-9);
+    }
+
+    #[test]
+    fn relation_chain_is_spaced_uniformly() {
+        use super::spacing::Spacing;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let thick = (Spacing::Thick.to_length() * config.font_size).unitless(Px);
+
+        // space on both sides of each '='
+        assert_close!(width_of("a=b", config) - width_of("a", config) - width_of("=b", config), thick, 1e-5);
+        assert_close!(width_of("=b", config) - width_of("=", config) - width_of("b", config), thick, 1e-5);
+        assert_close!(width_of("a=b=c", config) - width_of("a=b", config) - width_of("=c", config), thick, 1e-5);
+        assert_close!(width_of("=c", config) - width_of("=", config) - width_of("c", config), thick, 1e-5);
+        // adjacent relations are not separated
+        assert_close!(width_of("a==b", config) - width_of("a=b", config), width_of("=", config), 1e-5);
+    }
+
+    #[test]
+    fn style_node_shrinks_following_siblings() {
+        use crate::parser::nodes::ParseNode;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let glyph_sizes = |nodes : &[ParseNode]| {
+            glyphs(&layout(nodes, config).unwrap()).iter().map(|(_, glyph)| glyph.size.unitless(Px)).collect::<Vec<_>>()
+        };
+
+        let a = parse("a").unwrap().remove(0);
+        let b = parse("b").unwrap().remove(0);
+        let c = parse("c").unwrap().remove(0);
+        let normal = glyph_sizes(&[a.clone()])[0];
+        let script = normal * ctx.constants.script_percent_scale_down;
+
+        let sizes = glyph_sizes(&[a.clone(), ParseNode::Style(Style::Script), b.clone(), c.clone()]);
+        assert_close!(sizes[0], normal, 1e-9);
+        assert_close!(sizes[1], script, 1e-9);
+        assert_close!(sizes[2], script, 1e-9);
+
+        // The style change does not leak out of its group
+        let sizes = glyph_sizes(&[ParseNode::Group(vec![ParseNode::Style(Style::Script), a]), b]);
+        assert_close!(sizes[0], script, 1e-9);
+        assert_close!(sizes[1], normal, 1e-9);
+    }
+
+    #[test]
+    fn fraction_delimiters_of_fixed_size() {
+        use crate::parser::nodes::{ParseNode, GenFraction, BarThickness, MathStyle, ArrayColumnAlign};
+        use crate::parser::DelimiterSize;
+        use crate::parser::symbols::Symbol;
+        use crate::font::TexSymbolType;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+
+        let fraction = |numerator : &str, delimiter_size : Option<DelimiterSize>| ParseNode::GenFraction(GenFraction {
+            numerator:       parse(numerator).unwrap(),
+            denominator:     parse("b").unwrap(),
+            bar_thickness:   BarThickness::Default,
+            left_delimiter:  Some(Symbol { codepoint: '(', atom_type: TexSymbolType::Open }),
+            right_delimiter: Some(Symbol { codepoint: ')', atom_type: TexSymbolType::Close }),
+            delimiter_size,
+            style:             MathStyle::NoChange,
+            numerator_style:   MathStyle::NoChange,
+            denominator_style: MathStyle::NoChange,
+            numerator_alignment: ArrayColumnAlign::Centered,
+        });
+        // total height of the delimiter opening the fraction
+        let opening_height = |node : ParseNode| {
+            let layout = layout(&[node], config).unwrap();
+            let delimiter = &layout.contents[0];
+            (delimiter.height - delimiter.depth).unitless(Px)
+        };
+        let big = layout_of(r"\big(", config).size();
+        let big = big.height - big.depth;
+
+        // Fixed-size delimiters have the height of `\big(`, whatever the fraction
+        assert_close!(opening_height(fraction("a", Some(DelimiterSize::Big))), big, 1e-9);
+        assert_close!(opening_height(fraction(r"\frac{a}{\frac{b}{c}}", Some(DelimiterSize::Big))), big, 1e-9);
+
+        // ... whereas delimiters grow with the fraction by default
+        assert!(opening_height(fraction(r"\frac{a}{\frac{b}{c}}", None)) > opening_height(fraction("a", None)));
+    }
+
+    #[test]
+    fn single_glyph_layouts() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let x = layout_of("x", config);
+        let glyph = x.single_glyph().expect("layout of `x` should be a single glyph");
+        assert_eq!(glyph.gid, ctx.font.glyph_index('𝑥').unwrap());
+        assert!(layout_of(r"\color{red}{x}", config).single_glyph().is_some());
+
+        assert!(layout_of("x+y", config).single_glyph().is_none());
+        assert!(layout_of("", config).single_glyph().is_none());
+    }
+
+    #[test]
+    fn accented_letters_in_text() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let gids = |formula : &str| glyphs(&layout_of(formula, config)).iter().map(|(_, glyph)| glyph.gid).collect::<Vec<_>>();
+
+        // precomposed letters are found directly in the font
+        assert_eq!(gids(r"\text{é}"), vec![ctx.font.glyph_index('é').unwrap()]);
+        // letters followed by a combining mark, or with a text accent, are drawn with the precomposed glyph
+        assert_eq!(gids("\\text{cafe\u{301}}"), gids(r"\text{café}"));
+        assert_eq!(gids(r"\text{caf\'e}"),        gids(r"\text{café}"));
+        assert_eq!(gids(r"\text{\c{c}}"),         vec![ctx.font.glyph_index('ç').unwrap()]);
+
+        // without a precomposed letter, the combining mark is drawn after the letter
+        assert_eq!(gids(r"\text{\'x}"), vec![ctx.font.glyph_index('x').unwrap(), ctx.font.glyph_index('\u{301}').unwrap()]);
+    }
+
+    #[test]
+    fn set_builder_is_spaced_around_middle_delimiter() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let (thin, thick) = (width_of(r"a\,a", config) - width_of("aa", config), width_of(r"a\;a", config) - width_of("aa", config));
+
+        let spaced = layout_of(r"\left(\, a \;\middle\|\; b\,\right)", config);
+        assert_close!(
+            spaced.size().width - width_of(r"\left(a\middle\|b\right)", config),
+            2. * thin + 2. * thick,
+            1e-5
+        );
+
+        // the contents of the delimited group are laid out between the delimiters: `(`, `\, a \;`, `‖`, `\; b\,`, `)`
+        assert_eq!(spaced.contents.len(), 5);
+        let before_bar = spaced.contents[1].width.unitless(Px) - width_of(r"\,a", config);
+        let after_bar  = spaced.contents[3].width.unitless(Px) - width_of(r"b\,", config);
+        assert_close!(before_bar, thick, 1e-5);
+        assert_close!(after_bar,  thick, 1e-5);
+    }
+
+    #[test]
+    fn reserved_box() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let small = layout_of("x", config);
+        let large = layout_of(r"\frac{a+b}{c}", config).size();
+        let padded = small.clone().with_reserved_box(large);
+        assert_eq!(padded.size(), large);
+
+        // only the dimensions of the layout itself change, not its contents
+        let diffs = small.diff(&padded);
+        assert_eq!(diffs.len(), 3);
+        assert!(diffs.iter().all(|diff| diff.path.is_empty()));
+
+        // a box smaller than the layout leaves it unchanged
+        assert_eq!(padded.clone().with_reserved_box(small.size()).size(), large);
+    }
+
+    #[test]
+    fn dots_are_centered_in_matrix_cells() {
+        use crate::render::{Renderer, recorder::Recorder};
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // vertical and diagonal dots are ordinary symbols, with no space around them
+        assert_close!(width_of(r"a\vdots b", config), width_of("a", config) + width_of(r"\vdots", config) + width_of("b", config), 1e-9);
+        assert_close!(width_of(r"a\ddots b", config), width_of("a", config) + width_of(r"\ddots", config) + width_of("b", config), 1e-9);
+
+        // The dots are centered on the math axis
+        for dots in [r"\cdots", r"\vdots", r"\ddots"] {
+            let dots = layout_of(dots, config);
+            let glyph = &dots.contents[0];
+            let center = (glyph.height + glyph.depth).unitless(Px) / 2.;
+            assert_close!(center, dots.axis_height.unitless(Px), 0.05);
+        }
+
+        // Cells of a matrix row share their baseline: the dots are thus centered with the other cells of their row, and in their column
+        let matrix = layout_of(r"\begin{matrix} a & \cdots & b \\ \vdots & \ddots & \vdots \\ c & \cdots & d \end{matrix}", config);
+        let mut backend = Recorder::default();
+        let hit_map = Renderer::new().render_with_hit_map(&matrix, &mut backend);
+        // baseline and center of the box of each occurrence of a symbol, in the order they are drawn
+        let glyphs_of = |c : char| {
+            let gid = ctx.glyph(c).unwrap().gid;
+            backend.symbols.iter().zip(hit_map.iter())
+                .filter(|(symbol, _)| symbol.gid == gid)
+                .map(|(symbol, &(pos, width, height, _))| (symbol.pos.y, pos.translate(width / 2., height / 2.)))
+                .collect::<Vec<_>>()
+        };
+        let (a, b, c, d) = (glyphs_of('𝑎')[0], glyphs_of('𝑏')[0], glyphs_of('𝑐')[0], glyphs_of('𝑑')[0]);
+        let (cdots, vdots, ddots) = (glyphs_of('\u{22EF}'), glyphs_of('\u{22EE}'), glyphs_of('\u{22F1}'));
+        assert_eq!((cdots.len(), vdots.len(), ddots.len()), (2, 2, 1));
+
+        let axis = matrix.axis_height.unitless(Px);
+        for (baseline, center) in cdots.iter().chain(vdots.iter()).chain(ddots.iter()) {
+            assert_close!(center.y, baseline - axis, 0.05);
+        }
+        // rows
+        assert_close!(cdots[0].0, a.0, 1e-9);
+        assert_close!(cdots[1].0, c.0, 1e-9);
+        assert_close!(vdots[0].0, ddots[0].0, 1e-9);
+        assert_close!(vdots[1].0, ddots[0].0, 1e-9);
+        assert!(a.0 < ddots[0].0 && ddots[0].0 < c.0);
+        // columns
+        assert_close!(vdots[0].1.x, a.1.x, 1e-9);
+        assert_close!(vdots[0].1.x, c.1.x, 1e-9);
+        assert_close!(ddots[0].1.x, cdots[0].1.x, 1e-9);
+        assert_close!(ddots[0].1.x, cdots[1].1.x, 1e-9);
+        assert_close!(vdots[1].1.x, b.1.x, 1e-9);
+        assert_close!(vdots[1].1.x, d.1.x, 1e-9);
+    }
+
+    #[test]
+    fn stack_centered_on_baseline() {
+        use crate::parser::nodes::ParseNode;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let mut nodes = parse(r"\substack{a \\ b \\ c}").unwrap();
+        let center = |nodes : &[ParseNode]| {
+            let size = layout(nodes, config).unwrap().size();
+            (size.height + size.depth) / 2.
+        };
+        let axis = layout(&nodes, config).unwrap().axis_height.unitless(Px);
+        assert_close!(center(&nodes), axis, 1e-6);
+
+        match nodes[0] {
+            ParseNode::Stack(ref mut stack) => stack.centered_on_axis = false,
+            _ => panic!("expected a stack"),
+        }
+        assert_close!(center(&nodes), 0., 1e-6);
+    }
+
+    #[test]
+    fn vphantom_gives_delimiters_a_common_size() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+        // vertical extent of the opening delimiter
+        let left_delimiter = |formula : &str| {
+            let layout = layout_of(formula, config);
+            let delimiter = &layout.contents[0];
+            (delimiter.height.unitless(Px), delimiter.depth.unitless(Px))
+        };
+
+        // without a phantom, the parentheses grow with their contents
+        assert_ne!(left_delimiter(r"\left(x\right)"), left_delimiter(r"\left(\frac{1}{y_2}\right)"));
+
+        // with the same phantom, taller than the contents, the parentheses are the same
+        let (height, depth) = left_delimiter(r"\left(\vphantom{\frac{\frac{a}{b}}{\frac{c}{d}}} x\right)");
+        let (other_height, other_depth) = left_delimiter(r"\left(\vphantom{\frac{\frac{a}{b}}{\frac{c}{d}}} \frac{1}{y_2}\right)");
+        assert_close!(height, other_height, 1e-9);
+        assert_close!(depth,  other_depth,  1e-9);
+        assert!(height > left_delimiter(r"\left(x\right)").0);
+    }
+
+    #[test]
+    fn stretched_delimiter_on_its_own() {
+        use crate::font::{VariantGlyph, Direction};
+        use crate::layout::AsLayoutNode;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // much taller than the largest replacement glyph of '{'
+        let target = Unit::<FUnit>::new(5000.);
+        let variant = ctx.vert_variant('{', target).unwrap();
+        match variant {
+            VariantGlyph::Constructable(Direction::Vertical, ref parts) => assert!(parts.len() > 1),
+            _ => panic!("expected '{{' to be constructed from parts, got {:?}", variant),
+        }
+
+        let node = variant.as_layout(config).unwrap();
+        assert!(config.to_font(node.height - node.depth) >= target);
+    }
+
+    #[test]
+    fn fraction_parts_with_their_own_style() {
+        use crate::parser::nodes::{ParseNode, MathStyle};
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Text);
+
+        // sizes of the glyphs drawn, numerator first
+        let sizes = |numerator_style, denominator_style| {
+            let mut nodes = parse(r"\frac{x}{x}").unwrap();
+            match nodes[0] {
+                ParseNode::GenFraction(ref mut fraction) => {
+                    fraction.numerator_style   = numerator_style;
+                    fraction.denominator_style = denominator_style;
+                },
+                _ => panic!("expected a fraction"),
+            }
+            let sizes : Vec<_> = glyphs(&layout(&nodes, config).unwrap()).iter().map(|(_, glyph)| glyph.size.unitless(Px)).collect();
+            assert_eq!(sizes.len(), 2);
+            (sizes[0], sizes[1])
+        };
+
+        // in text style, both parts of a fraction are in script style
+        let (numerator, denominator) = sizes(MathStyle::NoChange, MathStyle::NoChange);
+        assert_close!(numerator, denominator, 1e-9);
+        assert!(numerator < 16.);
+
+        let (numerator, denominator) = sizes(MathStyle::Display, MathStyle::NoChange);
+        assert_close!(numerator, 16., 1e-9);
+        assert!(denominator < numerator);
+
+        let (numerator, denominator) = sizes(MathStyle::Display, MathStyle::Text);
+        assert_close!(numerator,   16., 1e-9);
+        assert_close!(denominator, 16., 1e-9);
+    }
+
+    #[test]
+    fn formula_wrapped_at_relations() {
+        use crate::render::{Renderer, recorder::Recorder};
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let formula = layout_of("x+y = b = c = d = e", config);
+        assert_eq!(formula.break_points.len(), 4);
+        // consecutive relations are not broken apart
+        let formula_colon = layout_of(r"a := b", config);
+        assert_eq!(formula_colon.break_points.len(), 1);
+        // nor are the parts of the formula
+        let formula_nested = layout_of(r"\frac{a = b}{c} = {d = e} = \color{red}{f = g}", config);
+        assert_eq!(formula_nested.break_points.len(), 2);
+
+        // a formula which fits is left unchanged
+        let unchanged = formula.wrapped(formula.width);
+        assert!(unchanged.diff(&formula).is_empty());
+
+        let max_width = formula.width.scale(0.5);
+        let wrapped = formula.wrapped(max_width);
+        let lines = match wrapped.contents[0].node {
+            LayoutVariant::VerticalBox(ref vbox) => vbox.contents.iter()
+                .filter(|node| matches!(node.node, LayoutVariant::HorizontalBox(_)))
+                .collect::<Vec<_>>(),
+            _ => panic!("expected lines stacked in a vertical box"),
+        };
+        assert!(lines.len() >= 2);
+        for line in lines.iter() {
+            assert!(line.width <= max_width);
+        }
+        // the baseline of the first line is kept, further lines go below
+        assert_close!(wrapped.height.unitless(Px), lines[0].height.unitless(Px), 1e-9);
+        assert!(wrapped.depth < formula.depth);
+        assert!(wrapped.width <= max_width);
+        assert_eq!(wrapped.glyph_count(), formula.glyph_count());
+
+        // each line starts the axis height below the bottom of the previous one, and its glyphs are drawn on its baseline
+        let mut backend = Recorder::default();
+        Renderer::new().render(&wrapped, &mut backend);
+        let mut baselines : Vec<f64> = backend.symbols.iter().map(|symbol| symbol.pos.y).collect();
+        baselines.dedup();
+        assert_eq!(baselines.len(), lines.len());
+        assert_close!(baselines[0], 0., 1e-9);
+        for (i, pair) in lines.windows(2).enumerate() {
+            let expected = baselines[i] - pair[0].depth.unitless(Px) + wrapped.axis_height.unitless(Px) + pair[1].height.unitless(Px);
+            assert_close!(baselines[i + 1], expected, 1e-9);
+        }
+    }
+
+    #[test]
+    fn starred_space_kept_at_line_breaks() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let max_width = Unit::<Px>::new(40.);
+        let first_line = |formula : &str| {
+            let wrapped = layout_of(formula, config).wrapped(max_width);
+            match wrapped.contents[0].node {
+                LayoutVariant::VerticalBox(ref vbox) => vbox.contents[0].width,
+                _ => panic!("expected lines stacked in a vertical box"),
+            }
+        };
+
+        // spaces at the end of a line are dropped, unless they come from `\hspace*`
+        let bare    = first_line(r"x+y = b = c = d = e");
+        let space   = first_line(r"x+y\hspace{1em} = b = c = d = e");
+        let starred = first_line(r"x+y\hspace*{1em} = b = c = d = e");
+        assert_close!(space.unitless(Px), bare.unitless(Px), 1e-9);
+        let em = layout_of(r"\hspace{1em}", config).width;
+        assert_close!(starred.unitless(Px), (bare + em).unitless(Px), 1e-9);
+    }
+
+    #[test]
+    fn radical_with_index() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let root  = layout_of(r"\sqrt{x}", config);
+        let cubic = layout_of(r"\sqrt[3]{x}", config);
+        let index = layout_of(r"\scriptscriptstyle 3", config);
+        assert_eq!(cubic.glyph_count(), root.glyph_count() + 1);
+
+        // the index is tucked in the kink of the radical sign, partly overlapping it
+        let constants = &ctx.constants;
+        let kerns = (constants.radical_kern_before_degree + constants.radical_kern_after_degree) * config.font_size;
+        assert!(constants.radical_kern_after_degree < Unit::ZERO);
+        assert_close!(cubic.width.unitless(Px), (root.width + index.width + kerns).unitless(Px), 1e-9);
+        assert!(cubic.width < root.width + index.width);
+
+        // the bottom of the index is raised above the bottom of the radical sign, so the index may stick out above the radical sign
+        assert!(cubic.height >= root.height);
+        assert_close!(cubic.depth.unitless(Px), root.depth.unitless(Px), 1e-9);
+        let tall_root  = layout_of(r"\sqrt{\frac{\frac{a}{b}}{c}}", config);
+        let tall_cubic = layout_of(r"\sqrt[3]{\frac{\frac{a}{b}}{c}}", config);
+        assert_close!(tall_cubic.height.unitless(Px), tall_root.height.unitless(Px), 1e-9);
+        assert_close!(tall_cubic.depth.unitless(Px),  tall_root.depth.unitless(Px), 1e-9);
+    }
+
+    #[test]
+    fn extensible_arrows_grow_with_labels() {
+        use crate::layout::constants::EXTENSIBLE_ARROW_PADDING;
+        use crate::layout::convert::Scaled;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let padding = EXTENSIBLE_ARROW_PADDING.scaled(config).unitless(Px);
+
+        let arrow = size_of(r"\to", config);
+        let short = size_of(r"\xrightarrow{f}", config);
+        let long  = size_of(r"\xrightarrow{f \circ g \circ h}", config);
+        let label = size_of(r"\scriptstyle f \circ g \circ h", config);
+        assert!(long.width > short.width);
+        assert!(long.width >= label.width + padding - 1e-9);
+
+        // the arrow itself is stretched, rather than spaced out
+        let glyph_widths = |formula : &str| glyphs(&layout_of(formula, config)).iter().map(|(node, _)| node.width.unitless(Px)).collect::<Vec<_>>();
+        let long_arrow_width : f64 = glyph_widths(r"\xrightarrow{f \circ g \circ h}").iter().sum::<f64>() 
+            - glyph_widths(r"\scriptstyle f \circ g \circ h").iter().sum::<f64>();
+        assert!(long_arrow_width >= label.width);
+        assert!(long_arrow_width > arrow.width);
+
+        // labels go above and below the arrow, which remains on the baseline
+        let over  = size_of(r"\xleftarrow{f}", config);
+        let both  = size_of(r"\xleftarrow[g]{f}", config);
+        assert!(over.height > arrow.height);
+        assert_close!(over.depth, arrow.depth, 1e-9);
+        assert!(both.depth < arrow.depth);
+        assert_close!(both.height, over.height, 1e-9);
+
+        // the arrow is a relation
+        assert_close!(size_of(r"a \xrightarrow{} b", config).width - size_of(r"\xrightarrow{}", config).width, size_of("a=b", config).width - size_of("=", config).width, 1e-9);
+    }
+
+    #[test]
+    fn boxed_includes_frame() {
+        use crate::layout::constants::FBOX_SEP;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let thickness = (ctx.constants.fraction_rule_thickness * config.font_size).unitless(Px);
+        let margin = (FBOX_SEP * Unit::standard_pt_to_px()).unitless(Px);
+        let contents = size_of(r"\displaystyle y^2", config);
+        let boxed    = size_of(r"\boxed{y^2}", config);
+        assert_close!(boxed.width,  contents.width  + 2. * (margin + thickness), 1e-9);
+        assert_close!(boxed.height, contents.height + margin + thickness, 1e-9);
+        assert_close!(boxed.depth,  contents.depth  - margin - thickness, 1e-9);
+
+        // neighbours are placed beside the frame
+        assert_close!(size_of(r"x\boxed{y^2}", config).width, size_of("x", config).width + boxed.width, 1e-9);
+
+        // the frame consists of four rules
+        let boxed = layout_of(r"\boxed{y^2}", config);
+        assert_eq!(all_nodes(&boxed.contents).iter().filter(|node| matches!(node.node, LayoutVariant::Rule)).count(), 4);
+    }
+
+    #[test]
+    fn continued_fractions_keep_their_size() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let sizes_of = |formula : &str| glyphs(&layout_of(formula, config)).iter().map(|(_, glyph)| glyph.size.unitless(Px)).collect::<Vec<_>>();
+
+        // All levels of a continued fraction are at full size, unlike nested fractions
+        let sizes = sizes_of(r"\cfrac{1}{1+\cfrac{1}{1+x}}");
+        assert_eq!(sizes.len(), 7);
+        for size in sizes {
+            assert_close!(size, 16., 1e-9);
+        }
+        let sizes = sizes_of(r"\frac{1}{1+\frac{1}{1+x}}");
+        assert!(sizes.iter().any(|&size| size < 16.));
+
+        // The numerator is placed on the left, at the center or on the right of the fraction
+        let numerator_alignment = |formula : &str| {
+            let layout = layout_of(formula, config);
+            let fraction = match layout.contents[1].node {
+                LayoutVariant::VerticalBox(ref vbox) => vbox,
+                _ => panic!("expected a fraction"),
+            };
+            match fraction.contents[0].node {
+                LayoutVariant::HorizontalBox(ref numerator) => (numerator.alignment, fraction.contents[0].width),
+                _ => panic!("expected a numerator"),
+            }
+        };
+        let (left, width) = numerator_alignment(r"\cfrac[l]{1}{1+x}");
+        assert_eq!(left, Alignment::Left);
+        let (right, _) = numerator_alignment(r"\cfrac[r]{1}{1+x}");
+        assert!(matches!(right, Alignment::Right(w) if w < width));
+        let (centered, _) = numerator_alignment(r"\cfrac{1}{1+x}");
+        assert!(matches!(centered, Alignment::Centered(w) if w < width));
+    }
+
+    #[test]
+    fn wide_accents_stretch() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // The accent is the last node of the box placed above the base
+        let accent_width = |formula : &str| {
+            let layout = layout_of(formula, config);
+            let accented = match layout.contents[0].node {
+                LayoutVariant::VerticalBox(ref vbox) => vbox,
+                _ => panic!("expected an accented node"),
+            };
+            match accented.contents[0].node {
+                LayoutVariant::HorizontalBox(ref hbox) => hbox.contents.last().unwrap().width,
+                _ => panic!("expected an accent"),
+            }
+        };
+
+        // Accents are drawn at their natural size, however wide the base
+        let hat_width = accent_width(r"\hat{a}");
+        assert_close!(accent_width(r"\hat{abcdef}").unitless(Px), hat_width.unitless(Px), 1e-9);
+
+        // Wide accents grow with the base, and even a narrow base gets a variant wider than the plain accent
+        for command in [r"\widehat", r"\widetilde"] {
+            let narrow = accent_width(&format!("{}{{a}}", command));
+            let wide   = accent_width(&format!("{}{{abcdef}}", command));
+            assert!(narrow < wide);
+        }
+        assert!(accent_width(r"\widehat{a}") > hat_width);
+
+        // Fonts with fewer variants fall back on their largest one
+        for font_bytes in [&include_bytes!("../../resources/Garamond_Math.otf")[..], &include_bytes!("../../resources/Asana-Math.otf")[..], &include_bytes!("../../resources/FiraMath_Regular.otf")[..]] {
+            let font = TtfMathFont::new(ttf_parser::Face::parse(font_bytes, 0).unwrap()).unwrap();
+            let ctx = FontContext::new(&font);
+            let config = LayoutSettings::new(&ctx).font_size(12.);
+            for formula in [r"\widehat{a}", r"\widetilde{abcdefghijklmnopqrstuvwxyz}"] {
+                layout_of(formula, config);
+            }
+        }
+    }
+
+    #[test]
+    fn primes_are_single_glyphs_at_base_size() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let glyphs_of = |formula : &str| glyphs(&layout_of(formula, config)).iter().map(|(_, glyph)| (glyph.gid, glyph.size.unitless(Px))).collect::<Vec<_>>();
+        let gid = |codepoint : char| ctx.glyph(codepoint).unwrap().gid;
+
+        // Runs of primes use the font's multiple prime glyphs, at the size of the base
+        for (formula, prime) in [("f'", '′'), ("f''", '″'), ("f'''", '‴'), ("f''''", '⁗')] {
+            assert_eq!(glyphs_of(formula), vec![(gid('𝑓'), 16.), (gid(prime), 16.)]);
+        }
+        assert_eq!(glyphs_of("f'''''"), vec![(gid('𝑓'), 16.), (gid('⁗'), 16.), (gid('′'), 16.)]);
+
+        // The rest of the superscript comes after the primes, at script size
+        let glyphs = glyphs_of("f'^2");
+        assert_eq!(glyphs[.. 2], [(gid('𝑓'), 16.), (gid('′'), 16.)]);
+        assert!(glyphs[2].1 < 16.);
+    }
+
+    #[test]
+    fn smash_ignores_height_and_depth() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let fraction = layout_of(r"\frac{b}{y}", config).size();
+        assert!(fraction.height > 0. && fraction.depth < 0.);
+
+        // The content is still drawn, with its width
+        for formula in [r"\smash{\frac{b}{y}}", r"\smash[t]{\frac{b}{y}}", r"\smash[b]{\frac{b}{y}}"] {
+            let smashed = layout_of(formula, config);
+            assert_eq!(smashed.glyph_count(), 2);
+            assert_close!(smashed.size().width, fraction.width, 1e-5);
+        }
+
+        assert_close!(size_of(r"\smash{\frac{b}{y}}", config).height,    0., 1e-5);
+        assert_close!(size_of(r"\smash{\frac{b}{y}}", config).depth,     0., 1e-5);
+        assert_close!(size_of(r"\smash[t]{\frac{b}{y}}", config).height, 0., 1e-5);
+        assert_close!(size_of(r"\smash[t]{\frac{b}{y}}", config).depth,  fraction.depth,  1e-5);
+        assert_close!(size_of(r"\smash[b]{\frac{b}{y}}", config).height, fraction.height, 1e-5);
+        assert_close!(size_of(r"\smash[b]{\frac{b}{y}}", config).depth,  0., 1e-5);
+
+        // Surrounding material ignores the smashed extent
+        assert_close!(size_of(r"x\smash{\frac{b}{y}}", config).height, size_of(r"x", config).height, 1e-5);
+    }
+
+    #[test]
+    fn ex_is_the_x_height_of_the_font() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        let x_height = ctx.constants_in_px(16.).accent_base_height.unitless(Px);
+        let rule = size_of(r"\rule{2ex}{1ex}", config);
+        assert_close!(rule.width,  2. * x_height, 1e-9);
+        assert_close!(rule.height, x_height,      1e-9);
+        // like em, ex scales down in scripts
+        assert!(size_of(r"x^{\rule{2ex}{1ex}}", config).width - size_of("x", config).width < 2. * x_height);
+    }
+
+    #[test]
+    fn glyph_metrics_are_queried_once_per_glyph() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // a 20×20 array whose entries all use the same glyphs
+        let row = vec!["x_1 + y"; 20].join(" & ");
+        let formula = format!(r"\begin{{array}}{{{}}} {} \end{{array}}", "c".repeat(20), vec![row; 20].join(r" \\ "));
+        let nodes = parse(&formula).unwrap();
+
+        let array = layout(&nodes, config).unwrap();
+        assert_eq!(array.glyph_count(), 20 * 20 * 4);
+        assert_eq!(ctx.glyph_font_lookups(), 4);
+
+        // laying out again needs no font lookup at all and gives the same result
+        let again = layout(&nodes, config).unwrap();
+        assert_eq!(ctx.glyph_font_lookups(), 4);
+        assert_eq!(array.size(), again.size());
+    }
+
+    #[test]
+    fn matrix_delimiters_stretch_to_the_matrix() {
+        use crate::layout::engine::layout_with_stats;
+
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let stats_of = |env : &str| {
+            let formula = format!(r"\begin{{{0}}} a \\ b \\ c \\ d \end{{{0}}}", env);
+            layout_with_stats(&parse(&formula).unwrap(), config).unwrap()
+        };
+
+        let (matrix, stats) = stats_of("matrix");
+        assert_eq!(stats.vert_variants, 0);
+        for env in ["pmatrix", "bmatrix", "Bmatrix", "vmatrix", "Vmatrix"] {
+            let (delimited, stats) = stats_of(env);
+            // both delimiters are stretched to the height of the matrix
+            assert_eq!(stats.vert_variants, 2, "delimiters of {}", env);
+            assert!(delimited.glyph_count() > matrix.glyph_count(), "{} has no delimiters", env);
+            assert_close!(delimited.size().height, matrix.size().height, 1e-5);
+        }
+    }
+
+    #[test]
+    fn smallmatrix_is_smaller_than_matrix() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+        let size_of = |env : &str| {
+            let formula = format!(r"\left(\begin{{{0}}}a&b\\c&d\end{{{0}}}\right)", env);
+            layout_of(&formula, config).size()
+        };
+
+        let small  = size_of("smallmatrix");
+        let normal = size_of("matrix");
+        assert!(small.width  < normal.width);
+        assert!(small.height < normal.height);
+        assert!(small.depth  > normal.depth);
+    }
+
+    #[test]
+    fn paragraph_columns_have_a_fixed_width() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.);
+
+        // the width of the column does not depend on its content, even if the content is wider
+        let narrow = width_of(r"\begin{array}{p{3em}c}a&b\end{array}", config);
+        assert_close!(width_of(r"\begin{array}{p{3em}c}aaaaaaaaaa&b\end{array}", config), narrow, 1e-5);
+        assert_close!(width_of(r"\begin{array}{p{5em}c}a&b\end{array}", config), narrow + 2. * config.font_size.to_unitless(), 1e-5);
+        // the font size is 12pt, so 3em is 36pt
+        assert_close!(width_of(r"\begin{array}{p{36pt}c}a&b\end{array}", config), narrow, 1e-5);
+    }
+
+    #[test]
+    fn missing_limits_take_no_room() {
+        let ctx = xits_context();
+        let config = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+
+        let sum      = size_of(r"\sum", config);
+        let sum_sub  = size_of(r"\sum_i", config);
+        let sum_sup  = size_of(r"\sum^n", config);
+        let sum_both = size_of(r"\sum_i^n", config);
+
+        // If a missing limit were laid out as an empty box, separated from the operator by the usual gap (as it used to be),
+        // `\sum_i` would be taller than `\sum` by this gap, which is not negligible.
+        let upper_gap = Unit::max(ctx.constants.upper_limit_baseline_rise_min, ctx.constants.upper_limit_gap_min) * config.font_size;
+        assert!(upper_gap.to_unitless() > 1.);
+        // Now, as in TeX, the side of the missing limit is left untouched
+        assert_close!(sum_sub.height, sum.height, 1e-5);
+        assert_close!(sum_sup.depth,  sum.depth,  1e-5);
+
+        // Limits that are present are placed as before, whether the other limit is present or not
+        assert!(sum_sup.height > sum.height + upper_gap.to_unitless());
+        assert!(sum_sub.depth  < sum.depth);
+        assert_close!(sum_both.height, sum_sup.height, 1e-5);
+        assert_close!(sum_both.depth,  sum_sub.depth,  1e-5);
+    }
+}
@@ -63,7 +63,7 @@ rex::render(
 
 Notes:
 
- - `render` places the first glyph's origin at (0, 0), using a font size of 16 surface units per em.
+ - `render` places the first glyph's origin at (0, 0), using a font size of 16 surface units per em. Use `render_with_size` to choose another font size.
  - The surface unit is whatever unit is used when making calls to the graphical backend directly. Here, with Cairo, these are pixels.
  - em is a unit with which the size of glyphs are expressed in the font file ; by convention, it is approximately, although not necessarily equal to, the size of upper case letter M.
 
@@ -211,41 +211,75 @@ pub mod font;
 use font::{FontContext, MathFont};
 pub use render::*;
 
-use crate::{layout::{LayoutSettings, Style}, parser::parse};
+use crate::{layout::{LayoutSettings, LayoutDimensions, Style}, parser::parse, dimensions::Unit};
 
 
+/// Font size used by [`render`], in surface units per em
+pub const DEFAULT_FONT_SIZE : f64 = 16.;
+
 /// Render a LateX formula to a given a surface `backend`, given a math font provided by `font_context`.
 pub fn render<F : MathFont, B : Backend<F>>(formula : &str, backend : &mut B, font_context: &FontContext<'_, F>) -> Result<(), crate::error::Error> {
-    const DEFAULT_FONT_SIZE : f64 = 16.;
+    render_with_size(formula, backend, font_context, DEFAULT_FONT_SIZE, Style::Display)
+}
 
+/// Same as [`render`], but with the given font size, in surface units per em (instead of [`DEFAULT_FONT_SIZE`]), 
+/// and the given starting style (instead of [`Style::Display`]).
+pub fn render_with_size<F : MathFont, B : Backend<F>>(
+    formula      : &str, 
+    backend      : &mut B, 
+    font_context : &FontContext<'_, F>,
+    font_size    : f64,
+    style        : Style,
+) -> Result<(), crate::error::Error> {
     let parse_nodes = parse(formula)?;
 
-    let layout_settings = LayoutSettings::new(font_context);
+    // `LayoutSettings` expects a font size in pt per em
+    let font_size_pt = font_size / Unit::standard_pt_to_px().to_unitless();
+    let layout_settings = LayoutSettings::new(font_context).font_size(font_size_pt).layout_style(style);
 
 
     let layout = crate::layout::engine::layout(&parse_nodes, layout_settings)?;
 
+    // Nothing to draw, e.g. for an empty formula
+    if layout.is_empty() {
+        return Ok(());
+    }
 
     let renderer = Renderer::new();
     renderer.render(&layout, backend);
     Ok(())
 }
 
+/// Returns the dimensions the formula has when drawn by [`render`], e.g. in order to create a surface of the right size.
+/// An empty formula has all dimensions equal to zero, cf [`LayoutDimensions::at_least`].
+pub fn measure<F : MathFont>(formula : &str, font_context: &FontContext<'_, F>) -> Result<LayoutDimensions, crate::error::Error> {
+    let parse_nodes = parse(formula)?;
+    let layout_settings = LayoutSettings::new(font_context);
+    let layout = crate::layout::engine::layout(&parse_nodes, layout_settings)?;
+    Ok(layout.size())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{parser::parse, font::{FontContext, backend::ttf_parser::TtfMathFont}, layout::{Style, LayoutSettings, engine}};
+    use crate::render::recorder::Recorder;
+    use std::sync::OnceLock;
 
     const GARAMOND_MATH_FONT : &[u8] = include_bytes!("../resources/Garamond_Math.otf");
 
+    /// A font context for Garamond Math, with caches of its own
+    fn garamond_context() -> FontContext<'static, TtfMathFont<'static>> {
+        static GARAMOND : OnceLock<TtfMathFont<'static>> = OnceLock::new();
+        FontContext::new(GARAMOND.get_or_init(|| TtfMathFont::new(ttf_parser::Face::parse(GARAMOND_MATH_FONT, 0).unwrap()).unwrap()))
+    }
+
 
     /// If the font's coverage of mathematical alphanumeric characters is exhaustive in all styles (as with Garamond-Math.otf, a.o.),
     /// then the library should not fail parsing and laying out on any of these.
     /// Test for bugs like [https://github.com/KenyC/ReX/issues/6](https://github.com/KenyC/ReX/issues/6)
     #[test]
     fn all_alphanumeric_style_combinations_must_work() {
-        let font = ttf_parser::Face::parse(GARAMOND_MATH_FONT, 0).unwrap();
-        let font = TtfMathFont::new(font).unwrap();
-        let ctx = FontContext::new(&font);
+        let ctx = garamond_context();
 
         let layout_settings = LayoutSettings::new(&ctx).font_size(10.0);
 
@@ -261,8 +295,20 @@ mod tests {
             Some("mathrm"),
             Some("mathfrak"),
             Some("mathbb"),
+            Some("mathbf"),
+            Some("mathsf"),
+            Some("mathtt"),
         ];
 
+        // First codepoint of the block of styled digits, cf Unicode's Mathematical Alphanumeric Symbols
+        let digit_block = |env : &str| match env {
+            "mathbf" => Some(0x1D7CE),
+            "mathbb" => Some(0x1D7D8),
+            "mathsf" => Some(0x1D7E2),
+            "mathtt" => Some(0x1D7F6),
+            _ => None,
+        };
+
         for env in envs {
             for character in alphanumeric.iter() {
                 let formula; 
@@ -275,8 +321,223 @@ mod tests {
 
                 println!("{}", formula);
                 let parse_nodes = parse(&formula).unwrap();
-                engine::layout(&parse_nodes, layout_settings).unwrap();
+                let layout = engine::layout(&parse_nodes, layout_settings).unwrap();
+
+                // Styled digits are drawn with the glyph of their dedicated codepoint
+                if let (Some(first), Some(digit)) = (env.and_then(digit_block), character.to_digit(10)) {
+                    let expected = ctx.glyph(std::char::from_u32(first + digit).unwrap()).unwrap().gid;
+                    assert_eq!(crate::layout::is_symbol(&layout.contents).unwrap().gid, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_formula() {
+        let ctx = garamond_context();
+
+        let mut backend = Recorder::default();
+        crate::render("", &mut backend, &ctx).unwrap();
+        assert!(backend.is_empty());
+
+        let size = crate::measure("", &ctx).unwrap();
+        assert_eq!(size.width, 0.);
+        assert_eq!(size.height - size.depth, 0.);
+
+        let size = size.at_least(1.);
+        assert_eq!(size.width, 1.);
+        assert_eq!(size.height - size.depth, 1.);
+
+        let size = crate::measure("x", &ctx).unwrap();
+        assert_eq!(size.at_least(1.), size);
+    }
+
+    #[test]
+    fn render_with_size_scales_formula() {
+        let ctx = garamond_context();
+        let formula = r"\frac{a}{b^2}";
+
+        let mut default = Recorder::default();
+        crate::render(formula, &mut default, &ctx).unwrap();
+        let mut small = Recorder::default();
+        crate::render_with_size(formula, &mut small, &ctx, 16., Style::Display).unwrap();
+        let mut large = Recorder::default();
+        crate::render_with_size(formula, &mut large, &ctx, 32., Style::Display).unwrap();
+
+        assert_eq!(default.symbols, small.symbols);
+        assert_eq!(small.symbols.len(), large.symbols.len());
+        for (small, large) in small.symbols.iter().zip(large.symbols.iter()) {
+            assert_close!(2. * small.pos.x,  large.pos.x, 1e-6);
+            assert_close!(2. * small.pos.y,  large.pos.y, 1e-6);
+            assert_close!(2. * small.scale,  large.scale, 1e-9);
+        }
+    }
+
+    #[test]
+    fn decimal_points_align_in_array() {
+        let ctx = garamond_context();
+
+        let mut backend = Recorder::default();
+        crate::render(r"\begin{array}{r@{.}l} 3 & 14 \\ 2 & 7 \end{array}", &mut backend, &ctx).unwrap();
+        let positions_of = |c : char| backend.positions_of(ctx.glyph(c).unwrap().gid);
+
+        // The '.' of the @-expression is drawn once per row
+        let points = positions_of('.');
+        assert_eq!(points.len(), 2);
+        let (three, two)  = (positions_of('3')[0], positions_of('2')[0]);
+        let (one, seven)  = (positions_of('1')[0], positions_of('7')[0]);
+        assert_close!(points[0].y, three.y, 1e-6);
+        assert_close!(points[1].y, two.y,   1e-6);
+
+        // Decimal points line up vertically, between the integral parts and the fractional parts
+        assert_close!(points[0].x, points[1].x, 1e-6);
+        assert!(three.x < points[0].x && points[0].x < one.x);
+
+        // The fractional parts are left-aligned against the decimal points
+        assert_close!(one.x, seven.x, 1e-6);
+        // The integral parts are right-aligned against the decimal points: '3' and '2' have the same advance in Garamond Math
+        assert_close!(three.x, two.x, 1e-6);
+    }
+
+    #[test]
+    fn cancel_draws_diagonal_across_box() {
+        let ctx = garamond_context();
+
+        // The struck-through nodes keep their dimensions
+        let size = crate::measure(r"\cancel{abc}", &ctx).unwrap();
+        let base = crate::measure("abc", &ctx).unwrap();
+        assert_close!(size.width,  base.width,  1e-9);
+        assert_close!(size.height, base.height, 1e-9);
+        assert_close!(size.depth,  base.depth,  1e-9);
+
+        // A single line from the bottom left to the top right corner
+        let mut backend = Recorder::default();
+        crate::render(r"\cancel{abc}", &mut backend, &ctx).unwrap();
+        assert_eq!(backend.lines.len(), 1);
+        let (from, to, thickness) = backend.lines[0];
+        assert_close!(from.x, 0.,          1e-9);
+        assert_close!(from.y, -size.depth,  1e-9);
+        assert_close!(to.x,   size.width,  1e-9);
+        assert_close!(to.y,   -size.height, 1e-9);
+        assert!(thickness > 0.);
+
+        let mut backend = Recorder::default();
+        crate::render(r"\bcancel{abc}", &mut backend, &ctx).unwrap();
+        assert_eq!(backend.lines.len(), 1);
+        let (from, to, _) = backend.lines[0];
+        assert_close!(from.y, -size.height, 1e-9);
+        assert_close!(to.y,   -size.depth,  1e-9);
+
+        let mut backend = Recorder::default();
+        crate::render(r"\xcancel{abc}", &mut backend, &ctx).unwrap();
+        assert_eq!(backend.lines.len(), 2);
+    }
+
+    #[test]
+    fn substack_is_centered_below_sum() {
+        use crate::{Renderer, font::common::GlyphId};
+
+        let ctx = garamond_context();
+        let settings = LayoutSettings::new(&ctx).font_size(12.).layout_style(Style::Display);
+        let layout = engine::layout(&parse(r"\sum_{\substack{i \\ j}}").unwrap(), settings).unwrap();
+        let glyphs = Renderer::new().render_with_hit_map(&layout, &mut Recorder::default());
+
+        // The large variant of the summation sign is drawn, so it is the only glyph other than 'i' and 'j'
+        let glyph_of = |c : char| ctx.glyph(crate::font::style_symbol(c, crate::font::Style::default())).unwrap().gid;
+        let (i, j) = (glyph_of('i'), glyph_of('j'));
+        assert_eq!(glyphs.len(), 3);
+        let find = |predicate : &dyn Fn(GlyphId) -> bool| *glyphs.iter().find(|&&(_, _, _, gid)| predicate(gid)).unwrap();
+        let (sum_pos, sum_width, _, _) = find(&|gid| gid != i && gid != j);
+        let (i_pos, i_width, _, _)     = find(&|gid| gid == i);
+        let (j_pos, j_width, _, _)     = find(&|gid| gid == j);
+
+        // Both lines are centered below the summation sign
+        let sum_center = sum_pos.x + sum_width / 2.;
+        assert_close!(i_pos.x + i_width / 2., sum_center, 1e-6);
+        assert_close!(j_pos.x + j_width / 2., sum_center, 1e-6);
+        assert!(sum_pos.y < i_pos.y && i_pos.y < j_pos.y);
+    }
+
+    #[test]
+    fn relations_line_up_in_aligned() {
+        let ctx = garamond_context();
+
+        let mut backend = Recorder::default();
+        crate::render(r"\begin{aligned} f(x) &= x^2 \\ &\leq |x|^2 \\ \end{aligned}", &mut backend, &ctx).unwrap();
+        let position_of = |c : char| backend.positions_of(ctx.glyph(c).unwrap().gid)[0];
+
+        // The relations line up, on two different rows
+        let (equal, leq) = (position_of('='), position_of('\u{2264}'));
+        assert_close!(equal.x, leq.x, 1e-6);
+        assert!(equal.y < leq.y);
+        let bar = position_of('|');
+        assert!(leq.x < bar.x);
+
+        // A trailing `\\` adds no empty row
+        assert_eq!(
+            crate::parser::parse(r"\begin{aligned} a &= b \\ &\leq c \\ \end{aligned}"),
+            crate::parser::parse(r"\begin{aligned} a &= b \\ &\leq c \end{aligned}"),
+        );
+    }
+
+    #[test]
+    fn brace_labels_are_centered() {
+        use crate::Renderer;
+
+        let ctx = garamond_context();
+        let settings = LayoutSettings::new(&ctx).font_size(12.);
+        let glyph_of = |c : char| ctx.glyph(crate::font::style_symbol(c, crate::font::Style::default())).unwrap().gid;
+        let base = [glyph_of('a'), glyph_of('b'), glyph_of('c'), glyph_of('+')];
+        let label = glyph_of('n');
+
+        for formula in [r"\overbrace{a+b+c}^{n}", r"\underbrace{a+b+c}_{n}"] {
+            let layout = engine::layout(&parse(formula).unwrap(), settings).unwrap();
+            let glyphs = Renderer::new().render_with_hit_map(&layout, &mut Recorder::default());
+
+            let (label_pos, label_width, _, _) = *glyphs.iter().find(|&&(_, _, _, gid)| gid == label).unwrap();
+            let brace : Vec<_> = glyphs.iter().filter(|&&(_, _, _, gid)| gid != label && !base.contains(&gid)).collect();
+            assert!(!brace.is_empty());
+
+            // The brace spans the whole argument, and the brace and the label are centered on the formula
+            let brace_left  = brace.iter().map(|(pos, _, _, _)| pos.x).fold(f64::INFINITY, f64::min);
+            let brace_right = brace.iter().map(|(pos, width, _, _)| pos.x + width).fold(f64::NEG_INFINITY, f64::max);
+            let base_width  = engine::layout(&parse("a+b+c").unwrap(), settings).unwrap().size().width;
+            assert!(brace_right - brace_left >= base_width - 1e-6);
+            let center = layout.width.unitless(crate::dimensions::units::Px) / 2.;
+            assert_close!(label_pos.x + label_width / 2., center, 1e-6);
+            // the pieces of the brace overlap, so their advances are not exactly symmetric
+            assert_close!((brace_left + brace_right) / 2., center, 0.1);
+
+            // The label is beyond the brace
+            for (pos, _, _, _) in brace {
+                if formula.contains("over") {
+                    assert!(label_pos.y < pos.y);
+                }
+                else {
+                    assert!(label_pos.y > pos.y);
+                }
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn lap_boxes_overlap_their_neighbours() {
+        let ctx = garamond_context();
+        let x_of = |formula : &str, c : char| {
+            let mut backend = Recorder::default();
+            crate::render(formula, &mut backend, &ctx).unwrap();
+            backend.positions_of(ctx.glyph(c).unwrap().gid)[0].x
+        };
+        let b_width = x_of("bc", '𝑐') - x_of("bc", '𝑏');
+
+        // The content of a lap box hangs to the left of, to the right of, or around the insertion point
+        assert_close!(x_of(r"a\mathllap{b}", '𝑏'), x_of("ab", '𝑏') - b_width, 1e-6);
+        assert_close!(x_of(r"a\mathrlap{b}", '𝑏'), x_of("ab", '𝑏'), 1e-6);
+        assert_close!(x_of(r"a\mathclap{b}", '𝑏'), x_of("ab", '𝑏') - b_width / 2., 1e-6);
+
+        // and takes no room
+        for lap in [r"\mathllap", r"\mathrlap", r"\mathclap"] {
+            assert_close!(x_of(&format!("a{}{{b}}c", lap), '𝑐'), x_of("ac", '𝑐'), 1e-6);
+        }
+    }
+}
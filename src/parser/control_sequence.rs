@@ -1,6 +1,6 @@
 use unicode_math::TexSymbolType;
 
-use crate::{dimensions::{units::Em, AnyUnit, Unit}, font::{Family, Weight}, layout::{constants, Style as LayoutStyle}, parser::{nodes::{BarThickness, MathStyle}, symbols::Symbol}, RGBA};
+use crate::{dimensions::{units::Em, AnyUnit, Unit}, font::{Family, Weight}, layout::{constants, Style as LayoutStyle}, parser::{nodes::{ArrayColumnAlign, BarThickness, CancelDirection, LinePlacement, MathStyle}, symbols::Symbol}, RGBA};
 
 use super::{error::{ParseError, ParseResult}, macros::CommandCollection, nodes::Color, textoken::TexToken, Parser};
 
@@ -13,17 +13,46 @@ pub enum PrimitiveControlSequence {
     Color,
     /// Represents ReX's command `\blue{..}`, `\red{..}`
     ColorLit(RGBA),
+    /// Represents LaTeX `\phantom{..}`, `\hphantom{..}` and `\vphantom{..}`
+    Phantom { horizontal: bool, vertical: bool },
+    /// Represents amsmath's `\smash[..]{..}`
+    Smash,
+    /// Represents LaTeX `\mathstrut`, i.e. `\vphantom{(}`
+    MathStrut,
+    /// Represents LaTeX `\overline{..}` and `\underline{..}`, and `\overbar{..}` and `\underbar{..}` (tight variants)
+    Line { placement: LinePlacement, tight: bool },
+    /// Represents the `cancel` package's `\cancel{..}`, `\bcancel{..}` and `\xcancel{..}`
+    Cancel(CancelDirection),
+    /// Represents amsmath's `\boxed{..}`
+    Boxed,
+    /// Represents amsmath's `\overset{..}{..}` and `\underset{..}{..}`, and LaTeX's `\stackrel{..}{..}` whose result is always a relation
+    OverUnderSet { placement: LinePlacement, relation: bool },
+    /// Represents amsmath's `\xrightarrow[..]{..}` and `\xleftarrow[..]{..}`, with the codepoint of the arrow
+    ExtensibleArrow(char),
     /// Represents LaTeX `\frac{..}`
     Fraction(Option<Symbol>, Option<Symbol>, BarThickness, MathStyle),
+    /// Represents amsmath's `\cfrac[..]{..}{..}`
+    ContinuedFraction,
+    /// Represents amsmath's `\genfrac{..}{..}{..}{..}{..}{..}`
+    GeneralizedFraction,
+    /// Represents TeX's infix fraction commands `\over`, `\atop`, `\overwithdelims` and `\atopwithdelims`: 
+    /// everything before the command in the current group is the numerator, everything after it is the denominator
+    InfixFraction { bar_thickness: BarThickness, with_delimiters: bool },
     /// Represents `\limits` and `\nolimits` control sequences (cf [here](https://texfaq.org/FAQ-limits))
     Limits(bool),
     ExtendedDelimiter(DelimiterSize, TexSymbolType),
     Kerning(AnyUnit),
+    /// Represents LaTeX `\hspace{..}` and `\hspace*{..}`
+    HSpace,
+    /// Represents amsmath's `\mspace{..}`, whose argument is in math units (e.g. `\mspace{-3mu}`)
+    MSpace,
     StyleCommand(LayoutStyle),
     AtomChange(TexSymbolType),
     TextOperator(&'static str, bool),
     SubStack(TexSymbolType),
     SymbolCommand(Symbol),
+    /// Represents LaTeX `\smallint`, an operator which is not enlarged in display style
+    SmallOperator(Symbol),
     StyleChange { family: Option<Family>, weight: Option<Weight>, takes_arg : bool },
     BeginEnv,
     EndEnv,
@@ -31,6 +60,16 @@ pub enum PrimitiveControlSequence {
     Middle,
     Right,
     Text,
+    /// Represents LaTeX `\mbox{..}`: like `\text{..}`, except it ignores font size changes in sub-/super-scripts
+    MBox,
+    /// Represents LaTeX `\makebox[..][..]{..}`
+    MakeBox,
+    /// Represents mathtools' `\mathllap{..}`, `\mathrlap{..}` and `\mathclap{..}`, with the alignment of the content on the insertion point
+    MathLap(ArrayColumnAlign),
+    /// Represents LaTeX `\multicolumn{..}{..}{..}`
+    MultiColumn,
+    /// Commands which have no effect on a formula, e.g. `\relax`, `\nobreak` ; some take an optional argument, e.g. `\displaybreak[..]`
+    Ignored { optional_argument: bool },
 }
 
 
@@ -48,6 +87,7 @@ pub enum DelimiterSize {
 }
 
 impl DelimiterSize {
+    /// Height of the content that a delimiter of this size is meant to enclose
     pub fn to_size(self) -> Unit<Em> {
         constants::BIG_HEIGHT.scale(match self {
             DelimiterSize::Big   => 1.0,
@@ -56,6 +96,16 @@ impl DelimiterSize {
             DelimiterSize::BBigg => 2.5,
         })
     }
+
+    /// Rank of the size among the enlarged versions of a delimiter, starting from 1 for [`DelimiterSize::Big`]
+    pub fn rank(self) -> usize {
+        match self {
+            DelimiterSize::Big   => 1,
+            DelimiterSize::BBig  => 2,
+            DelimiterSize::Bigg  => 3,
+            DelimiterSize::BBigg => 4,
+        }
+    }
 }
 
 
@@ -79,6 +129,12 @@ impl PrimitiveControlSequence {
             "binom"  => Self::Fraction(OPEN_PAREN, CLOSE_PAREN, BarThickness::None,    MathStyle::NoChange),
             "tbinom" => Self::Fraction(OPEN_PAREN, CLOSE_PAREN, BarThickness::None,    MathStyle::Text),
             "dbinom" => Self::Fraction(OPEN_PAREN, CLOSE_PAREN, BarThickness::None,    MathStyle::Display),
+            "cfrac"  => Self::ContinuedFraction,
+            "genfrac" => Self::GeneralizedFraction,
+            "over"           => Self::InfixFraction { bar_thickness: BarThickness::Default, with_delimiters: false },
+            "atop"           => Self::InfixFraction { bar_thickness: BarThickness::None,    with_delimiters: false },
+            "overwithdelims" => Self::InfixFraction { bar_thickness: BarThickness::Default, with_delimiters: true },
+            "atopwithdelims" => Self::InfixFraction { bar_thickness: BarThickness::None,    with_delimiters: true },
 
             // Stacking commands
             "substack" => Self::SubStack(TexSymbolType::Inner),
@@ -86,6 +142,25 @@ impl PrimitiveControlSequence {
             // Radical commands
             "sqrt" => Self::Radical,
 
+            // Lines over and under
+            "overline"  => Self::Line { placement: LinePlacement::Over,  tight: false },
+            "underline" => Self::Line { placement: LinePlacement::Under, tight: false },
+            "overbar"   => Self::Line { placement: LinePlacement::Over,  tight: true },
+            "underbar"  => Self::Line { placement: LinePlacement::Under, tight: true },
+            "cancel"    => Self::Cancel(CancelDirection::Forward),
+            "bcancel"   => Self::Cancel(CancelDirection::Backward),
+            "xcancel"   => Self::Cancel(CancelDirection::Cross),
+            "boxed"     => Self::Boxed,
+
+            // Annotations over and under
+            "overset"  => Self::OverUnderSet { placement: LinePlacement::Over,  relation: false },
+            "underset" => Self::OverUnderSet { placement: LinePlacement::Under, relation: false },
+            "stackrel" => Self::OverUnderSet { placement: LinePlacement::Over,  relation: true },
+
+            // Arrows stretched under and over labels
+            "xrightarrow" => Self::ExtensibleArrow('→'),
+            "xleftarrow"  => Self::ExtensibleArrow('←'),
+
             // Style-change command
             "mathbf"   => Self::StyleChange {family: None,                     weight: Some(Weight::Bold),   takes_arg: true, },
             "mathit"   => Self::StyleChange {family: None,                     weight: Some(Weight::Italic), takes_arg: true, },
@@ -131,6 +206,8 @@ impl PrimitiveControlSequence {
             " "     => Self::Kerning(AnyUnit::Em(1f64/4f64)),
             "quad"  => Self::Kerning(AnyUnit::Em(1.0f64)),
             "qquad" => Self::Kerning(AnyUnit::Em(2.0f64)),
+            "hspace" => Self::HSpace,
+            "mspace" => Self::MSpace,
             "rule"  => Self::Rule,
 
 
@@ -140,6 +217,20 @@ impl PrimitiveControlSequence {
             "scriptstyle"       => Self::StyleCommand(LayoutStyle::Script),
             "scriptscriptstyle" => Self::StyleCommand(LayoutStyle::ScriptScript),
             "text"              => Self::Text,
+            "mbox"              => Self::MBox,
+            "makebox"           => Self::MakeBox,
+            "mathllap"          => Self::MathLap(ArrayColumnAlign::Right),
+            "mathrlap"          => Self::MathLap(ArrayColumnAlign::Left),
+            "mathclap"          => Self::MathLap(ArrayColumnAlign::Centered),
+
+            // Arrays
+            "multicolumn" => Self::MultiColumn,
+
+            // Commands which control line breaks and expansion in LaTeX, meaningless here
+            "relax"        => Self::Ignored { optional_argument: false },
+            "nobreak"      => Self::Ignored { optional_argument: false },
+            "allowbreak"   => Self::Ignored { optional_argument: false },
+            "displaybreak" => Self::Ignored { optional_argument: true },
 
             // Atom-type changes
             "mathop"  => Self::AtomChange(TexSymbolType::Operator(false)),
@@ -151,7 +242,11 @@ impl PrimitiveControlSequence {
             "blue"    => Self::ColorLit(RGBA(0,0,0xff,0xff)),
             "red"     => Self::ColorLit(RGBA(0xff,0,0,0xff)),
             "gray"    => Self::ColorLit(RGBA(0x80,0x80,0x80,0xff)),
-            "phantom" => Self::ColorLit(RGBA(0,0,0,0)),
+            "phantom"  => Self::Phantom { horizontal: true, vertical: true },
+            "vphantom" => Self::Phantom { horizontal: false, vertical: true },
+            "hphantom" => Self::Phantom { horizontal: true,  vertical: false },
+            "mathstrut" => Self::MathStrut,
+            "smash"     => Self::Smash,
 
             // Operators with limits
             "det"     => Self::TextOperator("det", true),
@@ -190,6 +285,9 @@ impl PrimitiveControlSequence {
             "ln"      => Self::TextOperator("ln", false),
             "log"     => Self::TextOperator("log", false),
 
+            // Operators which are not enlarged in display style
+            "smallint" => Self::SmallOperator(Symbol { codepoint: '∫', atom_type: TexSymbolType::Operator(false) }),
+
             // Environment
             "begin" => Self::BeginEnv,
             "end"   => Self::EndEnv,
@@ -4,9 +4,9 @@ use crate::dimensions::AnyUnit;
 use crate::layout;
 use crate::layout::constants::JOT;
 use crate::parser::error::ParseError;
-use crate::parser::{tokens_as_string, List};
+use crate::parser::{parse_dimension, tokens_as_string, List};
 
-use super::nodes::{Array, ArrayColumnAlign, ArrayColumnsFormatting, ColSeparator, DummyNode};
+use super::nodes::{as_multicolumn, cell_span, Array, ArrayColumnAlign, ArrayColumnsFormatting, ColSeparator, DummyNode, MultiColumn};
 use super::symbols::Symbol;
 use super::{error::ParseResult, nodes::CellContent, textoken::TexToken, Parser};
 use super::{GroupKind, ParseNode};
@@ -21,7 +21,13 @@ pub enum Environment {
     BbMatrix,
     VMatrix,
     VvMatrix,
+    /// `\begin{smallmatrix} .. \end{smallmatrix}`, whose cells are in script style and tightly packed
+    SmallMatrix,
     Aligned,
+    /// `\begin{gathered} .. \end{gathered}`, whose rows are centered formulas in display style
+    Gathered,
+    /// `\begin{equation} .. \end{equation}`, a single formula in display style
+    Equation,
 }
 
 impl Environment {
@@ -34,29 +40,64 @@ impl Environment {
             "Bmatrix"  => Some(Self::BbMatrix),
             "vmatrix"  => Some(Self::VMatrix),
             "Vmatrix"  => Some(Self::VvMatrix),
+            "smallmatrix" => Some(Self::SmallMatrix),
             "aligned"  => Some(Self::Aligned),
+            // ReX does not number equations: `align` and `align*` are the same as `aligned`
+            "align"    => Some(Self::Aligned),
+            "align*"   => Some(Self::Aligned),
+            "gathered" => Some(Self::Gathered),
+            "gather"   => Some(Self::Gathered),
+            "gather*"  => Some(Self::Gathered),
+            "equation"  => Some(Self::Equation),
+            "equation*" => Some(Self::Equation),
             _ => None
         }
     }
 }
 
+/// The rows of an array, as parsed by [`Parser::parse_array_body`]
+pub struct ArrayBody {
+    /// Cf [`Array::rows`]
+    pub rows: Vec<Vec<CellContent>>,
+    /// Cf [`Array::row_spacing`]
+    pub row_spacing: Vec<Option<AnyUnit>>,
+}
 
 impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
+    /// Parses the contents of `\begin{equation} .. \end{equation}`: unlike other environments, it is not an array, 
+    /// so that `&` and `\\` are not allowed in it
+    pub fn parse_equation(&mut self) -> ParseResult<Vec<ParseNode>> {
+        let List { mut nodes, group } = self.parse_until_end_of_group()?;
+        if group != GroupKind::Env(Environment::Equation) {
+            return Err(ParseError::UnexpectedEndGroup { expected: Box::from([GroupKind::Env(Environment::Equation)]), got: group });
+        }
+
+        nodes.insert(0, ParseNode::Style(layout::Style::Display));
+        Ok(nodes)
+    }
+
     pub fn parse_environment(&mut self, env : Environment) -> ParseResult<Array> {
         let mut col_format = None;
 
         if let Environment::Array = env {
             let group = self.token_iter
-                .capture_group()
+                .capture_located_group()
                 .map_err(|e| match e {
                     ParseError::ExpectedToken => ParseError::MissingColFormatForArrayEnvironment,
                     _ => e,
                 })?;
 
-            let mut forked_parser = Parser::from_iter(Self::EMPTY_COMMAND_COLLECTION, group.into_iter());
-            col_format = Some(forked_parser.tokens_as_column_format()?);
+            // errors in the column format are located at the offending token of the format
+            let mut forked_parser = self.fork_located(group);
+            match forked_parser.tokens_as_column_format() {
+                Ok(format) => col_format = Some(format),
+                Err(error) => {
+                    self.item_start = forked_parser.error_position().or(self.item_start);
+                    return Err(error);
+                },
+            }
         }
-        let mut rows = self.parse_array_body(env)?;
+        let ArrayBody { mut rows, row_spacing } = self.parse_array_body(env)?;
 
         let left_delimiter;
         let right_delimiter;
@@ -64,7 +105,10 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
         match env {
             Environment::Array   |
             Environment::Matrix  | 
-            Environment::Aligned
+            Environment::SmallMatrix |
+            Environment::Aligned |
+            Environment::Gathered |
+            Environment::Equation
             => {
                 left_delimiter  = None;
                 right_delimiter = None;
@@ -105,51 +149,92 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
             }
         }
 
-        let col_format = col_format.unwrap_or_else(|| {
-            let n_cols = rows.last().map_or(0, |row| row.len());
+        let mut col_format = col_format.unwrap_or_else(|| {
+            // Without an explicit column format, the array has as many columns as its widest row
+            let n_cols = rows.iter().map(|row| row.iter().map(|cell| cell_span(cell)).sum()).max().unwrap_or(0);
             if let Environment::Aligned = env {
                 ArrayColumnsFormatting {
                     alignment:  [ArrayColumnAlign::Right, ArrayColumnAlign::Left].iter().cycle().cloned().take(n_cols).collect(),
                     separators: [Vec::new(), vec![ColSeparator::AtExpression(Vec::new())]].iter().cycle().cloned().take(n_cols + 1).collect(),
+                    widths:     vec![None; n_cols],
                 }
             }
             else {
                 ArrayColumnsFormatting { 
                     alignment:  vec![ArrayColumnAlign::Centered; n_cols], 
                     separators: vec![vec![]; n_cols + 1], 
+                    widths:     vec![None; n_cols],
                 }
             }
         });
 
+        // A cell spanning several columns may not go past the last column of the format
+        let n_cols = col_format.alignment.len();
+        for (row, cells) in rows.iter().enumerate() {
+            let mut column = 0;
+            for cell in cells.iter() {
+                let span = cell_span(cell);
+                if as_multicolumn(cell).is_some() && column + span > n_cols {
+                    return Err(ParseError::MultiColumnTooWide { row, span, remaining: n_cols.saturating_sub(column) });
+                }
+                column += span;
+            }
+        }
+
+        if self.settings.get_strict_arrays() {
+            let expected = col_format.alignment.len();
+            for (row, cells) in rows.iter().enumerate() {
+                let got = cells.iter().map(|cell| cell_span(cell)).sum();
+                if got != expected {
+                    return Err(ParseError::RaggedArrayRow { row, expected, got });
+                }
+            }
+        }
+
+        // Extra cells go in implicit centered columns, appended to the column format
+        let widest_row = rows.iter().map(|row| row.iter().map(|cell| cell_span(cell)).sum()).max().unwrap_or(0);
+        while col_format.alignment.len() < widest_row {
+            col_format.alignment.push(ArrayColumnAlign::Centered);
+            col_format.separators.push(Vec::new());
+            col_format.widths.push(None);
+        }
+
         let extra_row_sep = match env {
-            Environment::Aligned => true,
+            Environment::Aligned | Environment::Gathered | Environment::Equation => true,
             Environment::Array | Environment::Matrix | Environment::PMatrix 
             | Environment::BMatrix | Environment::BbMatrix | Environment::VMatrix 
-            | Environment::VvMatrix 
+            | Environment::VvMatrix | Environment::SmallMatrix
             => false,
         };
 
         let cell_layout_style = match env {
-            Environment::Aligned => layout::Style::Display,
+            Environment::Aligned | Environment::Gathered | Environment::Equation => layout::Style::Display,
+            Environment::SmallMatrix => layout::Style::Script,
             Environment::Array | Environment::Matrix | Environment::PMatrix 
             | Environment::BMatrix | Environment::BbMatrix | Environment::VMatrix 
             | Environment::VvMatrix 
             => layout::Style::Text,
         };
 
+        let tight_spacing = matches!(env, Environment::SmallMatrix);
+
         Ok(Array {
             col_format,
             rows,
+            row_spacing,
             left_delimiter,
             right_delimiter,
             extra_row_sep,
             cell_layout_style,
+            tight_spacing,
         })
     }
 
 
-    pub fn parse_array_body(&mut self, env : Environment) -> ParseResult<Vec<Vec<CellContent>>> {
+    /// Parses the rows of an array, along with the extra space after each row (cf [`Array::row_spacing`])
+    pub fn parse_array_body(&mut self, env : Environment) -> ParseResult<ArrayBody> {
         let mut to_return    = Vec::new();
+        let mut row_spacing  = Vec::new();
         let mut current_line = Vec::new();
 
         while {
@@ -160,6 +245,7 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                     if !current_line.is_empty() || !nodes.is_empty() {
                         current_line.push(nodes);
                         to_return.push(std::mem::take(&mut current_line));
+                        row_spacing.push(None);
                     }
                     false
                 },
@@ -170,6 +256,7 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                 GroupKind::NewLine => {
                     current_line.push(nodes);
                     to_return.push(std::mem::take(&mut current_line));
+                    row_spacing.push(self.optional_row_spacing()?);
                     true
                 },
 
@@ -179,17 +266,76 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
         {}
 
 
-        Ok(to_return)
+        Ok(ArrayBody { rows: to_return, row_spacing })
+    }
+
+    /// Parses the optional dimension following `\\`, e.g. `[-0.2em]` in `a \\[-0.2em] b`.
+    fn optional_row_spacing(&mut self) -> ParseResult<Option<AnyUnit>> {
+        match self.optional_bracket_argument()? {
+            Some(dimension) => Ok(Some(parse_dimension(dimension.trim())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses the arguments of `\multicolumn{span}{align}{..}`, e.g. `{2}{c}{x + y}`.
+    /// The alignment must consist of a single column format ; vertical bars are ignored.
+    pub fn parse_multicolumn(&mut self, control_sequence_name : &str) -> ParseResult<MultiColumn> {
+        let capture_argument = |parser : &mut Self| parser.token_iter.capture_group().map_err(|e| match e {
+            ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(control_sequence_name)),
+            _ => e,
+        });
+
+        let span_group = capture_argument(self)?;
+        let span_string = tokens_as_string(span_group.into_iter())?;
+        let span = match span_string.trim().parse::<usize>() {
+            Ok(span) if span > 0 && span <= MAX_COLUMN_SPAN => span,
+            _ => return Err(ParseError::UnrecognizedColumnSpan(span_string.into_boxed_str())),
+        };
+
+        let alignment_group = capture_argument(self)?;
+        let mut forked_parser = Parser::from_iter(Self::EMPTY_COMMAND_COLLECTION, alignment_group.into_iter());
+        let alignment = match forked_parser.tokens_as_column_format()?.alignment.as_slice() {
+            [alignment] => *alignment,
+            _ => return Err(ParseError::UnrecognizedArrayColumnFormat),
+        };
+
+        let inner = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+
+        Ok(MultiColumn {
+            span,
+            alignment,
+            inner,
+        })
     }
 }
 
+/// Largest number of repetitions allowed in `*{..}{..}` in a column format, e.g. `3` in `*{3}{c}`.
+/// Since the repeated specification is copied back into the token stream, a larger count could exhaust memory.
+pub const MAX_COLUMN_REPETITIONS : usize = 1000;
+
+/// Largest number of columns a cell may span with `\multicolumn{..}{..}{..}`, e.g. `3` in `\multicolumn{3}{c}{..}`.
+/// Matrices have as many columns as their widest row, so a larger span could make for a very wide grid.
+pub const MAX_COLUMN_SPAN : usize = 1000;
+
+/// Largest number of tokens that all repetitions `*{..}{..}` of a column format may expand to.
+/// This bounds the expansion of long repeated specifications, e.g. `*{1000}{cccc..}`, and of many repetitions in a row.
+pub const MAX_REPEATED_COLUMN_TOKENS : usize = 10_000;
+
 impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
     fn tokens_as_column_format(&mut self) -> ParseResult<ArrayColumnsFormatting> {
         let mut n_vertical_bars_before = 0;
         let mut current_vertical_bars = &mut n_vertical_bars_before;
         let mut alignment  = Vec::new();
         let mut separators = vec![Vec::new()];
-        while let Some(token) = self.token_iter.next_token()? {
+        let mut widths     = Vec::new();
+        let mut n_repeated_tokens : usize = 0;
+        loop {
+            // errors are located at the token being read, cf `Parser::error_position`
+            self.item_start = self.token_iter.offset();
+            let token = match self.token_iter.next_token()? {
+                Some(token) => token,
+                None => break,
+            };
             match token {
                   TexToken::Char(c@'c') 
                 | TexToken::Char(c@'l') 
@@ -202,6 +348,15 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                         _   => unreachable!(), // This has already been ruled out in the previous match
                     });
                     separators.push(Vec::new());
+                    widths.push(None);
+                },
+                TexToken::Char('p') => {
+                    // ReX does not break lines: `p{width}` is a left-aligned column of fixed width
+                    let width_group = self.token_iter.capture_group()?;
+                    let width = parse_dimension(tokens_as_string(width_group.into_iter())?.trim())?;
+                    alignment.push(ArrayColumnAlign::Left);
+                    separators.push(Vec::new());
+                    widths.push(Some(width));
                 },
                 TexToken::Char('|') 
                 => {
@@ -223,6 +378,36 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                     let current_separators = separators.last_mut().unwrap();
                     current_separators.push(ColSeparator::AtExpression(nodes));
                 }
+                TexToken::Char('*') => {
+                    // `*{n}{spec}` is the same as `spec` repeated n times: the copies are put back into the token stream
+                    let count_group = self.token_iter.capture_group()?;
+                    let count_string = tokens_as_string(count_group.into_iter())?;
+                    let count = count_string.trim().parse::<usize>()
+                        .map_err(|_| ParseError::UnrecognizedColumnRepetition(count_string.into_boxed_str()))?;
+                    if count > MAX_COLUMN_REPETITIONS {
+                        return Err(ParseError::TooManyColumnRepetitions(count));
+                    }
+                    let spec = self.token_iter.capture_group()?;
+                    // `*` inside a braced group, e.g. in `@{*}`, is not a repetition
+                    let mut depth : u32 = 0;
+                    for token in spec.iter() {
+                        match token {
+                            TexToken::BeginGroup => depth += 1,
+                            TexToken::EndGroup   => depth -= 1,
+                            TexToken::Char('*') if depth == 0 => return Err(ParseError::NestedColumnRepetition),
+                            _ => (),
+                        }
+                    }
+                    n_repeated_tokens = n_repeated_tokens.saturating_add(count.saturating_mul(spec.len()));
+                    if n_repeated_tokens > MAX_REPEATED_COLUMN_TOKENS {
+                        return Err(ParseError::RepeatedColumnFormatTooLong);
+                    }
+                    for _ in 0 .. count {
+                        for token in spec.iter().rev() {
+                            self.token_iter.push_back(token.clone());
+                        }
+                    }
+                }
                 TexToken::Char(_) => {
                     return Err(ParseError::UnrecognizedArrayColumnFormat);
                 }
@@ -241,6 +426,7 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
         Ok(ArrayColumnsFormatting {
             alignment,
             separators,
+            widths,
         })
     }
 }
@@ -258,16 +444,19 @@ mod tests {
             ArrayColumnsFormatting {
                 alignment  : vec![ArrayColumnAlign::Centered], 
                 separators : vec![vec![], vec![]],
+                widths     : vec![None; 1],
             }),
             ("||l", 
             ArrayColumnsFormatting {
                 alignment  : vec![ArrayColumnAlign::Left], 
                 separators : vec![vec![ColSeparator::VerticalBars(2)], vec![]],
+                widths     : vec![None; 1],
             }),
             ("||c|l", 
             ArrayColumnsFormatting {
                 alignment  : vec![ArrayColumnAlign::Centered, ArrayColumnAlign::Left], 
                 separators : vec![vec![ColSeparator::VerticalBars(2)], vec![ColSeparator::VerticalBars(1)], vec![]],
+                widths     : vec![None; 2],
             }),
             ("|  |c l|l|", 
             ArrayColumnsFormatting {
@@ -282,6 +471,7 @@ mod tests {
                     vec![ColSeparator::VerticalBars(1)], 
                     vec![ColSeparator::VerticalBars(1)], 
                 ],
+                widths     : vec![None; 3],
             }),
             (" |  r| l|| | |r||  ", 
             ArrayColumnsFormatting {
@@ -296,6 +486,59 @@ mod tests {
                     vec![ColSeparator::VerticalBars(4)], 
                     vec![ColSeparator::VerticalBars(2)], 
                 ],
+                widths     : vec![None; 3],
+            }),
+            ("*{3}{c|}", 
+            ArrayColumnsFormatting {
+                alignment  : vec![
+                    ArrayColumnAlign::Centered, 
+                    ArrayColumnAlign::Centered, 
+                    ArrayColumnAlign::Centered,
+                ], 
+                separators : vec![
+                    vec![], 
+                    vec![ColSeparator::VerticalBars(1)], 
+                    vec![ColSeparator::VerticalBars(1)], 
+                    vec![ColSeparator::VerticalBars(1)], 
+                ],
+                widths     : vec![None; 3],
+            }),
+            ("l*{2}{r}|", 
+            ArrayColumnsFormatting {
+                alignment  : vec![
+                    ArrayColumnAlign::Left, 
+                    ArrayColumnAlign::Right, 
+                    ArrayColumnAlign::Right,
+                ], 
+                separators : vec![
+                    vec![], 
+                    vec![], 
+                    vec![], 
+                    vec![ColSeparator::VerticalBars(1)], 
+                ],
+                widths     : vec![None; 3],
+            }),
+            ("c*{0}{l|}r", 
+            ArrayColumnsFormatting {
+                alignment  : vec![
+                    ArrayColumnAlign::Centered, 
+                    ArrayColumnAlign::Right,
+                ], 
+                separators : vec![vec![], vec![], vec![]],
+                widths     : vec![None; 2],
+            }),
+            ("p{2em}|c", 
+            ArrayColumnsFormatting {
+                alignment  : vec![
+                    ArrayColumnAlign::Left, 
+                    ArrayColumnAlign::Centered,
+                ], 
+                separators : vec![
+                    vec![], 
+                    vec![ColSeparator::VerticalBars(1)], 
+                    vec![], 
+                ],
+                widths     : vec![Some(AnyUnit::Em(2.)), None],
             }),
         ];
 
@@ -309,6 +552,47 @@ mod tests {
             );
 
         }
+
+        let command_collection = CommandCollection::new();
+        let mut parser = Parser::new(&command_collection, "*{x}{c}");
+        assert_eq!(
+            parser.tokens_as_column_format(),
+            Err(ParseError::UnrecognizedColumnRepetition(Box::from("x"))),
+        );
+
+        let mut parser = Parser::new(&command_collection, "*{20000000}{c}");
+        assert_eq!(
+            parser.tokens_as_column_format(),
+            Err(ParseError::TooManyColumnRepetitions(20000000)),
+        );
+        let mut parser = Parser::new(&command_collection, "*{1000}{c}");
+        assert_eq!(parser.tokens_as_column_format().unwrap().alignment.len(), 1000);
+
+        // neither a long repeated specification nor many repetitions can get around the limit
+        let long_spec = format!("*{{1000}}{{{}}}", "c".repeat(1000));
+        let mut parser = Parser::new(&command_collection, &long_spec);
+        assert_eq!(
+            parser.tokens_as_column_format(),
+            Err(ParseError::RepeatedColumnFormatTooLong),
+        );
+        let many_repetitions = "*{1000}{cc}".repeat(6);
+        let mut parser = Parser::new(&command_collection, &many_repetitions);
+        assert_eq!(
+            parser.tokens_as_column_format(),
+            Err(ParseError::RepeatedColumnFormatTooLong),
+        );
+
+        let mut parser = Parser::new(&command_collection, "*{2}{c*{3}{l}}");
+        assert_eq!(
+            parser.tokens_as_column_format(),
+            Err(ParseError::NestedColumnRepetition),
+        );
+
+        let mut parser = Parser::new(&command_collection, "p{wide}");
+        assert_eq!(
+            parser.tokens_as_column_format(),
+            Err(ParseError::UnrecognizedDimension(Box::from("wide"))),
+        );
         
     }
 
@@ -320,4 +604,139 @@ mod tests {
         let result = parser.parse_environment(Environment::PMatrix);
         result.unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn ragged_arrays() {
+        use crate::parser::ParserSettings;
+
+        let collection = CommandCollection::default();
+        let strict = ParserSettings::new().strict_arrays(true);
+
+        // By default, the missing cell of the second row is left empty
+        let mut parser = Parser::new(&collection, r"{cc} 1 & 2 \\ 3 \end{array}");
+        let array = parser.parse_environment(Environment::Array).unwrap();
+        assert_eq!(array.rows[1].len(), 1);
+
+        let mut parser = Parser::new(&collection, r"{cc} 1 & 2 \\ 3 \end{array}").with_settings(&strict);
+        assert_eq!(
+            parser.parse_environment(Environment::Array).unwrap_err(),
+            ParseError::RaggedArrayRow { row: 1, expected: 2, got: 1 },
+        );
+
+        let mut parser = Parser::new(&collection, r"{c} 1 & 2 \end{array}").with_settings(&strict);
+        assert_eq!(
+            parser.parse_environment(Environment::Array).unwrap_err(),
+            ParseError::RaggedArrayRow { row: 0, expected: 1, got: 2 },
+        );
+
+        // In matrices, the number of columns is that of the widest row, so the short row is reported
+        let mut parser = Parser::new(&collection, r"a & b \\ c \end{pmatrix}").with_settings(&strict);
+        assert_eq!(
+            parser.parse_environment(Environment::PMatrix).unwrap_err(),
+            ParseError::RaggedArrayRow { row: 1, expected: 2, got: 1 },
+        );
+
+        // By default, the short last row does not remove columns from the matrix
+        let mut parser = Parser::new(&collection, r"a & b \\ c \end{pmatrix}");
+        let array = parser.parse_environment(Environment::PMatrix).unwrap();
+        assert_eq!(array.col_format.alignment.len(), 2);
+
+        // By default, extra cells get columns of their own
+        let mut parser = Parser::new(&collection, r"{c|} 1 & 2 \\ 3 \end{array}");
+        let array = parser.parse_environment(Environment::Array).unwrap();
+        assert_eq!(array.col_format.alignment, vec![ArrayColumnAlign::Centered; 2]);
+        assert_eq!(array.col_format.separators, vec![vec![], vec![ColSeparator::VerticalBars(1)], vec![]]);
+        assert_eq!(array.col_format.widths, vec![None; 2]);
+
+        // Cells spanning several columns count as many times
+        let mut parser = Parser::new(&collection, r"{cc} 1 & 2 \\ \multicolumn{2}{c}{3} \end{array}").with_settings(&strict);
+        parser.parse_environment(Environment::Array).unwrap();
+        let mut parser = Parser::new(&collection, r"1 & 2 \\ 3 & 4 \end{pmatrix}").with_settings(&strict);
+        parser.parse_environment(Environment::PMatrix).unwrap();
+    }
+
+    #[test]
+    fn multicolumn_spans_are_bounded() {
+        let collection = CommandCollection::default();
+
+        // A cell may not span past the last column of the format
+        let mut parser = Parser::new(&collection, r"{cc} \multicolumn{3}{c}{x} \end{array}");
+        assert_eq!(
+            parser.parse_environment(Environment::Array).unwrap_err(),
+            ParseError::MultiColumnTooWide { row: 0, span: 3, remaining: 2 },
+        );
+        let mut parser = Parser::new(&collection, r"{ccc} 1 & 2 & 3 \\ 1 & \multicolumn{3}{c}{x} \end{array}");
+        assert_eq!(
+            parser.parse_environment(Environment::Array).unwrap_err(),
+            ParseError::MultiColumnTooWide { row: 1, span: 3, remaining: 2 },
+        );
+        let mut parser = Parser::new(&collection, r"{ccc} 1 & \multicolumn{2}{c}{x} \end{array}");
+        parser.parse_environment(Environment::Array).unwrap();
+
+        // Without a column format, a span adds columns, up to a limit
+        let mut parser = Parser::new(&collection, r"a \\ \multicolumn{3}{c}{x} \end{matrix}");
+        assert_eq!(parser.parse_environment(Environment::Matrix).unwrap().col_format.alignment.len(), 3);
+        let mut parser = Parser::new(&collection, r"\multicolumn{100000}{c}{x} \end{matrix}");
+        assert_eq!(
+            parser.parse_environment(Environment::Matrix).unwrap_err(),
+            ParseError::UnrecognizedColumnSpan(Box::from("100000")),
+        );
+    }
+
+    #[test]
+    fn starred_environments() {
+        use crate::parser::parse;
+
+        assert_eq!(Environment::from_name("align*"), Some(Environment::Aligned));
+        assert_eq!(
+            parse(r"\begin{align*} a &= b \\ c &= d \end{align*}"),
+            parse(r"\begin{aligned} a &= b \\ c &= d \end{aligned}"),
+        );
+        assert_eq!(
+            parse(r"\begin{align} a &= b \end{align}"),
+            parse(r"\begin{aligned} a &= b \end{aligned}"),
+        );
+
+        assert_eq!(Environment::from_name("gather*"), Some(Environment::Gathered));
+        assert_eq!(
+            parse(r"\begin{gather*} a = b \\ c \end{gather*}"),
+            parse(r"\begin{gathered} a = b \\ c \end{gathered}"),
+        );
+
+        // `equation*` is a single formula in display style
+        assert_eq!(Environment::from_name("equation*"), Some(Environment::Equation));
+        assert_eq!(
+            parse(r"\begin{equation*} \sum_i a_i \end{equation*}"),
+            parse(r"{\displaystyle \sum_i a_i}"),
+        );
+        assert!(matches!(
+            parse(r"\begin{equation*} a \\ b \end{equation*}").map_err(|e| e.error),
+            Err(ParseError::UnexpectedEndGroup { got: GroupKind::NewLine, .. }),
+        ));
+    }
+
+    #[test]
+    fn matrix_delimiters() {
+        use crate::parser::{parse, nodes::ParseNode};
+
+        let delimiters = [
+            ("matrix",  None),
+            ("pmatrix", Some(('(', ')'))),
+            ("bmatrix", Some(('[', ']'))),
+            ("Bmatrix", Some(('{', '}'))),
+            ("vmatrix", Some(('|', '|'))),
+            ("Vmatrix", Some(('\u{2016}', '\u{2016}'))),
+        ];
+        for (name, expected) in delimiters {
+            let nodes = parse(&format!(r"\begin{{{0}}} a & b & c \\ d & e & f \end{{{0}}}", name)).unwrap();
+            let array = match nodes.as_slice() {
+                [ParseNode::Array(array)] => array,
+                nodes => panic!("expected an array for {}, got {:?}", name, nodes),
+            };
+            let left  = array.left_delimiter .map(|symbol| symbol.codepoint);
+            let right = array.right_delimiter.map(|symbol| symbol.codepoint);
+            assert_eq!(left.zip(right), expected, "delimiters of {}", name);
+            assert_eq!(array.col_format.alignment, vec![ArrayColumnAlign::Centered; 3], "alignment of {}", name);
+        }
+    }
+}
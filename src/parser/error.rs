@@ -47,13 +47,49 @@ pub enum ParseError {
     MissingSubSuperScript,
     /// There either is more than one subscript or more than one superscript attached to the same node.
     TooManySubscriptsOrSuperscripts,
-    /// The command `\rule` expects an argument of the form `1.3pt` (number followed by dimension). The dimension may not be anything but `em` or `pt` at the moment.
+    /// Commands like `\rule` or `\hspace` expect an argument of the form `1.3pt` (number followed by dimension). The dimension may not be anything but `em`, `px`, `pt` or `mu` at the moment.
     UnrecognizedDimension(Box<str>),
+    /// `\mspace` expects a length in math units, e.g. `\mspace{3mu}` but not `\mspace{3em}`, as in amsmath.
+    ExpectedMathUnits(Box<str>),
     /// The string in `\begin{..}` or `\end{..}` is not a recognized environment. Cf [Environment] for the list of supported LaTeX environments.
     UnrecognizedEnvironment(Box<str>),
+    /// The first argument of `\multicolumn{..}{..}{..}` is not a positive integer, or is larger than [`MAX_COLUMN_SPAN`](super::environments::MAX_COLUMN_SPAN)
+    UnrecognizedColumnSpan(Box<str>),
+    /// A cell `\multicolumn{..}{..}{..}` spans more columns than remain in its row of the array
+    MultiColumnTooWide {
+        /// index of the row, starting from 0
+        row       : usize,
+        /// number of columns spanned by the cell
+        span      : usize,
+        /// number of columns of the array from the cell onwards
+        remaining : usize,
+    },
+    /// The position argument of `\makebox[..][..]{..}` or `\cfrac[..]{..}{..}` is not one of `c`, `l` or `r`
+    UnrecognizedBoxPosition(Box<str>),
+    /// The position argument of `\smash[..]{..}` is not one of `t` or `b`
+    UnrecognizedSmashPosition(Box<str>),
+    /// The style argument of `\genfrac{..}{..}{..}{..}{..}{..}` is neither empty nor one of `0`, `1`, `2` or `3`
+    UnrecognizedFractionStyle(Box<str>),
     /// The argument of `\begin{array}{..}` is not of the correct form: 
     /// it can only contain the characters `c`, `l`, `r`, whitespaces, braces, `|`  or macros that ultimately expand to one of these.
     UnrecognizedArrayColumnFormat,
+    /// The number of repetitions in `*{..}{..}` in a column format (e.g. `3` in `*{3}{c}`) is not a number
+    UnrecognizedColumnRepetition(Box<str>),
+    /// The number of repetitions in `*{..}{..}` in a column format is larger than [`MAX_COLUMN_REPETITIONS`](super::environments::MAX_COLUMN_REPETITIONS)
+    TooManyColumnRepetitions(usize),
+    /// The repetitions `*{..}{..}` of a column format expand to more than [`MAX_REPEATED_COLUMN_TOKENS`](super::environments::MAX_REPEATED_COLUMN_TOKENS) tokens in total
+    RepeatedColumnFormatTooLong,
+    /// The repeated column specification of `*{..}{..}` in a column format itself contains `*{..}{..}`, e.g. `*{2}{*{3}{c}}`
+    NestedColumnRepetition,
+    /// In strict mode (cf [`ParserSettings::strict_arrays`](super::ParserSettings::strict_arrays)), a row of an array does not have as many cells as the array has columns
+    RaggedArrayRow {
+        /// index of the row, starting from 0
+        row      : usize,
+        /// number of columns of the array
+        expected : usize,
+        /// number of columns spanned by the cells of the row
+        got      : usize,
+    },
     /// The token immediately following `\left`, `\middle` and `\right` isn't a symbol
     ExpectedSymbolForCommand,
     /// The symbol immediately following `\big`, `\Bigl`, etc. is not Open, Close or Fence symbol type (delimiter types)
@@ -67,6 +103,24 @@ pub enum ParseError {
 
     /// The command `\limits` and `\nolimits` must be placed right after an operator (or a macro that expands into something that ends in an operator)
     LimitControlSequenceMustBeAfterOperator,
+
+    /// A collection of custom commands may only contain `\newcommand` and `\newenvironment` definitions.
+    ExpectedMacroDefinition,
+    /// The first argument of `\newcommand` must be a single control sequence (e.g. `\newcommand{\foo}{..}`)
+    ExpectedMacroName,
+    /// The number of arguments in a macro definition (e.g. `[2]` in `\newcommand{\foo}[2]{..}`) is not a number
+    UnrecognizedNumberOfArgs(Box<str>),
+    /// The body of a macro definition refers to an argument (e.g. `#3`) that the macro does not have
+    InvalidArgSlot {
+        /// number of arguments of the macro
+        n_args : usize,
+    },
+    /// Macros are expanded more than [`MAX_EXPANSION_DEPTH`](super::macros::MAX_EXPANSION_DEPTH) times in a row, e.g. because a macro refers to itself as in `\newcommand{\foo}{\foo}`
+    MacroExpansionTooDeep,
+    /// The expansions of macros produce more than [`MAX_EXPANDED_TOKENS`](super::macros::MAX_EXPANDED_TOKENS) tokens in total, e.g. with nested uses of a macro duplicating its argument
+    MacroExpansionTooLong,
+    /// A group contains more than one infix fraction (`\over`, `\atop`, `\overwithdelims`, ..), e.g. `a \over b \over c`: braces must tell which is the numerator of which.
+    AmbiguousInfixFraction,
 }
 
 
@@ -107,10 +161,32 @@ impl fmt::Display for ParseError {
                 write!(f, "More than one subscript or more than one superscript"),
             UnrecognizedDimension(dimension) => 
                 write!(f, "'{}' cannot be recognized as a dimension", dimension),
+            ExpectedMathUnits(dimension) => 
+                write!(f, "\\mspace requires a length in mu, got '{}'", dimension),
             UnrecognizedEnvironment(env_name) => 
                 write!(f, "Unknown environment '{}'", env_name),
+            UnrecognizedColumnSpan(span) => 
+                write!(f, "'{}' is not a valid number of columns", span),
+            MultiColumnTooWide { row, span, remaining } => 
+                write!(f, "Cell of row {} spans {} columns, but only {} remain", row, span, remaining),
+            UnrecognizedBoxPosition(position) => 
+                write!(f, "'{}' is not a valid position, expected 'c', 'l' or 'r'", position),
+            UnrecognizedSmashPosition(position) => 
+                write!(f, "'{}' is not a valid position, expected 't' or 'b'", position),
+            UnrecognizedFractionStyle(style) => 
+                write!(f, "'{}' is not a valid fraction style, expected '0', '1', '2' or '3'", style),
             UnrecognizedArrayColumnFormat => 
                 write!(f, "Unrecognized character in column format"),
+            UnrecognizedColumnRepetition(count) => 
+                write!(f, "'{}' is not a valid number of repetitions in column format", count),
+            TooManyColumnRepetitions(count) => 
+                write!(f, "{} repetitions in column format, at most {} are allowed", count, super::environments::MAX_COLUMN_REPETITIONS),
+            RepeatedColumnFormatTooLong => 
+                write!(f, "Repetitions in column format expand to more than {} tokens", super::environments::MAX_REPEATED_COLUMN_TOKENS),
+            NestedColumnRepetition => 
+                write!(f, "Repetitions in column format cannot be nested"),
+            RaggedArrayRow { row, expected, got } => 
+                write!(f, "Row {} of array has {} cells, expected {}", row, got, expected),
             ExpectedSymbolForCommand => 
                 write!(f, r"Token after '\left', '\middle', '\right', '\big', etc. is not a symbol"),
             ExpectedDelimiter => 
@@ -123,6 +199,72 @@ impl fmt::Display for ParseError {
                 write!(f, r"Token after '\end' is a not a middle symbol"),
             LimitControlSequenceMustBeAfterOperator => 
                 write!(f, r"'\limits' or '\nolimits' isn't placed after an operator"),
+            ExpectedMacroDefinition => 
+                write!(f, r"Expected a '\newcommand' or '\newenvironment' definition"),
+            ExpectedMacroName => 
+                write!(f, r"The name of a macro must be a control sequence"),
+            UnrecognizedNumberOfArgs(n_args) => 
+                write!(f, "'{}' is not a valid number of arguments", n_args),
+            InvalidArgSlot { n_args } => 
+                write!(f, "Macro body refers to an argument beyond its {} arguments", n_args),
+            MacroExpansionTooDeep => 
+                write!(f, "Macros are nested more than {} times ; is a macro defined in terms of itself?", super::macros::MAX_EXPANSION_DEPTH),
+            MacroExpansionTooLong => 
+                write!(f, "Macros expand to more than {} tokens", super::macros::MAX_EXPANDED_TOKENS),
+            AmbiguousInfixFraction =>
+                write!(f, "Ambiguous; you need another {{ and }}"),
         }
     }
 }
+
+
+/// A [`ParseError`] along with the position in the formula where it occurred, as returned by [`parse`](super::parse).
+/// 
+/// Its `Display` implementation shows the line of the formula containing the error, with a caret `^` under the position of the error, e.g.
+/// ```text
+/// Unknown control sequence '\foo'
+/// a + \foo
+///     ^
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocatedParseError {
+    /// The error itself
+    pub error    : ParseError,
+    /// Offset in bytes of the error in the formula: this is the start of the item that was being parsed when the error occurred, e.g. the offset of `\foo` in `a + \foo`.
+    /// Errors in the argument of a command are located at the command, e.g. at `\frac` in `\frac{\foo}{b}`.
+    pub position : usize,
+    formula      : Box<str>,
+}
+
+impl LocatedParseError {
+    /// Locates `error` at byte offset `position` of `formula`.
+    /// A position past the end of the formula, or inside a character, is moved back to the start of the character.
+    pub fn new(error: ParseError, position: usize, formula: &str) -> Self {
+        Self { error, position: char_boundary(formula, position), formula: Box::from(formula) }
+    }
+
+    /// The formula in which the error occurred
+    pub fn formula(&self) -> &str {
+        &self.formula
+    }
+}
+
+/// Largest offset of `string` which is at most `position` and falls on a char boundary
+fn char_boundary(string: &str, position: usize) -> usize {
+    let mut position = position.min(string.len());
+    while !string.is_char_boundary(position) {
+        position -= 1;
+    }
+    position
+}
+
+impl fmt::Display for LocatedParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let position = char_boundary(&self.formula, self.position);
+        let line_start = self.formula[.. position].rfind('\n').map_or(0, |index| index + 1);
+        let line_end   = self.formula[position ..].find('\n').map_or(self.formula.len(), |index| position + index);
+        let column     = self.formula[line_start .. position].chars().count();
+
+        write!(f, "{}\n{}\n{}^", self.error, &self.formula[line_start .. line_end], " ".repeat(column))
+    }
+}
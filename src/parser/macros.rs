@@ -4,30 +4,75 @@ use std::pin::Pin;
 
 use crate::parser::error::ParseError;
 
-use super::{error::ParseResult, textoken::{TexToken, TokenIterator}};
+use super::{error::ParseResult, textoken::{TexToken, TokenIterator}, tokens_as_string};
 
 
 
 
-/// A collection of custom commands. You can find a macro with the given name using [`CommandCollection::query`].
+/// A collection of custom commands and environments. You can find a macro with the given name using [`CommandCollection::get`].
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
-pub struct CommandCollection(Vec<CustomCommand>);
+pub struct CommandCollection {
+    commands     : Vec<CustomCommand>,
+    environments : Vec<CustomEnvironment>,
+}
 
 
 
 impl CommandCollection {
     /// Creates a new empty [`CommandCollection`]    
     pub const fn new() -> Self {
-        Self(Vec::new())
+        Self { commands: Vec::new(), environments: Vec::new() }
+    }
+
+    /// Parses a sequence of definitions of the form `\newcommand{\name}[n]{..}` and `\newenvironment{name}[n]{..}{..}`.
+    /// The number of arguments `[n]` is optional ; arguments are referred to as `#1`, `#2`, etc. in the body of the definition.
+    /// Whitespace between definitions is ignored.
+    pub fn parse(input : &str) -> ParseResult<Self> {
+        let mut collection = Self::new();
+        let empty_collection = Self::new();
+        let mut token_iter = ExpandedTokenIter::new(&empty_collection, TokenIterator::new(input));
+
+        // Definitions must not be handled as those of a formula (cf [`ExpandedTokenIter::next_token`]), hence the unexpanded tokens
+        while let Some(token) = token_iter.produce_next_token() {
+            match token {
+                TexToken::WhiteSpace => (),
+                TexToken::ControlSequence(definition @ ("newcommand" | "newenvironment")) => 
+                    collection.parse_definition(definition, &mut token_iter)?,
+                _ => return Err(ParseError::ExpectedMacroDefinition),
+            }
+        }
+
+        Ok(collection)
     }
 
 
+    /// Parses the arguments of `\newcommand` or `\newenvironment` (as given by `definition`) and adds the command or environment defined to the collection
+    fn parse_definition<'a, I : Iterator<Item = TexToken<'a>>>(&mut self, definition : &str, token_iter : &mut ExpandedTokenIter<'a, I>) -> ParseResult<()> {
+        match token_iter.parse_definition(definition)? {
+            Definition::Command { name, n_args, body } => 
+                self.commands.push(CustomCommand::from_body(name, n_args, body)),
+            Definition::Environment { name, n_args, begin, end } => 
+                self.environments.push(CustomEnvironment {
+                    begin: CustomCommand::from_body(name.clone(), n_args, begin),
+                    end:   CustomCommand::from_body(name, 0, end),
+                }),
+        }
+        Ok(())
+    }
+
     /// Retrieves a method by name
     pub fn get<'s>(& 's self, name : &str) -> Option<& 's CustomCommand> {
-        self.0
+        self.commands
             .iter()
             .find(|command| command.name() == name)
     }
+
+    /// Retrieves an environment by name
+    pub fn get_environment<'s>(& 's self, name : &str) -> Option<& 's CustomEnvironment> {
+        self.environments
+            .iter()
+            .find(|environment| environment.name() == name)
+    }
 }
 
 
@@ -48,6 +93,21 @@ pub struct CustomCommand {
     expansion : Vec<CommandToken>,
 }
 
+/// A custom LateX environment, as defined by `\newenvironment`.
+/// `\begin{name}` expands to the tokens of `begin` and `\end{name}` expands to the tokens of `end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomEnvironment {
+    begin : CustomCommand,
+    end   : CustomCommand,
+}
+
+impl CustomEnvironment {
+    /// Name of the environment, as used in `\begin{name}`
+    pub fn name(&self) -> &str {
+        self.begin.name()
+    }
+}
+
 struct ExpansionIterator<'args, 'token> {
     token_remaining : & 'token [CommandToken],
     args : & 'args [Vec<TexToken<'token>>],
@@ -91,6 +151,24 @@ impl CustomCommand {
         Self { n_args, name: name.to_string(), expansion: Vec::new() }
     }
 
+    fn from_body(name : String, n_args : usize, body : Vec<BodyToken>) -> Self {
+        let expansion = body.into_iter().map(|token| match token {
+            BodyToken::ArgSlot(slot) => CommandToken::ArgSlot(slot),
+            BodyToken::Token(token)  => match token {
+                TexToken::ControlSequence(name) => CommandToken::OwnedCommand(name.to_string()),
+                TexToken::Char(c)       => CommandToken::NormalToken(TexToken::Char(c)),
+                TexToken::Superscript   => CommandToken::NormalToken(TexToken::Superscript),
+                TexToken::Subscript     => CommandToken::NormalToken(TexToken::Subscript),
+                TexToken::Alignment     => CommandToken::NormalToken(TexToken::Alignment),
+                TexToken::WhiteSpace    => CommandToken::NormalToken(TexToken::WhiteSpace),
+                TexToken::BeginGroup    => CommandToken::NormalToken(TexToken::BeginGroup),
+                TexToken::EndGroup      => CommandToken::NormalToken(TexToken::EndGroup),
+                TexToken::Prime(primes) => CommandToken::NormalToken(TexToken::Prime(primes)),
+            },
+        }).collect();
+        Self { n_args, name, expansion }
+    }
+
     pub fn n_args(&self) -> usize {
         self.n_args
     }
@@ -113,11 +191,91 @@ impl CustomCommand {
 }
 
 
+/// A token of the body of a definition, where `#1`, `#2`, etc. have been replaced by argument slots
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BodyToken<'a> {
+    Token(TexToken<'a>),
+    ArgSlot(usize),
+}
+
+/// Replaces `#1`, `#2`, etc. in the body of a definition with `n_args` arguments by argument slots
+fn body_tokens(tokens : Vec<TexToken>, n_args : usize) -> ParseResult<Vec<BodyToken>> {
+    let mut body = Vec::with_capacity(tokens.len());
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        body.push(match token {
+            TexToken::Char('#') => {
+                let slot = match tokens.next() {
+                    Some(TexToken::Char(digit)) => digit.to_digit(10),
+                    _ => None,
+                };
+                match slot {
+                    Some(slot) if 1 <= slot && slot as usize <= n_args => BodyToken::ArgSlot(slot as usize - 1),
+                    _ => return Err(ParseError::InvalidArgSlot { n_args }),
+                }
+            },
+            token => BodyToken::Token(token),
+        });
+    }
+    Ok(body)
+}
+
+/// A definition by `\newcommand{..}..` or `\newenvironment{..}..`, whose body borrows from the input
+enum Definition<'a> {
+    Command {
+        name   : String,
+        n_args : usize,
+        body   : Vec<BodyToken<'a>>,
+    },
+    Environment {
+        name   : String,
+        n_args : usize,
+        begin  : Vec<BodyToken<'a>>,
+        end    : Vec<BodyToken<'a>>,
+    },
+}
+
+impl<'a> Definition<'a> {
+    fn name(&self) -> &str {
+        match self {
+            Definition::Command { name, .. } | Definition::Environment { name, .. } => name,
+        }
+    }
+}
+
+/// Largest number of nested expansions, e.g. of a macro whose body uses a macro, and so on.
+/// A macro whose definition refers to itself, e.g. `\newcommand{\foo}{\foo}`, would otherwise be expanded forever.
+pub const MAX_EXPANSION_DEPTH : usize = 32;
+
+/// Largest number of tokens that all the expansions of macros in a formula may produce in total.
+/// This bounds macros which duplicate their arguments, e.g. `\newcommand{\foo}[1]{#1#1}`, whose nested uses expand exponentially.
+pub const MAX_EXPANDED_TOKENS : usize = 100_000;
+
+/// A definition found in the input, cf [`ExpandedTokenIter::next_token`]
+struct LocalDefinition<'a> {
+    definition : Definition<'a>,
+    /// number of groups `{..}` enclosing the definition ; the definition is dropped at the end of the innermost one
+    depth      : usize,
+}
+
+
 pub struct ExpandedTokenIter<'a, I : Iterator<Item = TexToken<'a>>> {
     command_collection : & 'a CommandCollection,
     token_iter : I,
-    /// token obtained from macro expansion
-    expanded_token : Vec<TexToken<'a>>, 
+    /// token obtained from macro expansion, along with their offset in the input if known and the number of nested expansions which produced them
+    expanded_token : Vec<(TexToken<'a>, Option<usize>, usize)>, 
+    /// gives the offset in the input of the next token of `token_iter`, if it is known
+    offset_of : Option<fn(&I) -> usize>,
+    /// offset in the input of the last token returned
+    last_offset : Option<usize>,
+    /// number of nested expansions which produced the last token returned, 0 for a token of the input
+    last_depth : usize,
+    /// number of tokens produced by the expansions so far, cf [`MAX_EXPANDED_TOKENS`]
+    n_expanded_tokens : usize,
+    /// commands and environments defined in the input so far, which are still in scope
+    local_definitions : Vec<LocalDefinition<'a>>,
+    /// number of groups `{..}` enclosing the next token
+    depth : usize,
 }
 
 impl<'a, I : Iterator<Item = TexToken<'a>>> Iterator for ExpandedTokenIter<'a, I> {
@@ -130,49 +288,270 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Iterator for ExpandedTokenIter<'a, I
 
 impl<'a, I : Iterator<Item = TexToken<'a>>> ExpandedTokenIter<'a, I> {
 
-    /// Get next token from the iterator
+    /// Get next token from the iterator.
+    /// Definitions `\newcommand{..}..` and `\newenvironment{..}..` are consumed: they apply from there on, until the end of the enclosing group `{..}`.
+    /// 
+    /// Expansions may not be nested more than [`MAX_EXPANSION_DEPTH`] times, nor produce more than [`MAX_EXPANDED_TOKENS`] tokens in total.
     pub fn next_token(&mut self) -> ParseResult<Option<TexToken<'a>>> {
-        if let Some(token) = self.produce_next_token() {
-            if let TexToken::ControlSequence(command) = token {
-                if let Some(command) = self.command_collection.get(command) {
-                    let tokens: Vec<Vec<TexToken<'a>>> = self.gather_args_of_command(command)?;
-                    let token_slice : & [Vec<TexToken<'a>>] = tokens.as_slice();
-                    // TODO: something not to have to do reversals
-                    let mut expanded_tokens : Vec<TexToken<'a>> = command.expand_iter(token_slice).collect();
-                    self.expanded_token.reserve(expanded_tokens.len());
-                    while let Some(token) = expanded_tokens.pop() {
-                        self.expanded_token.push(token)
+        // Definitions and macros produce no token themselves: we move on to the tokens that follow them, or that they expand to
+        while let Some(token) = self.produce_next_token() {
+            let depth = self.last_depth;
+            match token {
+                TexToken::BeginGroup => {
+                    self.depth += 1;
+                    return Ok(Some(token));
+                },
+                TexToken::EndGroup => {
+                    self.depth = self.depth.saturating_sub(1);
+                    let depth = self.depth;
+                    self.local_definitions.retain(|local| local.depth <= depth);
+                    return Ok(Some(token));
+                },
+                TexToken::ControlSequence(definition @ ("newcommand" | "newenvironment")) => {
+                    let definition = self.parse_definition(definition)?;
+                    self.local_definitions.push(LocalDefinition { definition, depth: self.depth });
+                },
+                TexToken::ControlSequence(command) if self.local_command(command).is_some() => {
+                    // Safe to unwrap b/c of the guard ; the body is cloned since gathering the arguments borrows `self` mutably
+                    let (n_args, body) = self.local_command(command).unwrap();
+                    let body = body.to_vec();
+                    self.expand_body(depth, n_args, &body)?;
+                },
+                TexToken::ControlSequence(command) if self.command_collection.get(command).is_some() => {
+                    let command = self.command_collection.get(command).unwrap();
+                    self.expand_command(depth, command)?;
+                },
+                TexToken::ControlSequence(begin_or_end @ ("begin" | "end")) 
+                if !self.command_collection.environments.is_empty() || self.local_definitions.iter().any(|local| matches!(local.definition, Definition::Environment { .. })) => {
+                    let name_group = self.capture_group().map_err(|e| match e {
+                        ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(begin_or_end)),
+                        _ => e,
+                    })?;
+                    let name = tokens_as_string(name_group.iter().cloned()).ok();
+
+                    let local_environment = name.as_deref().and_then(|name| self.local_environment(name));
+                    if let Some((n_args, begin, end)) = local_environment {
+                        let (n_args, body) = if begin_or_end == "begin" { (n_args, begin.to_vec()) } else { (0, end.to_vec()) };
+                        self.expand_body(depth, n_args, &body)?;
+                        continue;
                     }
-                    self.next_token()
-                }
-                else {
-                    Ok(Some(token))
-                }
-            }
-            else {
-                Ok(Some(token))
+
+                    let environment = name.and_then(|name| self.command_collection.get_environment(&name));
+                    if let Some(environment) = environment {
+                        let command = if begin_or_end == "begin" { &environment.begin } else { &environment.end };
+                        self.expand_command(depth, command)?;
+                    }
+                    else {
+                        // Not a custom environment: the group is put back for the parser to process
+                        let offset = self.offset();
+                        self.expanded_token.push((TexToken::EndGroup, offset, depth));
+                        self.expanded_token.extend(name_group.into_iter().rev().map(|token| (token, offset, depth)));
+                        self.expanded_token.push((TexToken::BeginGroup, offset, depth));
+                        return Ok(Some(token));
+                    }
+                },
+                _ => return Ok(Some(token)),
             }
         }
+        Ok(None)
+    }
+
+    /// If the next token is whitespace or starts a definition, consumes it, along with the rest of the definition, and returns `true`.
+    /// Otherwise, returns `false` and leaves the token to be returned by [`ExpandedTokenIter::next_token`].
+    pub(crate) fn skip_definition(&mut self) -> ParseResult<bool> {
+        match self.produce_next_token() {
+            Some(TexToken::WhiteSpace) => Ok(true),
+            Some(TexToken::ControlSequence(definition @ ("newcommand" | "newenvironment"))) => {
+                let definition = self.parse_definition(definition)?;
+                self.local_definitions.push(LocalDefinition { definition, depth: self.depth });
+                Ok(true)
+            },
+            Some(token) => {
+                self.expanded_token.push((token, self.last_offset, self.last_depth));
+                Ok(false)
+            },
+            None => Ok(false),
+        }
+    }
+
+    /// Parses the arguments of `\newcommand` or `\newenvironment` (as given by `definition`).
+    /// As in LaTeX, the name and the body of the definition are not expanded.
+    fn parse_definition(&mut self, definition : &str) -> ParseResult<Definition<'a>> {
+        let missing_arg = |e| match e {
+            ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(definition)),
+            e => e,
+        };
+        if definition == "newcommand" {
+            let name = match self.capture_unexpanded_group().map_err(missing_arg)?.as_slice() {
+                [TexToken::ControlSequence(name)] => name.to_string(),
+                _ => return Err(ParseError::ExpectedMacroName),
+            };
+            let n_args = self.optional_number_of_args()?;
+            let body = body_tokens(self.capture_unexpanded_group().map_err(missing_arg)?, n_args)?;
+            Ok(Definition::Command { name, n_args, body })
+        }
         else {
-            Ok(None)            
+            let name = tokens_as_string(self.capture_unexpanded_group().map_err(missing_arg)?.into_iter())?;
+            let n_args = self.optional_number_of_args()?;
+            let begin = body_tokens(self.capture_unexpanded_group().map_err(missing_arg)?, n_args)?;
+            let end   = body_tokens(self.capture_unexpanded_group().map_err(missing_arg)?, 0)?;
+            Ok(Definition::Environment { name, n_args, begin, end })
+        }
+    }
+
+    /// Finds the innermost command defined in the input with the given name, returning its number of arguments and its body
+    fn local_command(&self, name : &str) -> Option<(usize, &[BodyToken<'a>])> {
+        self.local_definitions.iter().rev().find_map(|local| match &local.definition {
+            Definition::Command { name: command_name, n_args, body } if command_name == name => Some((*n_args, body.as_slice())),
+            _ => None,
+        })
+    }
+
+    /// Finds the innermost environment defined in the input with the given name, returning its number of arguments and its begin and end bodies
+    fn local_environment(&self, name : &str) -> Option<(usize, &[BodyToken<'a>], &[BodyToken<'a>])> {
+        self.local_definitions.iter().rev().find_map(|local| match &local.definition {
+            Definition::Environment { n_args, begin, end, .. } if local.definition.name() == name => Some((*n_args, begin.as_slice(), end.as_slice())),
+            _ => None,
+        })
+    }
+
+    /// The underlying token iterator
+    pub(crate) fn inner(&self) -> &I {
+        &self.token_iter
+    }
+
+    /// Puts a token back into the iterator ; it will be the next token returned.
+    pub fn push_back(&mut self, token : TexToken<'a>) {
+        // braces are counted again when the token is read
+        match token {
+            TexToken::BeginGroup => self.depth = self.depth.saturating_sub(1),
+            TexToken::EndGroup   => self.depth += 1,
+            _ => (),
+        }
+        self.expanded_token.push((token, self.last_offset, self.last_depth));
+    }
+
+    /// Returns the offset in the input of the next token, if it is known.
+    /// The tokens obtained from the expansion of a macro are all placed at the end of the macro's invocation.
+    pub fn offset(&self) -> Option<usize> {
+        match self.expanded_token.last() {
+            Some((_, offset, _)) => *offset,
+            None => self.offset_of.map(|offset_of| offset_of(&self.token_iter)),
+        }
+    }
+
+    /// Expands `command`, invoked by a token produced by `depth` nested expansions
+    fn expand_command(&mut self, depth : usize, command : & 'a CustomCommand) -> ParseResult<()> {
+        self.check_expansion_depth(depth)?;
+        let tokens: Vec<Vec<TexToken<'a>>> = self.gather_args_of_command(command)?;
+        let token_slice : & [Vec<TexToken<'a>>] = tokens.as_slice();
+        // TODO: something not to have to do reversals
+        let mut expanded_tokens : Vec<TexToken<'a>> = command.expand_iter(token_slice).collect();
+        self.count_expanded_tokens(expanded_tokens.len())?;
+        let offset = self.offset();
+        self.expanded_token.reserve(expanded_tokens.len());
+        while let Some(token) = expanded_tokens.pop() {
+            self.expanded_token.push((token, offset, depth + 1))
+        }
+        Ok(())
+    }
+
+    /// Same as [`ExpandedTokenIter::expand_command`], for a command defined in the input
+    fn expand_body(&mut self, depth : usize, n_args : usize, body : &[BodyToken<'a>]) -> ParseResult<()> {
+        self.check_expansion_depth(depth)?;
+        let args = self.gather_args(n_args)?;
+        let n_tokens = body.iter().map(|token| match token {
+            BodyToken::Token(_)      => 1,
+            BodyToken::ArgSlot(slot) => args[*slot].len(),
+        }).sum();
+        self.count_expanded_tokens(n_tokens)?;
+        let offset = self.offset();
+        for token in body.iter().rev() {
+            match token {
+                BodyToken::Token(token)  => self.expanded_token.push((token.clone(), offset, depth + 1)),
+                BodyToken::ArgSlot(slot) => self.expanded_token.extend(args[*slot].iter().rev().map(|token| (token.clone(), offset, depth + 1))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if a macro invoked by a token produced by `depth` nested expansions may not be expanded, cf [`MAX_EXPANSION_DEPTH`]
+    fn check_expansion_depth(&self, depth : usize) -> ParseResult<()> {
+        if depth >= MAX_EXPANSION_DEPTH {
+            return Err(ParseError::MacroExpansionTooDeep);
+        }
+        Ok(())
+    }
+
+    /// Adds `n_tokens` to the number of tokens produced by expansions, failing past [`MAX_EXPANDED_TOKENS`]
+    fn count_expanded_tokens(&mut self, n_tokens : usize) -> ParseResult<()> {
+        self.n_expanded_tokens = self.n_expanded_tokens.saturating_add(n_tokens);
+        if self.n_expanded_tokens > MAX_EXPANDED_TOKENS {
+            return Err(ParseError::MacroExpansionTooLong);
+        }
+        Ok(())
+    }
+
+    /// Parses the optional number of arguments of a definition, e.g. `[2]` in `\newcommand{\foo}[2]{..}`. Defaults to 0.
+    /// Like the rest of the definition, it is not expanded.
+    fn optional_number_of_args(&mut self) -> ParseResult<usize> {
+        let mut token = self.produce_next_token();
+        while let Some(TexToken::WhiteSpace) = token {
+            token = self.produce_next_token();
+        }
+
+        match token {
+            Some(TexToken::Char('[')) => {
+                let mut digits = String::new();
+                loop {
+                    match self.produce_next_token() {
+                        Some(TexToken::Char(']')) => break,
+                        Some(TexToken::Char(c)) => digits.push(c),
+                        Some(TexToken::WhiteSpace) => (),
+                        _ => return Err(ParseError::UnrecognizedNumberOfArgs(digits.into_boxed_str())),
+                    }
+                }
+                digits.parse().map_err(|_| ParseError::UnrecognizedNumberOfArgs(digits.into_boxed_str()))
+            },
+            Some(token) => {
+                self.expanded_token.push((token, self.last_offset, self.last_depth));
+                Ok(0)
+            },
+            None => Ok(0),
         }
     }
 
     /// From a regular token iterator, creates one that expands macros.
     pub fn new<'command : 'a>(command_collection: & 'command CommandCollection, token_iter: I) -> Self {
-        Self { command_collection, token_iter, expanded_token: Vec::new() }
+        Self { command_collection, token_iter, expanded_token: Vec::new(), offset_of: None, last_offset: None, last_depth: 0, n_expanded_tokens: 0, local_definitions: Vec::new(), depth: 0 }
+    }
+
+    /// Same as [`ExpandedTokenIter::new`], keeping track of the offsets of tokens in the input with `offset_of`, cf [`ExpandedTokenIter::offset`].
+    pub(crate) fn with_offsets<'command : 'a>(command_collection: & 'command CommandCollection, token_iter: I, offset_of: fn(&I) -> usize) -> Self {
+        Self { offset_of: Some(offset_of), ..Self::new(command_collection, token_iter) }
     }
 
 
     fn produce_next_token(&mut self) -> Option<TexToken<'a>> {
-        Option::or_else(
-            self.expanded_token.pop(),
-            || self.token_iter.next(),
-        )
+        match self.expanded_token.pop() {
+            Some((token, offset, depth)) => {
+                self.last_offset = offset;
+                self.last_depth  = depth;
+                Some(token)
+            },
+            None => {
+                self.last_offset = self.offset_of.map(|offset_of| offset_of(&self.token_iter));
+                self.last_depth  = 0;
+                self.token_iter.next()
+            },
+        }
     }
 
     fn gather_args_of_command(&mut self, command : &CustomCommand) -> ParseResult<Vec<Vec<TexToken<'a>>>> {
-        let n_args = command.n_args();
+        self.gather_args(command.n_args())
+    }
+
+    fn gather_args(&mut self, n_args : usize) -> ParseResult<Vec<Vec<TexToken<'a>>>> {
         let mut args : Vec<Vec<TexToken>> = Vec::with_capacity(n_args);
         for i in 0 .. n_args {
             let arg = self
@@ -187,22 +566,43 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> ExpandedTokenIter<'a, I> {
     }
 
     pub fn capture_group(&mut self) -> ParseResult<Vec<TexToken<'a>>> {
+        let group = self.capture_group_with(Self::next_token)?;
+        Ok(group.into_iter().map(|(token, _)| token).collect())
+    }
+
+    /// Same as [`ExpandedTokenIter::capture_group`], also returning the offset in the input of each token of the group, if known (cf [`ExpandedTokenIter::offset`])
+    pub fn capture_located_group(&mut self) -> ParseResult<Vec<(TexToken<'a>, Option<usize>)>> {
+        self.capture_group_with(Self::next_token)
+    }
+
+    /// Same as [`ExpandedTokenIter::capture_group`], without expanding macros or handling definitions in the group, e.g. for the body of a definition
+    fn capture_unexpanded_group(&mut self) -> ParseResult<Vec<TexToken<'a>>> {
+        let group = self.capture_group_with(|token_iter| Ok(token_iter.produce_next_token()))?;
+        Ok(group.into_iter().map(|(token, _)| token).collect())
+    }
+
+    fn capture_group_with(&mut self, mut next_token : impl FnMut(&mut Self) -> ParseResult<Option<TexToken<'a>>>) -> ParseResult<Vec<(TexToken<'a>, Option<usize>)>> {
+        let mut next_located_token = |token_iter : &mut Self| {
+            let offset = token_iter.offset();
+            Ok(next_token(token_iter)?.map(|token| (token, offset)))
+        };
+
         let mut arg = Vec::with_capacity(1);
-        let mut token = self.next_token()?
-            .ok_or_else(|| ParseError::ExpectedToken)?;
-        while let TexToken::WhiteSpace = token {
-            token = self.next_token()?
-                .ok_or_else(|| ParseError::ExpectedToken)?;
+        let mut token = next_located_token(self)?
+            .ok_or(ParseError::ExpectedToken)?;
+        while let (TexToken::WhiteSpace, _) = token {
+            token = next_located_token(self)?
+                .ok_or(ParseError::ExpectedToken)?;
         }
-        if let TexToken::BeginGroup = token {
+        if let (TexToken::BeginGroup, _) = token {
             let mut n_open_paren : u32 = 1;
             while n_open_paren != 0 {
-                let token = self.next_token()?
+                let token = next_located_token(self)?
                     .ok_or(ParseError::UnmatchedBrackets)?;
-                if let TexToken::BeginGroup = token {
+                if let (TexToken::BeginGroup, _) = token {
                     n_open_paren += 1;
                 }
-                else if let TexToken::EndGroup = token {
+                else if let (TexToken::EndGroup, _) = token {
                     n_open_paren -= 1;
                 }
 
@@ -273,11 +673,14 @@ mod tests {
 
     impl CommandCollection {
         fn test_collection() -> Self {
-            Self(vec![
-                CustomCommand::test_command1("testone"), 
-                CustomCommand::test_command2("testtwo"), 
-                CustomCommand::test_command3("testtri"), 
-            ])
+            Self {
+                commands: vec![
+                    CustomCommand::test_command1("testone"), 
+                    CustomCommand::test_command2("testtwo"), 
+                    CustomCommand::test_command3("testtri"), 
+                ],
+                environments: Vec::new(),
+            }
         }
     }
 
@@ -430,4 +833,119 @@ mod tests {
 
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_definitions() {
+        let collection = CommandCollection::parse(include_str!("macros_test_files/ok1.tex")).unwrap();
+        assert_eq!(collection.get("dbb").map(CustomCommand::n_args),  Some(1));
+        assert_eq!(collection.get("quo").map(CustomCommand::n_args),  Some(1));
+        assert_eq!(collection.get("poly").map(CustomCommand::n_args), Some(3));
+
+        let collection = CommandCollection::parse(include_str!("macros_test_files/ok2.tex")).unwrap();
+        assert_eq!(collection.get("poly").map(CustomCommand::n_args), Some(3));
+
+        let collection = CommandCollection::parse(r"\newcommand{\R}{\mathbb{R}} \newenvironment{myeq}{\left(}{\right)}").unwrap();
+        assert_eq!(collection.get("R").map(CustomCommand::n_args), Some(0));
+        assert!(collection.get_environment("myeq").is_some());
+
+        // ERRORED INPUT
+        assert!(CommandCollection::parse(r"a").is_err());
+        assert!(CommandCollection::parse(r"\newcommand{ab}{x}").is_err());
+        assert!(CommandCollection::parse(r"\newcommand{\a}[1]{#2}").is_err());
+        assert!(CommandCollection::parse(r"\newcommand{\a}[x]{#1}").is_err());
+        assert!(CommandCollection::parse(r"\newenvironment{a}{x}").is_err());
+    }
+
+    #[test]
+    fn custom_environments() {
+        use crate::parser::{parse, parse_with_custom_commands};
+
+        let collection = CommandCollection::parse(r"\newenvironment{myeq}{\left(}{\right)}").unwrap();
+        assert_eq!(
+            parse_with_custom_commands(r"\begin{myeq}x\end{myeq}", &collection),
+            parse(r"\left(x\right)"),
+        );
+        // built-in environments are unaffected
+        assert_eq!(
+            parse_with_custom_commands(r"\begin{myeq}\begin{pmatrix}x\end{pmatrix}\end{myeq}", &collection),
+            parse(r"\left(\begin{pmatrix}x\end{pmatrix}\right)"),
+        );
+
+        // environment with arguments
+        let collection = CommandCollection::parse(r"\newenvironment{norm}[1]{\left\|#1\,}{\right\|}").unwrap();
+        assert_eq!(
+            parse_with_custom_commands(r"\begin{norm}{2}x\end{norm}", &collection),
+            parse(r"\left\|2\,x\right\|"),
+        );
+        // arguments can only be used at the beginning of the environment
+        assert!(CommandCollection::parse(r"\newenvironment{norm}[1]{\left\|}{\right\|_{#1}}").is_err());
+    }
+
+    #[test]
+    fn inline_definitions() {
+        use crate::parser::{parse, parse_with_custom_commands};
+
+        assert_eq!(parse(r"\newcommand{\R}{\mathbb{R}} x \in \R"), parse(r"x \in \mathbb{R}"));
+        assert_eq!(parse(r"\newcommand{\sq}[1]{#1^2} \sq{x} + \sq{y}"), parse(r"x^2 + y^2"));
+        assert_eq!(
+            parse(r"\newenvironment{myeq}{\left(}{\right)} \begin{myeq}x\end{myeq}"),
+            parse(r"\left(x\right)"),
+        );
+
+        // inline definitions add to the custom commands provided
+        let collection = CommandCollection::parse(r"\newcommand{\R}{\mathbb{R}}").unwrap();
+        assert_eq!(
+            parse_with_custom_commands(r"\newcommand{\N}{\mathbb{N}} \N \subset \R", &collection),
+            parse(r"\mathbb{N} \subset \mathbb{R}"),
+        );
+
+        // definitions only apply after them
+        assert_eq!(
+            parse(r"\R \newcommand{\R}{\mathbb{R}}").map_err(|e| e.error),
+            Err(ParseError::UnrecognizedControlSequence(Box::from("R"))),
+        );
+        assert_eq!(parse(r"x \newcommand{\y}{z} \y"), parse(r"x z"));
+
+        // definitions only apply until the end of their group
+        assert_eq!(parse(r"{\newcommand{\y}{z} \y} a"), parse(r"{z} a"));
+        assert_eq!(
+            parse(r"{\newcommand{\y}{z} \y} \y").map_err(|e| e.error),
+            Err(ParseError::UnrecognizedControlSequence(Box::from("y"))),
+        );
+        assert_eq!(
+            parse(r"\frac{\newcommand{\y}{z} \y}{\y}").map_err(|e| e.error),
+            Err(ParseError::UnrecognizedControlSequence(Box::from("y"))),
+        );
+        assert_eq!(parse(r"\newcommand{\y}{z} \frac{\y}{\y}"), parse(r"\frac{z}{z}"));
+        assert_eq!(
+            parse(r"{\newenvironment{myeq}{\left(}{\right)}} \begin{myeq}x\end{myeq}").map_err(|e| e.error),
+            Err(ParseError::UnrecognizedEnvironment(Box::from("myeq"))),
+        );
+        // an inner definition shadows an outer one until the end of its group
+        assert_eq!(parse(r"\newcommand{\y}{a} {\newcommand{\y}{b} \y} \y"), parse(r"{b} a"));
+
+        assert_eq!(parse(r"x \newcommand{ab}{x}").map_err(|e| e.error), Err(ParseError::ExpectedMacroName));
+        assert_eq!(parse(r"x \newcommand{\a}").map_err(|e| e.error), Err(ParseError::MissingArgForCommand(Box::from("newcommand"))));
+    }
+
+    #[test]
+    fn expansion_is_bounded() {
+        use crate::parser::{parse, parse_with_custom_commands};
+
+        // macros defined in terms of themselves
+        assert_eq!(parse(r"\newcommand{\b}{\b b}\b").map_err(|e| e.error), Err(ParseError::MacroExpansionTooDeep));
+        assert_eq!(parse(r"\newcommand{\b}{{\b}}\b").map_err(|e| e.error), Err(ParseError::MacroExpansionTooDeep));
+        assert_eq!(parse(r"\newenvironment{e}{\begin{e}}{}\begin{e}").map_err(|e| e.error), Err(ParseError::MacroExpansionTooDeep));
+        let collection = CommandCollection::parse(r"\newcommand{\b}{\b b}").unwrap();
+        assert_eq!(parse_with_custom_commands(r"\b", &collection).map_err(|e| e.error), Err(ParseError::MacroExpansionTooDeep));
+
+        // nested uses of a macro which duplicates its argument
+        let nested = |n : usize| format!(r"\newcommand{{\x}}[1]{{#1#1}}{}a{}", r"\x{".repeat(n), "}".repeat(n));
+        assert!(parse(&nested(10)).is_ok());
+        assert_eq!(parse(&nested(30)).map_err(|e| e.error), Err(ParseError::MacroExpansionTooLong));
+
+        // many macros in a row, expanding to nothing
+        let empty = format!(r"\newcommand{{\e}}{{}}{}", r"\e".repeat(MAX_EXPANDED_TOKENS));
+        assert_eq!(parse(&empty), parse(""));
+    }
+}
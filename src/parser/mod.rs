@@ -8,12 +8,16 @@ pub mod symbols;
 pub mod macros;
 pub mod error;
 pub mod environments;
+pub mod settings;
 mod textoken;
 mod control_sequence;
 
+use std::ops::Range;
+
 use unicode_math::TexSymbolType;
 
-use crate::dimensions::AnyUnit;
+use crate::dimensions::{AnyUnit, Unit};
+use crate::dimensions::units::Pt;
 use crate::error::ParseResult;
 use crate::font::style_symbol;
 use crate::font::Style;
@@ -23,16 +27,23 @@ use crate::parser::control_sequence::parse_color;
 use crate::parser::nodes::Accent;
 use crate::parser::nodes::Delimited;
 use crate::parser::nodes::GenFraction;
+use crate::parser::nodes::MathStyle;
+use crate::parser::nodes::BarThickness;
+use crate::parser::nodes::MakeBox;
+use crate::parser::nodes::ArrayColumnAlign;
 use crate::parser::nodes::PlainText;
 use crate::parser::textoken::TexToken;
 use crate::parser::control_sequence::PrimitiveControlSequence;
 
 use self::environments::Environment;
 use self::error::ParseError;
+use self::error::LocatedParseError;
 use self::macros::CommandCollection;
 use self::macros::ExpandedTokenIter;
 pub use self::nodes::ParseNode;
 pub use self::nodes::is_symbol;
+pub use self::settings::ParserSettings;
+pub use self::control_sequence::DelimiterSize;
 use self::nodes::Scripts;
 use self::symbols::Symbol;
 use self::textoken::NumberOfPrimes;
@@ -72,6 +83,28 @@ struct List {
     group : GroupKind
 }
 
+/// Tokens captured along with their offset in the input, cf [`Parser::fork_located`]
+struct LocatedTokens<'a> {
+    tokens : std::vec::IntoIter<(TexToken<'a>, Option<usize>)>,
+    /// offset given once all tokens are read
+    end    : usize,
+}
+
+impl<'a> LocatedTokens<'a> {
+    /// Offset of the next token
+    fn offset(&self) -> usize {
+        self.tokens.as_slice().first().and_then(|(_, offset)| *offset).unwrap_or(self.end)
+    }
+}
+
+impl<'a> Iterator for LocatedTokens<'a> {
+    type Item = TexToken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tokens.next().map(|(token, _)| token)
+    }
+}
+
 
 /// Contains the internal state of the TeX parser, what's left to parse, and has methods to parse various TeX construct.  
 /// Holds a reference to `CommandCollection`, which holds the definition of custom TeX macros defined by the user.
@@ -79,28 +112,101 @@ struct List {
 pub struct Parser<'a, I : Iterator<Item = TexToken<'a>>> {
     token_iter : ExpandedTokenIter<'a, I>,
     current_style : Style,
+    settings : & 'a ParserSettings,
+    /// offset in the input of the item being parsed, if known, cf [`Parser::error_position`]
+    item_start : Option<usize>,
+    /// set when the next list to be parsed is the denominator of an infix fraction (e.g. `\over`), which may not contain another one
+    in_denominator : bool,
 }
 
 impl<'a> Parser<'a, TokenIterator<'a>> {
     pub fn new<'command : 'a, 'input : 'a>(command_collection: & 'command CommandCollection, input: & 'input str) -> Self { 
-        Self { 
-            token_iter : ExpandedTokenIter::new(command_collection, TokenIterator::new(input)),
-            current_style : Style::default()
-        } 
+        Self::with_offsets(command_collection, TokenIterator::new(input), TokenIterator::offset)
+    }
+
+    /// Same as [`Parser::parse`], also returning the span in the input, as a range of byte offsets, of each of the nodes returned.
+    /// Spans are only given for the nodes of the outermost list, not for the nodes they contain: e.g. the span of `\frac{a}{b}` covers the whole fraction.
+    /// A node produced by a custom macro is given the span of the macro's invocation ;
+    /// when the invocation produces several nodes, the nodes after the first are given an empty span at the end of the invocation.
+    pub fn parse_with_spans(&mut self) -> ParseResult<(Vec<ParseNode>, Vec<Range<usize>>)> {
+        let input = self.token_iter.inner().input();
+        let mut spans = Vec::new();
+        let List { nodes, group } = self.parse_list(Some(&mut spans))?;
+        if group != GroupKind::EndOfInput {
+            return Err(ParseError::UnexpectedEndGroup {
+                expected: Box::from([GroupKind::EndOfInput]),
+                got: group,
+            });
+        }
+
+        // The lexer eats whitespace after control sequences: it is not part of the spans
+        for span in spans.iter_mut() {
+            let source = &input[span.clone()];
+            span.start += source.len() - source.trim_start().len();
+            span.end = span.start.max(span.end - (source.len() - source.trim_end().len()));
+        }
+
+        Ok((nodes, spans))
     }
 }
 
 impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
 
     const EMPTY_COMMAND_COLLECTION : & 'static CommandCollection = &CommandCollection::new();
+    const DEFAULT_SETTINGS : & 'static ParserSettings = &ParserSettings::new();
 
     pub fn from_iter<'command : 'a>(command_collection: & 'command CommandCollection, input: I) -> Self { 
         Self { 
             token_iter : ExpandedTokenIter::new(command_collection, input),
             current_style: Style::default(),
+            settings : Self::DEFAULT_SETTINGS,
+            item_start : None,
+            in_denominator : false,
         } 
     }
 
+    /// Same as [`Parser::from_iter`], keeping track of the offsets of tokens in the input with `offset_of`, cf [`ExpandedTokenIter::offset`]
+    fn with_offsets<'command : 'a>(command_collection: & 'command CommandCollection, input: I, offset_of: fn(&I) -> usize) -> Self {
+        Self { 
+            token_iter : ExpandedTokenIter::with_offsets(command_collection, input, offset_of),
+            current_style: Style::default(),
+            settings : Self::DEFAULT_SETTINGS,
+            item_start : None,
+            in_denominator : false,
+        } 
+    }
+
+    /// Same as [`Parser::fork`], for a group of tokens located in the input (cf [`ExpandedTokenIter::capture_located_group`]):
+    /// the items parsed by the forked parser keep their position, cf [`Parser::error_position`].
+    fn fork_located(&self, group : Vec<(TexToken<'a>, Option<usize>)>) -> Parser<'a, LocatedTokens<'a>> {
+        let end = group.last().and_then(|(_, offset)| *offset).unwrap_or_default();
+        let tokens = LocatedTokens { tokens: group.into_iter(), end };
+        let mut forked_parser = Parser::with_offsets(Self::EMPTY_COMMAND_COLLECTION, tokens, LocatedTokens::offset);
+        forked_parser.current_style = self.current_style;
+        forked_parser.settings = self.settings;
+        forked_parser
+    }
+
+    /// After a parse error, returns the offset in the input of the item that was being parsed when the error occurred, if it is known.
+    /// Errors in the argument of a command are located at the command, as arguments are parsed from the tokens captured for them.
+    pub fn error_position(&self) -> Option<usize> {
+        self.item_start
+    }
+
+    /// Sets the settings used by the parser (by default, [`ParserSettings::new`])
+    pub fn with_settings<'settings : 'a>(mut self, settings : & 'settings ParserSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Creates a parser for a group of tokens which have already been expanded, which inherits the style and the settings of this parser.
+    fn fork(&self, group : Vec<TexToken<'a>>) -> Parser<'a, std::vec::IntoIter<TexToken<'a>>> {
+        let mut forked_parser = Parser::from_iter(Self::EMPTY_COMMAND_COLLECTION, group.into_iter());
+        forked_parser.current_style = self.current_style;
+        forked_parser.settings = self.settings;
+        forked_parser
+    }
+
     pub fn parse(&mut self) -> ParseResult<Vec<ParseNode>> {
         let List { nodes, group } = self.parse_until_end_of_group()?;
         if let GroupKind::EndOfInput = group 
@@ -114,9 +220,31 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
 
 
     fn parse_until_end_of_group(&mut self) -> ParseResult<List> {
+        self.parse_list(None)
+    }
+
+    /// Parses until the end of the group, recording the spans of the nodes in `spans` if given, cf [`Parser::parse_with_spans`]
+    fn parse_list(&mut self, mut spans : Option<&mut Vec<Range<usize>>>) -> ParseResult<List> {
         let mut results = Vec::new();
+        // groups nested in a denominator may have their own infix fraction
+        let in_denominator = std::mem::take(&mut self.in_denominator);
+
+        loop {
+            let start = self.token_iter.offset();
+            if start.is_some() {
+                self.item_start = start;
+            }
+            // Whitespace and definitions produce no node: they are not part of the span of the next node
+            if self.token_iter.skip_definition()? {
+                continue;
+            }
+            let token = match self.token_iter.next_token()? {
+                Some(token) => token,
+                None => break,
+            };
+            let is_script = matches!(token, TexToken::Superscript | TexToken::Subscript | TexToken::Prime(_));
+            let previous_length = results.len();
 
-        while let Some(token) = self.token_iter.next_token()? {
             match token {
                 TexToken::Superscript | TexToken::Subscript  => {
                     let is_superscript = token == TexToken::Superscript;
@@ -124,47 +252,22 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                         ParseError::ExpectedToken => ParseError::MissingSubSuperScript,
                         e => e,
                     })?;
-                    let last_node = results.pop();
-                    let new_node = match last_node {
-                        Some(ParseNode::Scripts(mut scripts)) =>{
-                            let sub_or_super_script = scripts.get_script(is_superscript);
-                            match sub_or_super_script {
-                                Some(_) => return Err(ParseError::TooManySubscriptsOrSuperscripts),
-                                None => {
-                                    *sub_or_super_script = Some(group);
-                                },
-                            }
-                            ParseNode::Scripts(scripts)
-                        }
-                        Some(node) => {
-                            let mut scripts = Scripts { 
-                                base: Some(Box::new(node)), 
-                                superscript: None,
-                                subscript: None, 
-                            };
-                            *scripts.get_script(is_superscript) = Some(group);
-                            ParseNode::Scripts(scripts)
-                        }
-                        None => {
-                            let mut scripts = Scripts { 
-                                base: None, 
-                                superscript: None,
-                                subscript: None, 
-                            };
-                            *scripts.get_script(is_superscript) = Some(group);
-                            ParseNode::Scripts(scripts)
-                        }
-                    };
-                    results.push(new_node);
+                    attach_script(&mut results, group, is_superscript)?;
                 },
                 TexToken::Prime(number_of_primes) => { 
-                    let codepoint = match number_of_primes {
-                        NumberOfPrimes::Simple => '′',
-                        NumberOfPrimes::Double => '″',
-                        NumberOfPrimes::Triple => '‴',
-                    };
-                    let symbol = Symbol { codepoint, atom_type: TexSymbolType::Ordinary };
-                    results.push(ParseNode::Symbol(symbol));
+                    // A run of primes is a superscript, e.g. `''''` which is tokenized as a triple prime followed by a simple prime
+                    let mut count = number_of_primes.count();
+                    loop {
+                        match self.token_iter.next_token()? {
+                            Some(TexToken::Prime(number_of_primes)) => count += number_of_primes.count(),
+                            Some(token) => {
+                                self.token_iter.push_back(token);
+                                break;
+                            },
+                            None => break,
+                        }
+                    }
+                    attach_script(&mut results, prime_symbols(count), true)?;
                 },
                 TexToken::WhiteSpace => { },
                 TexToken::BeginGroup => {
@@ -189,19 +292,28 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                     results.push(ParseNode::Symbol(symbol));
                 },
                 TexToken::ControlSequence("\\") => {
+                    // `\\*` forbids a page break after the line in LaTeX ; there are no pages here, so it is the same as `\\`
+                    match self.token_iter.next_token()? {
+                        Some(TexToken::Char('*')) | None => (),
+                        Some(token) => self.token_iter.push_back(token),
+                    }
                     return Ok(List { nodes: results, group: GroupKind::NewLine });
                 }
                 // Here we deal with "primitive" control sequences, not macros
                 TexToken::ControlSequence(control_sequence_name) => {
                     let command = 
-                        PrimitiveControlSequence::from_name(control_sequence_name)
+                        self.control_sequence_from_name(control_sequence_name)
                         .ok_or_else(|| ParseError::UnrecognizedControlSequence(control_sequence_name.to_string().into_boxed_str()))?
                     ;
                     use PrimitiveControlSequence::*;
                     match command {
                         Radical => {
+                            let index = match self.optional_bracket_tokens()? {
+                                Some(tokens) => Some(self.parse_tokens_as_nodes(tokens)?),
+                                None => None,
+                            };
                             let inner = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
-                            results.push(ParseNode::Radical(nodes::Radical { inner, }));
+                            results.push(ParseNode::Radical(nodes::Radical { inner, index, }));
                         },
                         Rule => {
                             let width_tokens = self.token_iter.capture_group().map_err(|e| match e {
@@ -241,6 +353,107 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                                 inner,
                             }));
                         },
+                        Line { placement, tight } => {
+                            let inner = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            results.push(ParseNode::Line(nodes::Line {
+                                placement,
+                                tight,
+                                inner,
+                            }));
+                        },
+                        Cancel(direction) => {
+                            let inner = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            results.push(ParseNode::Cancel(nodes::Cancel {
+                                direction,
+                                inner,
+                            }));
+                        },
+                        Boxed => {
+                            let inner = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            results.push(ParseNode::Boxed(nodes::Boxed { inner }));
+                        },
+                        ExtensibleArrow(codepoint) => {
+                            let under = match self.optional_bracket_tokens()? {
+                                Some(tokens) => self.parse_tokens_as_nodes(tokens)?,
+                                None => Vec::new(),
+                            };
+                            let over = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            results.push(ParseNode::ExtensibleArrow(nodes::ExtensibleArrow {
+                                symbol: Symbol { codepoint, atom_type: TexSymbolType::Relation },
+                                over,
+                                under,
+                            }));
+                        },
+                        OverUnderSet { placement, relation } => {
+                            let annotation = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            let base       = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+
+                            // Without annotation, there is nothing to stack: the base is left untouched, except that `\stackrel` still makes it a relation
+                            if annotation.is_empty() && relation {
+                                results.push(ParseNode::AtomChange(nodes::AtomChange {
+                                    at: TexSymbolType::Relation,
+                                    inner: base,
+                                }));
+                            }
+                            else if annotation.is_empty() {
+                                results.extend(base);
+                            }
+                            else {
+                                // As in amsmath, the annotation is placed as a limit of the base ;
+                                // the result is a relation (resp. binary operator) if the base is one, and an ordinary symbol otherwise.
+                                // `\stackrel` always results in a relation.
+                                let at = match base.as_slice() {
+                                    _ if relation => TexSymbolType::Relation,
+                                    [node] if matches!(node.atom_type(), TexSymbolType::Relation | TexSymbolType::Binary) => node.atom_type(),
+                                    _ => TexSymbolType::Ordinary,
+                                };
+                                let mut scripts = Scripts {
+                                    base: Some(Box::new(ParseNode::AtomChange(nodes::AtomChange {
+                                        at: TexSymbolType::Operator(true),
+                                        inner: base,
+                                    }))),
+                                    superscript: None,
+                                    subscript: None,
+                                };
+                                *scripts.get_script(placement == nodes::LinePlacement::Over) = Some(annotation);
+                                results.push(ParseNode::AtomChange(nodes::AtomChange {
+                                    at,
+                                    inner: vec![ParseNode::Scripts(scripts)],
+                                }));
+                            }
+                        },
+                        Phantom { horizontal, vertical } => {
+                            let inner = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            results.push(ParseNode::Phantom(nodes::Phantom {
+                                inner,
+                                horizontal,
+                                vertical,
+                            }));
+                        },
+                        Smash => {
+                            let (top, bottom) = match self.optional_bracket_argument()? {
+                                None => (true, true),
+                                Some(position) => match position.trim() {
+                                    "t" => (true, false),
+                                    "b" => (false, true),
+                                    _   => return Err(ParseError::UnrecognizedSmashPosition(position.into_boxed_str())),
+                                },
+                            };
+                            let inner = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            results.push(ParseNode::Smash(nodes::Smash {
+                                inner,
+                                top,
+                                bottom,
+                            }));
+                        },
+                        MathStrut => {
+                            let strut = Symbol { codepoint: '(', atom_type: TexSymbolType::Open };
+                            results.push(ParseNode::Phantom(nodes::Phantom {
+                                inner: vec![ParseNode::Symbol(strut)],
+                                horizontal: false,
+                                vertical: true,
+                            }));
+                        },
                         StyleChange { family, weight, takes_arg } => {
                             let old_style = self.current_style;
                             if let Some(family) = family {
@@ -263,9 +476,68 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                             results.push(ParseNode::GenFraction(GenFraction {
                                 numerator, denominator,
                                 left_delimiter, right_delimiter,
+                                delimiter_size: None,
                                 bar_thickness, style,
+                                numerator_style: MathStyle::NoChange, denominator_style: MathStyle::NoChange,
+                                numerator_alignment: ArrayColumnAlign::Centered,
+                            }));
+                        },
+                        ContinuedFraction => {
+                            let numerator_alignment = match self.optional_bracket_argument()? {
+                                Some(position) => parse_position(position)?,
+                                None => ArrayColumnAlign::Centered,
+                            };
+                            let numerator   = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            let denominator = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+
+                            // Unlike with `\frac`, the parts of nested continued fractions do not get smaller
+                            results.push(ParseNode::GenFraction(GenFraction {
+                                numerator, denominator,
+                                left_delimiter: None, right_delimiter: None,
+                                delimiter_size: None,
+                                bar_thickness: BarThickness::Default, style: MathStyle::NoChange,
+                                numerator_style: MathStyle::Display, denominator_style: MathStyle::Display,
+                                numerator_alignment,
                             }));
                         },
+                        GeneralizedFraction => {
+                            let fraction = self.parse_genfrac(control_sequence_name)?;
+                            results.push(fraction);
+                        },
+                        InfixFraction { bar_thickness, with_delimiters } => {
+                            if in_denominator {
+                                return Err(ParseError::AmbiguousInfixFraction);
+                            }
+                            let (left_delimiter, right_delimiter) = if with_delimiters {
+                                // the null delimiter '.' means no delimiter
+                                let mut parse_delimiter = || -> ParseResult<Option<Symbol>> {
+                                    let delimiter = self.parse_next_token_as_delimiter()?;
+                                    Ok(Some(delimiter).filter(|delimiter| delimiter.codepoint != '.'))
+                                };
+                                (parse_delimiter()?, parse_delimiter()?)
+                            }
+                            else {
+                                (None, None)
+                            };
+
+                            // The fraction takes up the whole group: the nodes parsed so far are the numerator, the rest of the group is the denominator
+                            self.in_denominator = true;
+                            let List { nodes: denominator, group } = self.parse_until_end_of_group()?;
+                            let fraction = GenFraction {
+                                numerator: std::mem::take(&mut results), denominator,
+                                left_delimiter, right_delimiter,
+                                delimiter_size: None,
+                                bar_thickness, style: MathStyle::NoChange,
+                                numerator_style: MathStyle::NoChange, denominator_style: MathStyle::NoChange,
+                                numerator_alignment: ArrayColumnAlign::Centered,
+                            };
+                            if let (Some(spans), Some(start), Some(end)) = (spans.as_deref_mut(), start, self.token_iter.offset()) {
+                                let start = spans.first().map_or(start, |span| span.start);
+                                spans.clear();
+                                spans.push(start .. end);
+                            }
+                            return Ok(List { nodes: vec![ParseNode::GenFraction(fraction)], group });
+                        },
                         ExtendedDelimiter(delimiter_size, atom_type) => {
                             let mut delimiter = self.parse_next_token_as_delimiter()?;
                             match delimiter.atom_type {
@@ -280,7 +552,31 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                             results.push(ParseNode::ExtendedDelimiter(nodes::ExtendedDelimiter::new(
                                 delimiter,
                                 height_enclosed_content
-                            )));
+                            ).with_size(delimiter_size)));
+                        },
+                        HSpace | MSpace => {
+                            // `\hspace*{..}` differs from `\hspace{..}` in that its space is kept at the end of a line
+                            // when the formula is broken into lines (cf `Layout::wrapped`), whereas other spaces are dropped there.
+                            let mut starred = false;
+                            if command == HSpace {
+                                match self.token_iter.next_token()? {
+                                    Some(TexToken::Char('*')) => starred = true,
+                                    None => (),
+                                    Some(token) => self.token_iter.push_back(token),
+                                }
+                            }
+                            let dimension_tokens = self.token_iter.capture_group().map_err(|e| match e {
+                                ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(control_sequence_name)),
+                                _ => e,
+                            })?;
+                            let dimension = tokens_as_string(dimension_tokens.into_iter())?;
+                            let dimension = dimension.trim();
+                            // As in amsmath, `\mspace{..}` only takes lengths in math units
+                            if command == MSpace && !dimension.ends_with("mu") {
+                                return Err(ParseError::ExpectedMathUnits(Box::from(dimension)));
+                            }
+                            let space = parse_dimension(dimension)?;
+                            results.push(if starred { ParseNode::FixedKerning(space) } else { ParseNode::Kerning(space) });
                         },
                         Kerning(space) => {
                             results.push(ParseNode::Kerning(space))
@@ -315,8 +611,7 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                                 _ => e,
                             })?;
 
-                            let mut forked_parser = Parser::from_iter(Self::EMPTY_COMMAND_COLLECTION, group.into_iter());
-                            forked_parser.current_style = self.current_style;
+                            let mut forked_parser = self.fork(group);
 
                             let mut lines = Vec::new();
 
@@ -337,6 +632,7 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                             results.push(ParseNode::Stack(nodes::Stack {
                                 atom_type,
                                 lines,
+                                centered_on_axis: true,
                             }))
 
                         },
@@ -355,15 +651,42 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
 
                         }
                         Text => {
-                            let text_group = self.token_iter.capture_group().map_err(|e| match e {
-                                ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(control_sequence_name)),
-                                _ => e,
-                            })?;
-                            let text = tokens_as_string(text_group.into_iter())?;
-                            results.push(ParseNode::PlainText(PlainText {
-                                text,
+                            let mut nodes = self.parse_text_argument(control_sequence_name)?;
+                            if nodes.len() == 1 {
+                                results.extend(nodes.pop());
+                            }
+                            else {
+                                results.push(ParseNode::Group(nodes));
+                            }
+                        },
+                        MBox => {
+                            // The text style resets the font size, even in sub-/super-scripts
+                            let mut nodes = vec![ParseNode::Style(layout::Style::Text)];
+                            nodes.extend(self.parse_text_argument(control_sequence_name)?);
+                            results.push(ParseNode::Group(nodes));
+                        },
+                        MakeBox => {
+                            let makebox = self.parse_makebox(control_sequence_name)?;
+                            results.push(ParseNode::MakeBox(makebox));
+                        },
+                        MathLap(alignment) => {
+                            // A box of zero width, out of which the content sticks on the left, on the right or on both sides
+                            let inner = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+                            results.push(ParseNode::MakeBox(nodes::MakeBox {
+                                width: Some(AnyUnit::Em(0.)),
+                                alignment,
+                                inner,
                             }));
                         },
+                        Ignored { optional_argument } => {
+                            if optional_argument {
+                                self.optional_bracket_argument()?;
+                            }
+                        },
+                        MultiColumn => {
+                            let multicolumn = self.parse_multicolumn(control_sequence_name)?;
+                            results.push(ParseNode::MultiColumn(multicolumn));
+                        },
                         BeginEnv => {
                             let env_name_group = self.token_iter.capture_group().map_err(|e| match e {
                                 ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(control_sequence_name)),
@@ -371,8 +694,14 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                             })?;
                             let env_name = tokens_as_string(env_name_group.into_iter())?;
                             let env = Environment::from_name(&env_name).ok_or_else(|| ParseError::UnrecognizedEnvironment(env_name.into_boxed_str()))?;
-                            let array = self.parse_environment(env)?;
-                            results.push(ParseNode::Array(array));
+                            if let Environment::Equation = env {
+                                let nodes = self.parse_equation()?;
+                                results.push(ParseNode::Group(nodes));
+                            }
+                            else {
+                                let array = self.parse_environment(env)?;
+                                results.push(ParseNode::Array(array));
+                            }
                         },
                         EndEnv => {
                             let env_name_group = self.token_iter.capture_group().map_err(|e| match e {
@@ -432,14 +761,31 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                             return Ok(List { nodes: results, group: GroupKind::RightDelimiter });
                         },
                         SymbolCommand(mut symbol) => {
+                            if let Some(atom_type) = self.settings.get_atom_type(symbol.codepoint) {
+                                symbol.atom_type = atom_type;
+                            }
                             match symbol.atom_type {
-                                  TexSymbolType::Accent 
-                                | TexSymbolType::Over   
-                                | TexSymbolType::Under  => {
+                                  TexSymbolType::Accent
+                                | TexSymbolType::AccentWide => {
                                     let nucleus = self.parse_required_argument_as_nodes()?;
                                     results.push(ParseNode::Accent(Accent {
                                         symbol,
                                         nucleus,
+                                        extend: symbol.atom_type == TexSymbolType::AccentWide,
+                                    }));
+                                },
+                                // As in LaTeX, wide constructions like `\overbrace{..}` are operators with limits, 
+                                // so that e.g. a superscript is placed above the brace
+                                  TexSymbolType::Over   
+                                | TexSymbolType::Under  => {
+                                    let nucleus = self.parse_required_argument_as_nodes()?;
+                                    results.push(ParseNode::AtomChange(nodes::AtomChange {
+                                        at: TexSymbolType::Operator(true),
+                                        inner: vec![ParseNode::Accent(Accent {
+                                            symbol,
+                                            nucleus,
+                                            extend: true,
+                                        })],
                                     }));
                                 },
                                 _ => {
@@ -448,21 +794,49 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
                                 },
                             }
                         },
+                        SmallOperator(symbol) => {
+                            results.push(ParseNode::SmallOperator(symbol));
+                        },
                     }
                 },
             }
+
+            if let (Some(spans), Some(start), Some(end)) = (spans.as_deref_mut(), start, self.token_iter.offset()) {
+                if results.len() > previous_length {
+                    spans.push(start .. end);
+                }
+                // scripts are attached to the last node, whose span is extended to cover them
+                else if let Some(span) = spans.last_mut().filter(|_| is_script) {
+                    span.end = end;
+                }
+            }
         }
 
         Ok(List { nodes: results, group: GroupKind::EndOfInput })
     }
 
+    /// Same as [`PrimitiveControlSequence::from_name`], except that symbols overridden in the settings take precedence
+    fn control_sequence_from_name(&self, name : &str) -> Option<PrimitiveControlSequence> {
+        match self.settings.get_symbol(name) {
+            Some(codepoint) => {
+                let atom_type = Symbol::from_name(name).map(|symbol| symbol.atom_type)
+                    .or_else(|| codepoint_atom_type(codepoint))
+                    .unwrap_or(TexSymbolType::Ordinary);
+                Some(PrimitiveControlSequence::SymbolCommand(Symbol { codepoint, atom_type }))
+            },
+            None => PrimitiveControlSequence::from_name(name),
+        }
+    }
+
     fn style_symbol_with_current_style(&self, symbol: &mut Symbol) {
         let Symbol { codepoint, .. } = symbol;
         *codepoint = style_symbol(*codepoint, self.current_style);
     }
 
     fn char_to_symbol(&self, codepoint: char) -> Result<Symbol, ParseError> {
-        let atom_type = codepoint_atom_type(codepoint).ok_or_else(|| ParseError::UnrecognizedSymbol(codepoint))?;
+        let atom_type = self.settings.get_atom_type(codepoint)
+            .or_else(|| codepoint_atom_type(codepoint))
+            .ok_or(ParseError::UnrecognizedSymbol(codepoint))?;
         let mut symbol = Symbol { codepoint, atom_type };
         self.style_symbol_with_current_style(&mut symbol);
         Ok(symbol)
@@ -477,14 +851,14 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
     }
 
     fn parse_next_token_as_delimiter(&mut self) -> ParseResult<Symbol> {
-        let token = self.token_iter.next_token()?.ok_or_else(|| ParseError::ExpectedSymbolForCommand)?;
+        let token = self.token_iter.next_token()?.ok_or(ParseError::ExpectedSymbolForCommand)?;
         match token {
             TexToken::Char(c) => {
                 self.char_to_symbol(c)
             },
             TexToken::ControlSequence(control_sequence_name) => {
                 let command = 
-                    PrimitiveControlSequence::from_name(control_sequence_name)
+                    self.control_sequence_from_name(control_sequence_name)
                     .ok_or_else(|| ParseError::UnrecognizedControlSequence(control_sequence_name.to_string().into_boxed_str()))?
                 ;
                 match command {
@@ -504,11 +878,14 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
 
     fn parse_required_argument_as_nodes(&mut self) -> ParseResult<Vec<ParseNode>> {
         let group = self.token_iter.capture_group()?;
+        self.parse_tokens_as_nodes(group)
+    }
 
+    /// Parses a sequence of tokens, e.g. the tokens captured as the argument of a command, as nodes.
+    fn parse_tokens_as_nodes(&self, group : Vec<TexToken<'a>>) -> ParseResult<Vec<ParseNode>> {
         // Normally all tokens are already expanded after `capture_group`
         // There is no need to have further expansions
-        let mut forked_parser = Parser::from_iter(Self::EMPTY_COMMAND_COLLECTION, group.into_iter());
-        forked_parser.current_style = self.current_style;
+        let mut forked_parser = self.fork(group);
 
         let List { nodes, group } = forked_parser.parse_until_end_of_group()?;
 
@@ -518,10 +895,272 @@ impl<'a, I : Iterator<Item = TexToken<'a>>> Parser<'a, I> {
         
         Ok(nodes)
     }
+
+    /// Parses the argument of `\text{..}` and similar commands as text, cf [`Parser::parse_text`].
+    fn parse_text_argument(&mut self, control_sequence_name : &str) -> ParseResult<Vec<ParseNode>> {
+        let text_group = self.token_iter.capture_group().map_err(|e| match e {
+            ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(control_sequence_name)),
+            _ => e,
+        })?;
+        Parser::from_iter(Self::EMPTY_COMMAND_COLLECTION, text_group.into_iter()).parse_text()
+    }
+
+    /// Parses the arguments of `\makebox[width][pos]{..}`, e.g. `[3em][l]{text}`. Both bracketed arguments are optional.
+    /// The position may be `c`, `l` or `r` ; without a width, the box takes the width of its content.
+    fn parse_makebox(&mut self, control_sequence_name : &str) -> ParseResult<MakeBox> {
+        let width = match self.optional_bracket_argument()? {
+            Some(dimension) => Some(parse_dimension(dimension.trim())?),
+            None => None,
+        };
+        let alignment = match self.optional_bracket_argument()? {
+            Some(position) => parse_position(position)?,
+            None => ArrayColumnAlign::Centered,
+        };
+
+        // Like `\mbox`, the content is text whose font size does not change in sub-/super-scripts
+        let mut inner = vec![ParseNode::Style(layout::Style::Text)];
+        inner.extend(self.parse_text_argument(control_sequence_name)?);
+
+        Ok(MakeBox {
+            width,
+            alignment,
+            inner,
+        })
+    }
+
+    /// Parses the arguments of `\genfrac{left}{right}{thickness}{style}{numerator}{denominator}`, e.g. `{(}{)}{0pt}{}{n}{k}`.
+    /// Empty arguments, or `.` for delimiters, stand for no delimiter, the default thickness and the current style respectively.
+    /// A thickness of zero means that the fraction has no bar, as in `\\binom`.
+    /// The style is `0` (display), `1` (text), `2` (script) or `3` (scriptscript).
+    fn parse_genfrac(&mut self, control_sequence_name : &str) -> ParseResult<ParseNode> {
+        let capture_argument = |parser : &mut Self| -> ParseResult<Vec<TexToken<'a>>> {
+            let group = parser.token_iter.capture_group().map_err(|e| match e {
+                ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(control_sequence_name)),
+                _ => e,
+            })?;
+            Ok(group.into_iter().filter(|token| *token != TexToken::WhiteSpace).collect())
+        };
+
+        let delimiter = |parser : &mut Self| -> ParseResult<Option<Symbol>> {
+            let group = capture_argument(parser)?;
+            if group.is_empty() {
+                return Ok(None);
+            }
+            let mut forked_parser = parser.fork(group);
+            let delimiter = forked_parser.parse_next_token_as_delimiter()?;
+            if forked_parser.token_iter.next_token()?.is_some() {
+                return Err(ParseError::ExpectedDelimiter);
+            }
+            Ok(Some(delimiter).filter(|delimiter| delimiter.codepoint != '.'))
+        };
+        let left_delimiter  = delimiter(self)?;
+        let right_delimiter = delimiter(self)?;
+
+        let thickness = tokens_as_string(capture_argument(self)?.into_iter())?;
+        let bar_thickness = match thickness.as_str() {
+            "" => BarThickness::Default,
+            thickness => match parse_dimension(thickness)? {
+                AnyUnit::Em(0.) | AnyUnit::Px(0.) | AnyUnit::Ex(0.) => BarThickness::None,
+                thickness => BarThickness::Unit(thickness),
+            },
+        };
+
+        let style = tokens_as_string(capture_argument(self)?.into_iter())?;
+        let (style, script_style) = match style.as_str() {
+            ""  => (MathStyle::NoChange, None),
+            "0" => (MathStyle::Display,  None),
+            "1" => (MathStyle::Text,     None),
+            "2" => (MathStyle::NoChange, Some(layout::Style::Script)),
+            "3" => (MathStyle::NoChange, Some(layout::Style::ScriptScript)),
+            _   => return Err(ParseError::UnrecognizedFractionStyle(style.into_boxed_str())),
+        };
+
+        let numerator   = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+        let denominator = self.parse_control_seq_argument_as_nodes(control_sequence_name)?;
+
+        let fraction = ParseNode::GenFraction(GenFraction {
+            numerator, denominator,
+            left_delimiter, right_delimiter,
+            delimiter_size: None,
+            bar_thickness, style,
+            numerator_style: MathStyle::NoChange, denominator_style: MathStyle::NoChange,
+            numerator_alignment: ArrayColumnAlign::Centered,
+        });
+
+        // Fractions in script styles are laid out in a group starting with a style change
+        Ok(match script_style {
+            Some(script_style) => ParseNode::Group(vec![ParseNode::Style(script_style), fraction]),
+            None => fraction,
+        })
+    }
+
+    /// If the next non-whitespace token is `[`, returns everything up to the matching `]` as a string (e.g. `[-0.2em]`, `[l]`).
+    /// Otherwise, leaves the token stream untouched and returns `None`.
+    fn optional_bracket_argument(&mut self) -> ParseResult<Option<String>> {
+        match self.optional_bracket_tokens()? {
+            Some(argument) => Ok(Some(tokens_as_string(argument.into_iter())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Same as [`Parser::optional_bracket_argument`], but returns the tokens between brackets, e.g. to parse them as nodes.
+    fn optional_bracket_tokens(&mut self) -> ParseResult<Option<Vec<TexToken<'a>>>> {
+        let mut token = self.token_iter.next_token()?;
+        while let Some(TexToken::WhiteSpace) = token {
+            token = self.token_iter.next_token()?;
+        }
+
+        match token {
+            Some(TexToken::Char('[')) => {
+                let mut argument = Vec::new();
+                loop {
+                    match self.token_iter.next_token()? {
+                        Some(TexToken::Char(']')) => break,
+                        Some(token) => argument.push(token),
+                        None => return Err(ParseError::UnmatchedBrackets),
+                    }
+                }
+                Ok(Some(argument))
+            },
+            Some(token) => {
+                self.token_iter.push_back(token);
+                Ok(None)
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Parses the contents of `\text{..}`. Text is a plain string, except for `\textsuperscript{..}` and `\textsubscript{..}`,
+    /// which are turned into raised or lowered runs of text.
+    fn parse_text(&mut self) -> ParseResult<Vec<ParseNode>> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+
+        while let Some(token) = self.token_iter.next_token()? {
+            match token {
+                TexToken::ControlSequence(control_sequence_name @ ("textsuperscript" | "textsubscript")) => {
+                    let group = self.token_iter.capture_group().map_err(|e| match e {
+                        ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(control_sequence_name)),
+                        _ => e,
+                    })?;
+                    let script = Parser::from_iter(Self::EMPTY_COMMAND_COLLECTION, group.into_iter()).parse_text()?;
+
+                    if !text.is_empty() {
+                        nodes.push(ParseNode::PlainText(PlainText { text : std::mem::take(&mut text) }));
+                    }
+                    let mut scripts = Scripts {
+                        base: None,
+                        superscript: None,
+                        subscript: None,
+                    };
+                    *scripts.get_script(control_sequence_name == "textsuperscript") = Some(script);
+                    nodes.push(ParseNode::Scripts(scripts));
+                },
+                // Text accents, e.g. `\'e` or `\'{e}`: the letter is followed by the corresponding combining mark
+                TexToken::ControlSequence(control_sequence_name) if text_accent(control_sequence_name).is_some() => {
+                    let group = self.token_iter.capture_group().map_err(|e| match e {
+                        ParseError::ExpectedToken => ParseError::MissingArgForCommand(Box::from(control_sequence_name)),
+                        _ => e,
+                    })?;
+                    let letters = tokens_as_string(group.into_iter())?;
+                    let mut letters = letters.chars();
+                    text.extend(letters.next());
+                    text.extend(text_accent(control_sequence_name));
+                    text.extend(letters);
+                },
+                token @ TexToken::ControlSequence(control_sequence_name) => match PrimitiveControlSequence::from_name(control_sequence_name) {
+                    // Spacing commands, e.g. `\,` or `\quad`
+                    Some(PrimitiveControlSequence::Kerning(space)) => {
+                        if !text.is_empty() {
+                            nodes.push(ParseNode::PlainText(PlainText { text : std::mem::take(&mut text) }));
+                        }
+                        nodes.push(ParseNode::Kerning(space));
+                    },
+                    _ => text.push_str(&tokens_as_string(std::iter::once(token))?),
+                },
+                token => text.push_str(&tokens_as_string(std::iter::once(token))?),
+            }
+        }
+
+        if !text.is_empty() || nodes.is_empty() {
+            nodes.push(ParseNode::PlainText(PlainText { text }));
+        }
+        Ok(nodes)
+    }
+}
+
+/// Returns the combining mark added by a text accent command, e.g. U+0301 Combining Acute Accent for `\'`
+fn text_accent(control_sequence_name: &str) -> Option<char> {
+    Some(match control_sequence_name {
+        "`"  => '\u{0300}',
+        "'"  => '\u{0301}',
+        "^"  => '\u{0302}',
+        "~"  => '\u{0303}',
+        "="  => '\u{0304}',
+        "u"  => '\u{0306}',
+        "."  => '\u{0307}',
+        "\"" => '\u{0308}',
+        "r"  => '\u{030A}',
+        "H"  => '\u{030B}',
+        "v"  => '\u{030C}',
+        "c"  => '\u{0327}',
+        "k"  => '\u{0328}',
+        _ => return None,
+    })
+}
+
+/// Attaches `script` as a superscript or a subscript to the last node of `nodes`, or to an empty base if there is none.
+/// A superscript following primes, as in `f'^2`, is appended to them.
+fn attach_script(nodes: &mut Vec<ParseNode>, script: Vec<ParseNode>, is_superscript: bool) -> ParseResult<()> {
+    let mut scripts = match nodes.pop() {
+        Some(ParseNode::Scripts(scripts)) => scripts,
+        base => Scripts {
+            base: base.map(Box::new),
+            superscript: None,
+            subscript: None,
+        },
+    };
+
+    let sub_or_super_script = scripts.get_script(is_superscript);
+    match sub_or_super_script {
+        None => *sub_or_super_script = Some(script),
+        Some(primes) if primes.iter().all(ParseNode::is_prime) => primes.extend(script),
+        Some(_) => return Err(ParseError::TooManySubscriptsOrSuperscripts),
+    }
+    nodes.push(ParseNode::Scripts(scripts));
+    Ok(())
+}
+
+/// Returns the symbols for a run of `count` primes: the single codepoints `′`, `″`, `‴` and `⁗` for up to four primes, 
+/// followed by as many more as needed beyond that.
+fn prime_symbols(mut count: usize) -> Vec<ParseNode> {
+    const PRIMES : [char; 4] = ['′', '″', '‴', '⁗'];
+    let mut symbols = Vec::new();
+    while count > 0 {
+        let n_primes = count.min(PRIMES.len());
+        symbols.push(ParseNode::Symbol(Symbol { codepoint: PRIMES[n_primes - 1], atom_type: TexSymbolType::Ordinary }));
+        count -= n_primes;
+    }
+    symbols
 }
 
-/// Parses the input as a dimension, e.g. `1cm` or `-2pt or `3.5em`
-fn parse_dimension(mut input_string: &str) -> ParseResult<AnyUnit> {
+/// Parses a horizontal position, as in `\makebox[..][l]{..}` or `\cfrac[r]{..}{..}`: `c`, `l` or `r`
+fn parse_position(position: String) -> ParseResult<ArrayColumnAlign> {
+    match position.trim() {
+        "c" => Ok(ArrayColumnAlign::Centered),
+        "l" => Ok(ArrayColumnAlign::Left),
+        "r" => Ok(ArrayColumnAlign::Right),
+        _   => Err(ParseError::UnrecognizedBoxPosition(position.into_boxed_str())),
+    }
+}
+
+/// Parses the input as a dimension, e.g. `1cm` or `-2pt` or `3.5em`.
+/// 
+/// Lengths relative to the font are kept as such: `em`, `mu` (math units, 1/18 em) and `ex` (x-height, cf [`AnyUnit::Ex`]).
+/// Absolute lengths are converted to pixels at 96 pixels per inch (cf [`Unit::STANDARD_PPI`]): 
+/// `1in` = 72pt, `1cm` = 72/2.54 pt and `1mm` = 72/25.4 pt.
+/// As elsewhere in ReX, `pt` is 1/72 in, i.e. the same as `bp` (TeX's `pt` is slightly smaller, 1/72.27 in).
+fn parse_dimension(input_string: &str) -> ParseResult<AnyUnit> {
     fn is_float_char(character : &char) -> bool {
         character.is_ascii_digit()
         || *character == '-'
@@ -538,9 +1177,16 @@ fn parse_dimension(mut input_string: &str) -> ParseResult<AnyUnit> {
     // expecting 2 ASCII characters representing the dimension
     let dim = dim_string.get(.. 2).ok_or_else(|| ParseError::UnrecognizedDimension(Box::from(input_string)))?;
 
+    let points = |points_per_unit : f64| AnyUnit::Px((Unit::<Pt>::new(number * points_per_unit) * Unit::standard_pt_to_px()).to_unitless());
     match dim {
         "em" => Ok(AnyUnit::Em(number)),
         "px" => Ok(AnyUnit::Px(number)),
+        "ex" => Ok(AnyUnit::Ex(number)),
+        "mu" => Ok(AnyUnit::Em(number / 18.)),
+        "pt" | "bp" => Ok(points(1.)),
+        "in" => Ok(points(72.)),
+        "cm" => Ok(points(72. / 2.54)),
+        "mm" => Ok(points(72. / 25.4)),
         _ => Err(ParseError::UnrecognizedDimension(Box::from(input_string))),
     }
 }
@@ -574,13 +1220,41 @@ fn tokens_as_string<'a, I : Iterator<Item = TexToken<'a>>>(iterator : I) -> Pars
 
 
 /// This function is the API entry point for parsing tex.
-pub fn parse(input: &str) -> ParseResult<Vec<ParseNode>> {
+/// In case of error, the error is returned along with its position in `input`, cf [`LocatedParseError`].
+pub fn parse(input: &str) -> Result<Vec<ParseNode>, LocatedParseError> {
     parse_with_custom_commands(input, &CommandCollection::default())
 }
 
+/// Same as [`parse`], also returning the span in `input` of each node of the formula, e.g. to map nodes back to the source in an editor.
+/// Only the nodes of the outermost list are given a span: the nodes they contain (e.g. the numerator of a fraction) are not.
+/// Cf [`Parser::parse_with_spans`] for details.
+pub fn parse_with_spans(input: &str) -> Result<(Vec<ParseNode>, Vec<Range<usize>>), LocatedParseError> {
+    parse_with_spans_and_settings(input, &CommandCollection::new(), &ParserSettings::new())
+}
 
-pub fn parse_with_custom_commands<'a>(input: & 'a str, custom_commands : &CommandCollection) -> ParseResult<Vec<ParseNode>> {
-    Parser::new(custom_commands, input).parse()
+/// Same as [`parse_with_spans`], with some custom commands and [`ParserSettings`], cf [`parse_with_settings`].
+pub fn parse_with_spans_and_settings(input: &str, custom_commands : &CommandCollection, settings : &ParserSettings) -> Result<(Vec<ParseNode>, Vec<Range<usize>>), LocatedParseError> {
+    let mut parser = Parser::new(custom_commands, input).with_settings(settings);
+    parser.parse_with_spans().map_err(|error| locate_error(&parser, error, input))
+}
+
+
+/// Parses the input, expanding the custom commands and environments in `custom_commands`.
+/// The input may also contain its own definitions, e.g. `\newcommand{\R}{\mathbb{R}} x \in \R`,
+/// which apply from there on, until the end of the enclosing group `{..}`.
+pub fn parse_with_custom_commands(input: &str, custom_commands : &CommandCollection) -> Result<Vec<ParseNode>, LocatedParseError> {
+    parse_with_settings(input, custom_commands, &ParserSettings::new())
+}
+
+/// Same as [`parse_with_custom_commands`], with some [`ParserSettings`] (e.g. to change the atom type of some symbols).
+pub fn parse_with_settings(input: &str, custom_commands : &CommandCollection, settings : &ParserSettings) -> Result<Vec<ParseNode>, LocatedParseError> {
+    let mut parser = Parser::new(custom_commands, input).with_settings(settings);
+    parser.parse().map_err(|error| locate_error(&parser, error, input))
+}
+
+/// Locates an error returned by `parser`, which parsed `input`, cf [`Parser::error_position`]
+fn locate_error<'a, I : Iterator<Item = TexToken<'a>>>(parser : &Parser<'a, I>, error : ParseError, input : &str) -> LocatedParseError {
+    LocatedParseError::new(error, parser.error_position().unwrap_or_default(), input)
 }
 
 
@@ -635,6 +1309,111 @@ mod tests {
         insta::assert_debug_snapshot!(parse(r"\frac\alpha\beta"));
     }
 
+    #[test]
+    fn snapshot_continued_fractions() {
+        insta::assert_debug_snapshot!(parse(r"\cfrac{1}{1+x}"));
+        insta::assert_debug_snapshot!(parse(r"\cfrac[l]{1}{1+x}"));
+        insta::assert_debug_snapshot!(parse(r"\cfrac[t]{1}{1+x}"));
+    }
+
+    #[test]
+    fn snapshot_genfrac() {
+        insta::assert_debug_snapshot!(parse(r"\genfrac{(}{)}{0pt}{}{n}{k}"));
+        insta::assert_debug_snapshot!(parse(r"\genfrac{[}{.}{1.5pt}{1}{a}{b}"));
+        insta::assert_debug_snapshot!(parse(r"\genfrac{}{}{}{3}{a}{b}"));
+    }
+
+    #[test]
+    fn genfrac_errors() {
+        assert_eq!(parse(r"\genfrac{}{}{}{4}{a}{b}").map_err(|e| e.error), Err(ParseError::UnrecognizedFractionStyle(Box::from("4"))));
+        assert_eq!(parse(r"\genfrac{}{}{1ft}{}{a}{b}").map_err(|e| e.error), Err(ParseError::UnrecognizedDimension(Box::from("1ft"))));
+        assert_eq!(parse(r"\genfrac{(}{)}{}{}{a}").map_err(|e| e.error), Err(ParseError::MissingArgForCommand(Box::from("genfrac"))));
+        assert_eq!(parse(r"\genfrac{()}{}{}{}{a}{b}").map_err(|e| e.error), Err(ParseError::ExpectedDelimiter));
+    }
+
+    #[test]
+    fn genfrac_matches_binom() {
+        assert_eq!(parse(r"\genfrac{(}{)}{0pt}{}{n}{k}"), parse(r"\binom{n}{k}"));
+    }
+
+    #[test]
+    fn ignored_commands() {
+        assert_eq!(parse(r"a\relax b"), parse(r"ab"));
+        assert_eq!(parse(r"a\nobreak+\allowbreak b"), parse(r"a+b"));
+        assert_eq!(parse(r"a=b\displaybreak[0]=c"), parse(r"a=b=c"));
+        assert_eq!(parse(r"a\displaybreak b"), parse(r"ab"));
+    }
+
+    #[test]
+    fn hspace() {
+        let mut expected = vec![ParseNode::Kerning(AnyUnit::Em(1.))];
+        expected.extend(parse("a").unwrap());
+        assert_eq!(parse(r"\hspace{1em}a"),  Ok(expected.clone()));
+        assert_eq!(parse(r"\hspace*{1em}a"), Ok(vec![ParseNode::FixedKerning(AnyUnit::Em(1.)), expected[1].clone()]));
+        assert_eq!(parse(r"\hspace*{-2px}").unwrap(), vec![ParseNode::FixedKerning(AnyUnit::Px(-2.))]);
+        assert_eq!(parse(r"\hspace*").map_err(|e| e.error), Err(ParseError::MissingArgForCommand(Box::from("hspace"))));
+        assert_eq!(parse(r"\hspace{1ft}").map_err(|e| e.error), Err(ParseError::UnrecognizedDimension(Box::from("1ft"))));
+        assert_eq!(parse(r"\hspace{-9mu}").unwrap(), vec![ParseNode::Kerning(AnyUnit::Em(-0.5))]);
+    }
+
+    #[test]
+    fn dimensions() {
+        let px = |dimension : &str| match parse_dimension(dimension).unwrap() {
+            AnyUnit::Px(px) => px,
+            unit => panic!("{} should be converted to pixels, got {}", dimension, unit),
+        };
+        assert_close!(px("1in"),   96.,      1e-9);
+        assert_close!(px("2.54cm"), 96.,     1e-9);
+        assert_close!(px("25.4mm"), 96.,     1e-9);
+        assert_close!(px("72pt"),   96.,     1e-9);
+        assert_close!(px("-72bp"), -96.,     1e-9);
+        assert_eq!(parse_dimension("18mu"), Ok(AnyUnit::Em(1.)));
+        assert_eq!(parse_dimension("1.5ex"), Ok(AnyUnit::Ex(1.5)));
+        assert_eq!(parse(r"\rule{1mu}{1ex}"), Ok(vec![ParseNode::Rule(nodes::Rule { width: AnyUnit::Em(1. / 18.), height: AnyUnit::Ex(1.) })]));
+    }
+
+    #[test]
+    fn mspace() {
+        assert_eq!(parse(r"\mspace{18mu}"), parse(r"\quad"));
+        assert_eq!(parse(r"\mspace{ -3mu }"), parse(r"\!"));
+        assert_eq!(parse(r"\mspace{1em}").map_err(|e| e.error), Err(ParseError::ExpectedMathUnits(Box::from("1em"))));
+        assert_eq!(ParseError::ExpectedMathUnits(Box::from("1em")).to_string(), r"\mspace requires a length in mu, got '1em'");
+        assert_eq!(parse(r"\mspace{1xmu}").map_err(|e| e.error), Err(ParseError::UnrecognizedDimension(Box::from("1xmu"))));
+        assert_eq!(parse(r"\mspace").map_err(|e| e.error), Err(ParseError::MissingArgForCommand(Box::from("mspace"))));
+    }
+
+    #[test]
+    fn text_operator_atom_types() {
+        let atom_type_of = |formula : &str| match parse(formula).unwrap().as_slice() {
+            [node] => node.atom_type(),
+            nodes  => panic!("expected a single node, got {:?}", nodes),
+        };
+        assert_eq!(atom_type_of(r"\sin"), TexSymbolType::Operator(false));
+        assert_eq!(atom_type_of(r"\log"), TexSymbolType::Operator(false));
+        assert_eq!(atom_type_of(r"\lim"), TexSymbolType::Operator(true));
+        assert_eq!(atom_type_of(r"\max"), TexSymbolType::Operator(true));
+    }
+
+    #[test]
+    fn snapshot_infix_fractions() {
+        insta::assert_debug_snapshot!(parse(r"1 + a \over b"));
+        insta::assert_debug_snapshot!(parse(r"{a \overwithdelims [. b}"));
+        assert_eq!(parse(r"x{a \atopwithdelims () b}"), parse(r"x{\binom{a}{b}}"));
+        assert_eq!(parse(r"\left(a \over b\right)"), parse(r"\left(\frac{a}{b}\right)"));
+
+        // several infix fractions in the same group are ambiguous, unless braces tell them apart
+        for formula in [r"a \over b \over c", r"a \over b \atop c", r"{a \atopwithdelims () b \over c}", r"\frac{a \over b \over c}{d}"] {
+            assert_eq!(parse(formula).map_err(|e| e.error), Err(ParseError::AmbiguousInfixFraction), "{}", formula);
+        }
+        assert_eq!(parse(r"{a \over b} \over c"), parse(r"\frac{{\frac{a}{b}}}{c}"));
+        assert_eq!(parse(r"a \over {b \over c}"), parse(r"\frac{a}{{\frac{b}{c}}}"));
+        assert!(parse(r"a \over \left(b \over c\right) \frac{d \over e}{f}").is_ok());
+        assert_eq!(
+            parse(r"a \over b \over c").unwrap_err().to_string(),
+            "Ambiguous; you need another { and }\na \\over b \\over c\n          ^",
+        );
+    }
+
     #[test]
     fn snapshot_radicals() {
         // success
@@ -651,6 +1430,10 @@ mod tests {
         insta::assert_debug_snapshot!(parse(r"\sqrt" ));
         insta::assert_debug_snapshot!(parse(r"\sqrt_2" ));
         insta::assert_debug_snapshot!(parse(r"\sqrt^2"));
+
+        // index
+        insta::assert_debug_snapshot!(parse(r"\sqrt[3]{x}"));
+        insta::assert_debug_snapshot!(parse(r"\sqrt [n+1] x"));
     }
 
 
@@ -699,6 +1482,19 @@ mod tests {
         insta::assert_debug_snapshot!(parse(r"\begin{array}{r@{-}|l} 1 & 2 \\ 3 & 4\end{array}"));
         insta::assert_debug_snapshot!(parse(r"\begin{array}{r@{}l} 1 & 2 \\ 3 & 4\end{array}"));
         insta::assert_debug_snapshot!(parse(r"\begin{array}{rl@} 1 & 2 \\ 3 & 4\end{array}"));
+
+        // nested matrices
+        insta::assert_debug_snapshot!(parse(r"\begin{matrix}\begin{pmatrix}a&b\\c&d\end{pmatrix}\end{matrix}"));
+
+        // cells spanning several columns
+        insta::assert_debug_snapshot!(parse(r"\begin{array}{ccc}\multicolumn{2}{l}{a}&b\\1&2&3\end{array}"));
+        insta::assert_debug_snapshot!(parse(r"\begin{array}{cc}\multicolumn{0}{c}{a}\end{array}"));
+        insta::assert_debug_snapshot!(parse(r"\begin{array}{cc}\multicolumn{2}{cl}{a}\end{array}"));
+
+        // extra space between rows
+        insta::assert_debug_snapshot!(parse(r"\begin{matrix}a\\[-0.3em]b\\ [2px] c\end{matrix}"));
+        insta::assert_debug_snapshot!(parse(r"\begin{matrix}a\\[-0.3em b\end{matrix}"));
+        insta::assert_debug_snapshot!(parse(r"\begin{smallmatrix}a&b\\c&d\end{smallmatrix}"));
     }
 
     #[ignore = "unsupported as of yet"]
@@ -715,6 +1511,64 @@ mod tests {
         insta::assert_debug_snapshot!(parse(r"\text{\{\}1}1}"));
         insta::assert_debug_snapshot!(parse(r"\text{}}"));
         insta::assert_debug_snapshot!(parse(r"\text{a{\}}}"));
+        insta::assert_debug_snapshot!(parse(r"\text{4\textsuperscript{th}}"));
+        insta::assert_debug_snapshot!(parse(r"\text{H\textsubscript{2}O}"));
+        insta::assert_debug_snapshot!(parse(r"\text{arg\,max}"));
+        insta::assert_debug_snapshot!(parse(r"\text{a\quad b\;}"));
+        insta::assert_debug_snapshot!(parse(r"\mbox{abc}"));
+        insta::assert_debug_snapshot!(parse(r"\makebox[3em][l]{abc}"));
+        insta::assert_debug_snapshot!(parse(r"\makebox{abc}"));
+        insta::assert_debug_snapshot!(parse(r"\makebox[3em][t]{abc}"));
+        insta::assert_debug_snapshot!(parse(r"\mathllap{=}"));
+    }
+
+    #[test]
+    fn text_accents() {
+        let text = |text : &str| Ok(vec![ParseNode::PlainText(PlainText { text: text.to_string() })]);
+        assert_eq!(parse(r"\text{caf\'e}"),        text("cafe\u{301}"));
+        assert_eq!(parse(r"\text{caf\'{e}s}"),     text("cafe\u{301}s"));
+        assert_eq!(parse(r"\text{gar\c con}"),     text("garc\u{327}on"));
+        assert_eq!(parse(r#"\text{\"{u}ber}"#), text("u\u{308}ber"));
+        assert_eq!(parse(r"\text{caf\'}").map_err(|e| e.error),         Err(ParseError::MissingArgForCommand(Box::from("'"))));
+    }
+
+    #[test]
+    fn snapshot_lines() {
+        insta::assert_debug_snapshot!(parse(r"\overline{x}"));
+        insta::assert_debug_snapshot!(parse(r"\underline{x}"));
+        insta::assert_debug_snapshot!(parse(r"\overline{\underline{x}}"));
+        insta::assert_debug_snapshot!(parse(r"\underbar{x}"));
+
+        // fail
+        insta::assert_debug_snapshot!(parse(r"\overline"));
+    }
+
+    #[test]
+    fn snapshot_phantom() {
+        insta::assert_debug_snapshot!(parse(r"\vphantom{\frac ab}x"));
+        insta::assert_debug_snapshot!(parse(r"\left(\vphantom{\frac ab}x\right)"));
+
+        // fail
+        insta::assert_debug_snapshot!(parse(r"\vphantom"));
+
+        // reserves both width and height
+        insta::assert_debug_snapshot!(parse(r"\phantom{abc}"));
+
+        insta::assert_debug_snapshot!(parse(r"\sqrt{\mathstrut a}"));
+
+        // reserves only width
+        insta::assert_debug_snapshot!(parse(r"\hphantom{abc}"));
+    }
+
+    #[test]
+    fn snapshot_smash() {
+        insta::assert_debug_snapshot!(parse(r"\smash{y}"));
+        insta::assert_debug_snapshot!(parse(r"\smash[t]{\frac ab}"));
+        insta::assert_debug_snapshot!(parse(r"\smash[b]{y}"));
+
+        // fail
+        insta::assert_debug_snapshot!(parse(r"\smash[c]{y}"));
+        insta::assert_debug_snapshot!(parse(r"\smash"));
     }
 
     #[test]
@@ -753,6 +1607,7 @@ mod tests {
         insta::assert_debug_snapshot!(parse(r"\sin(a + b) = \sin a \cos b + \cos b \sin a"));
         insta::assert_debug_snapshot!(parse(r"\det_{B} M"));
         insta::assert_debug_snapshot!(parse(r"\lim_{h \to 0 } \frac{f(x+h)-f(x)}{h}"));
+        insta::assert_debug_snapshot!(parse(r"\smallint_0^1 f"));
     }
 
 
@@ -795,6 +1650,9 @@ mod tests {
         insta::assert_debug_snapshot!(parse(r"\begin{array}{c}\substack{1 \\ \frac{7}8 \\ 4} \\ 5 \end{array}"));
         insta::assert_debug_snapshot!(parse(r"\substack{1 \\}"));
         insta::assert_debug_snapshot!(parse(r"1 \substack{}"));
+        // `\\*` is the same as `\\`
+        assert_eq!(parse(r"\substack{a\\*b}"), parse(r"\substack{a\\b}"));
+        assert_eq!(parse(r"\begin{matrix}a\\*b\\* [1em] c\end{matrix}"), parse(r"\begin{matrix}a\\b\\[1em] c\end{matrix}"));
 
         // failure
         insta::assert_debug_snapshot!(parse(r"\substack{ 1 \\ 2}\\"));
@@ -826,6 +1684,10 @@ mod tests {
         insta::assert_debug_snapshot!(parse("a''''"));
         insta::assert_debug_snapshot!(parse("'a"));
         insta::assert_debug_snapshot!(parse(r"\sqrt'"));
+        insta::assert_debug_snapshot!(parse("a'''''"));
+        insta::assert_debug_snapshot!(parse("a'^2"));
+        insta::assert_debug_snapshot!(parse("a'_1"));
+        insta::assert_debug_snapshot!(parse("a^2'"));
     }
 
     #[test]
@@ -854,9 +1716,39 @@ mod tests {
 
 
         insta::assert_debug_snapshot!(parse(r"\mathop{\overbrace{1}}\limits^{2}"));
+        insta::assert_debug_snapshot!(parse(r"\overbrace{x}^{a}_{b}"));
     }
 
 
+    #[test]
+    fn snapshot_over_under_set() {
+        insta::assert_debug_snapshot!(parse(r"\overset{!}{=}"));
+        insta::assert_debug_snapshot!(parse(r"\underset{n}{xy}"));
+        // empty annotation leaves the base untouched
+        assert_eq!(parse(r"a\overset{}{=}b"), parse(r"a=b"));
+    }
+
+    #[test]
+    fn snapshot_stackrel() {
+        insta::assert_debug_snapshot!(parse(r"\stackrel{?}{=}"));
+        insta::assert_debug_snapshot!(parse(r"\stackrel{def}{xy}"));
+        // `\stackrel` is `\overset` for relations
+        assert_eq!(parse(r"a\stackrel{!}{=}b"), parse(r"a\overset{!}{=}b"));
+    }
+
+    #[test]
+    fn snapshot_boxed() {
+        insta::assert_debug_snapshot!(parse(r"\boxed{x=1}"));
+        insta::assert_debug_snapshot!(parse(r"\boxed"));
+    }
+
+    #[test]
+    fn snapshot_extensible_arrows() {
+        insta::assert_debug_snapshot!(parse(r"\xrightarrow{f}"));
+        insta::assert_debug_snapshot!(parse(r"\xleftarrow[g]{f}"));
+        insta::assert_debug_snapshot!(parse(r"\xrightarrow"));
+    }
+
     #[test]
     fn snapshot_accents() {
         insta::assert_debug_snapshot!(parse(r"\hat{A^2}"));
@@ -866,5 +1758,93 @@ mod tests {
         insta::assert_debug_snapshot!(parse(r"\~o"));
         insta::assert_debug_snapshot!(parse(r"\.o"));
         insta::assert_debug_snapshot!(parse(r"\overbrace{1}"));
+        insta::assert_debug_snapshot!(parse(r"\widehat{ab}"));
+        insta::assert_debug_snapshot!(parse(r"\widetilde{ab}"));
+    }
+
+    #[test]
+    fn symbol_substitutions() {
+        let commands = CommandCollection::default();
+        let codepoint_of = |settings : &ParserSettings| match parse_with_settings(r"\emptyset", &commands, settings).unwrap().as_slice() {
+            [ParseNode::Symbol(symbol)] => symbol.codepoint,
+            nodes => panic!("expected a single symbol, got {:?}", nodes),
+        };
+
+        let settings = ParserSettings::new().symbol("emptyset", '\u{2205}');
+        assert_eq!(codepoint_of(&settings), '\u{2205}');
+        let settings = ParserSettings::new().symbol("emptyset", '\u{00D8}');
+        assert_eq!(codepoint_of(&settings), '\u{00D8}');
+
+        // the symbol keeps the atom type of the built-in command
+        let substitutions = std::collections::HashMap::from([("leq", '\u{2A7D}')]);
+        let settings = ParserSettings::new().symbols(substitutions);
+        assert_eq!(
+            parse_with_settings(r"\leq", &commands, &settings),
+            Ok(vec![ParseNode::Symbol(Symbol { codepoint: '\u{2A7D}', atom_type: TexSymbolType::Relation })]),
+        );
+    }
+
+    #[test]
+    fn spans_of_nodes() {
+        let sources = |input : &'static str, commands : &CommandCollection| -> Vec<&'static str> {
+            let (nodes, spans) = Parser::new(commands, input).parse_with_spans().unwrap();
+            assert_eq!(nodes.len(), spans.len());
+            spans.into_iter().map(|span| &input[span]).collect()
+        };
+        let commands = CommandCollection::default();
+
+        assert_eq!(
+            sources(r"x^2 +  \frac{a}{b}\alpha  y' \begin{matrix} 1 \end{matrix}", &commands),
+            vec!["x^2", "+", r"\frac{a}{b}", r"\alpha", "y'", r"\begin{matrix} 1 \end{matrix}"],
+        );
+        assert_eq!(sources(r" a + b \over c ", &commands), vec![r"a + b \over c"]);
+
+        let commands = CommandCollection::parse(r"\newcommand{\R}{\mathbb{R}}\newcommand{\ab}{ab}").unwrap();
+        assert_eq!(sources(r"x \in \R", &commands), vec!["x", r"\in", r"\R"]);
+        assert_eq!(sources(r"\ab c", &commands), vec![r"\ab", "", "c"]);
+        assert_eq!(sources(r"\newcommand{\R}{\mathbb{R}} x \in \R", &commands), vec!["x", r"\in", r"\R"]);
+
+        let (nodes, spans) = parse_with_spans("a_1").unwrap();
+        assert_eq!(nodes, parse("a_1").unwrap());
+        assert_eq!(spans, vec![0 .. 3]);
+
+        // settings and custom commands apply as with `parse_with_settings`
+        let settings = ParserSettings::new().strict_arrays(true);
+        let (nodes, spans) = parse_with_spans_and_settings(r"\ab+c", &commands, &settings).unwrap();
+        assert_eq!(nodes, parse_with_settings(r"\ab+c", &commands, &settings).unwrap());
+        assert_eq!(spans, vec![0 .. 3, 3 .. 3, 3 .. 4, 4 .. 5]);
+        assert_eq!(
+            parse_with_spans_and_settings(r"\begin{array}{c}a&b\end{array}", &commands, &settings).unwrap_err(),
+            parse_with_settings(r"\begin{array}{c}a&b\end{array}", &commands, &settings).unwrap_err(),
+        );
+    }
+
+    #[test]
+    fn error_positions() {
+        let error = parse(r"a + \foo").unwrap_err();
+        assert_eq!(error.error, ParseError::UnrecognizedControlSequence(Box::from("foo")));
+        assert_eq!(error.position, 4);
+        assert_eq!(error.to_string(), "Unknown control sequence '\\foo'\na + \\foo\n    ^");
+
+        // errors in arguments are located at the command
+        assert_eq!(parse(r"x = \frac{\foo}{b}").unwrap_err().position, 4);
+        // the caret is placed under the line containing the error
+        assert_eq!(parse("α\n+ β^").unwrap_err().to_string(), "Missing group after _ or ^\n+ β^\n   ^");
+        // positions are offsets in the whole input, including the definitions it contains
+        assert_eq!(parse(r"\newcommand{\R}{\mathbb{R}} x \in \R^").unwrap_err().position, 36);
+        assert_eq!(parse(r"x \newcommand{\a}").unwrap_err().position, 2);
+        // errors in the column format of an array are located at the offending token
+        assert_eq!(parse(r"x + \begin{array}{rl@} 1 \end{array}").unwrap_err().position, 20);
+        assert_eq!(parse(r"x + \begin{array}{r|q} 1 \end{array}").unwrap_err().position, 20);
+        assert_eq!(parse(r"x + \begin{array}{c*{2}{l}*{x}{c}} 1 \end{array}").unwrap_err().position, 26);
+
+        // positions inside a character are moved to its start
+        let error = LocatedParseError::new(ParseError::ExpectedToken, 2, "aβc");
+        assert_eq!(error.position, 1);
+        assert_eq!(error.to_string(), format!("{}\naβc\n ^", ParseError::ExpectedToken));
+        let mut error = LocatedParseError::new(ParseError::ExpectedToken, 10, "aβ");
+        assert_eq!(error.position, 3);
+        error.position = 2;
+        assert_eq!(error.to_string(), format!("{}\naβ\n ^", ParseError::ExpectedToken));
     }
 }
@@ -5,6 +5,7 @@ use crate::layout::{self, Style};
 use super::color::RGBA;
 use crate::font::TexSymbolType;
 use super::symbols::Symbol;
+use super::control_sequence::DelimiterSize;
 
 /// Nodes are the output of parsing a LateX formula ; they can then be arranged in space with [`crate::layout::engine::layout`].
 // TODO: It might be worth letting the `Group` variant
@@ -14,6 +15,8 @@ use super::symbols::Symbol;
 pub enum ParseNode {
     /// A simple symbol like 'x' or 'α'
     Symbol(Symbol),
+    /// An operator which, unlike operator [`ParseNode::Symbol`]s (e.g. `\int`), is not enlarged in display style (e.g. `\smallint`)
+    SmallOperator(Symbol),
     /// A group of nodes enclosed by '\left' and '\right'
     Delimited(Delimited),
     /// A delimiter with a user-specified height (created by `\bigl`, `\bigg`, etc.)
@@ -28,9 +31,14 @@ pub enum ParseNode {
     Rule(Rule),
     /// Some (positive or negative) spacing between groups of nodes
     Kerning(AnyUnit),
+    /// Some spacing which, unlike [`ParseNode::Kerning`], is kept at the line breaks of the formula (`\hspace*{..}`)
+    FixedKerning(AnyUnit),
     /// An accent over a certain groups of nodes
     Accent(Accent),
-    /// A style (text cramped) to apply over a certain group of nodes
+    /// A change of style (e.g. `\displaystyle`, `\scriptstyle`) ; the style applies to the nodes which follow it in the same list of nodes, up to the end of the list.
+    /// Enclosing nodes (e.g. groups, fractions) are unaffected, so that the style change is scoped to the innermost group containing it.
+    ///
+    /// Style changes can be inserted in parsed formulas, e.g. `ParseNode::Style(Style::Script)` before some nodes lays them out in script size.
     Style(Style),
     /// A span of normal text without special math symbol replacement, spacing, etc.
     PlainText(PlainText),
@@ -38,12 +46,28 @@ pub enum ParseNode {
     AtomChange(AtomChange),
     /// A change in color
     Color(Color),
+    /// Nodes which take up space but are not drawn (e.g. `\phantom{..}`, `\hphantom{..}`, `\vphantom{..}`)
+    Phantom(Phantom),
+    /// Nodes which are drawn but whose height and/or depth are ignored (`\smash{..}`, `\smash[t]{..}`, `\smash[b]{..}`)
+    Smash(Smash),
+    /// A horizontal line drawn over or under some nodes (`\overline{..}`, `\underline{..}`, `\overbar{..}`, `\underbar{..}`)
+    Line(Line),
+    /// Nodes struck through by one or two diagonal lines (`\cancel{..}`, `\bcancel{..}`, `\xcancel{..}`)
+    Cancel(Cancel),
+    /// Nodes surrounded by a rectangular frame (`\boxed{..}`)
+    Boxed(Boxed),
+    /// An arrow stretched to the width of the labels above and below it (`\xrightarrow[..]{..}`, `\xleftarrow[..]{..}`)
+    ExtensibleArrow(ExtensibleArrow),
     /// A group of nodes
     Group(Vec<ParseNode>),
     /// Nodes stacked on top of each other with no alignment (the \substack command)
     Stack(Stack),
     /// Array of formulas, with some alignment
     Array(Array),
+    /// A cell of an array spanning several columns (`\multicolumn{..}{..}{..}`)
+    MultiColumn(MultiColumn),
+    /// Some text in a box of a given width (`\makebox[..][..]{..}`), or some nodes in a box of zero width (`\mathllap{..}`, `\mathrlap{..}`, `\mathclap{..}`)
+    MakeBox(MakeBox),
     /// An invisible node, used only for spacing rule in `\begin{aligned} .. \end{aligned}` environments
     DummyNode(DummyNode)
 
@@ -64,6 +88,9 @@ pub struct ArrayColumnsFormatting {
 
     /// Separators between each column, could be vertical bars or
     pub separators: Vec<Vec<ColSeparator>>,
+
+    /// Fixed width of each column, for `p{..}` columns (e.g. `p{3cm}`) ; other columns are as wide as their widest cell
+    pub widths: Vec<Option<AnyUnit>>,
 }
 
 
@@ -75,6 +102,7 @@ pub struct ArrayColumnsFormatting {
 /// where a `[unit]` is any recognized dimension which will add (or subtract)
 /// space between the rows.  Note, the last line termination is ignored
 /// if the a line is empty.
+/// A cell may span several columns if it consists of a single [`ParseNode::MultiColumn`] node.
 pub type CellContent = Vec<ParseNode>;
 
 
@@ -100,7 +128,7 @@ impl Default for ArrayColumnAlign {
 }
 
 
-/// An array of nodes as created by e.g. `\begin{array}{c} .. \end{array}`, `\begin{pmatrix} .. \end{pmatrix}` or `\begin{aligned} .. \end{aligned}`
+/// An array of nodes as created by e.g. `\begin{array}{c} .. \end{array}`, `\begin{pmatrix} .. \end{pmatrix}`, `\begin{smallmatrix} .. \end{smallmatrix}` or `\begin{aligned} .. \end{aligned}`
 #[derive(Debug, Clone, PartialEq)]
 pub struct Array {
     /// The alignment arguments (clr) for each row, plus separators (bars and @-expressions).  Default: center.
@@ -109,6 +137,10 @@ pub struct Array {
     /// A collection of rows.  Each row consists of one `Vec<Expression>`.
     pub rows: Vec<Vec<CellContent>>,
 
+    /// Extra vertical space (possibly negative) after each row, as given by `\\[dimen]`.
+    /// There are as many entries as there are rows.
+    pub row_spacing: Vec<Option<AnyUnit>>,
+
     /// The left delimiter for the array (optional).
     pub left_delimiter: Option<Symbol>,
 
@@ -124,6 +156,10 @@ pub struct Array {
     /// when they are embedded in an environment with text style.
     /// In `\begin{array} .. \end{array}`, they are rendered in text style
     pub cell_layout_style : layout::Style,
+
+    /// Whether rows and columns are packed tightly, as in `\begin{smallmatrix} .. \end{smallmatrix}`: 
+    /// cells are not given the height of a line of text and columns are closer (cf [`SMALL_MATRIX_COLUMN_SEP`](crate::layout::constants::SMALL_MATRIX_COLUMN_SEP))
+    pub tight_spacing : bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -148,6 +184,9 @@ pub struct Stack {
     pub atom_type: TexSymbolType,
     /// Lines of formulas to stack on top of each other.
     pub lines: Vec<Vec<ParseNode>>,
+    /// If true (the default for `\substack`), the stack is vertically centered on the math axis, like a fraction ;
+    /// otherwise, it is vertically centered on the baseline.
+    pub centered_on_axis: bool,
 }
 
 /// Cf [`ParseNode::Delimited`]
@@ -182,12 +221,21 @@ pub struct ExtendedDelimiter {
     /// The shape and size of an extended delimiter like `(` are computed by assuming it will enclose a content of size `height_enclosed_content`, 
     /// as if it were in `\left(\rule{0pt}{<height_enclosed_content>}\right.`
     pub height_enclosed_content : AnyUnit,
+    /// Size class requested by `\big`, `\Big`, etc. if any.
+    /// If [`crate::layout::LayoutSettings::delimiter_sizes_from_font`] is set, the delimiter is the font's variant of the corresponding rank, rather than one fitting `height_enclosed_content`.
+    pub size : Option<DelimiterSize>,
 }
 
 impl ExtendedDelimiter {
     /// Creates a new delimiter from t
     pub fn new(symbol: Symbol, height_enclosed_content: AnyUnit) -> Self {
-        Self { symbol, height_enclosed_content }
+        Self { symbol, height_enclosed_content, size: None }
+    }
+
+    /// Records the size class (`\big`, `\Big`, etc.) the delimiter was requested with
+    pub fn with_size(mut self, size: DelimiterSize) -> Self {
+        self.size = Some(size);
+        self
     }
 }
 
@@ -244,6 +292,8 @@ pub struct Accent {
     pub symbol: Symbol,
     /// The nodes "below" the accent.
     pub nucleus: Vec<ParseNode>,
+    /// Whether the accent stretches to cover the nucleus, as for `\widehat{..}`, or is drawn at its natural size, as for `\hat{..}`.
+    pub extend: bool,
 }
 
 /// Cf [`ParseNode::Rule`]. While intended to be used as lines, rules can in fact be any rectangle.
@@ -261,7 +311,8 @@ pub struct Rule {
 pub struct Radical {
     /// The nodes that the root covers
     pub inner: Vec<ParseNode>,
-    // pub superscript: Vec<ParseNode>,
+    /// The index of the root, e.g. `3` in `\sqrt[3]{x}`, if any
+    pub index: Option<Vec<ParseNode>>,
 }
 
 /// Cf [`ParseNode::GenFraction`]
@@ -277,8 +328,16 @@ pub struct GenFraction {
     pub left_delimiter: Option<Symbol>,
     /// symbol closing the fraction.
     pub right_delimiter: Option<Symbol>,
+    /// fixed size of the delimiters, as with `\big`, `\Big`, etc. ; if `None`, the delimiters grow with the fraction.
+    pub delimiter_size: Option<DelimiterSize>,
     /// style for the whole fraction.
     pub style: MathStyle,
+    /// style for the numerator ; if [`MathStyle::NoChange`], the numerator is one style smaller than the fraction (e.g. text style in a displayed fraction)
+    pub numerator_style: MathStyle,
+    /// style for the denominator ; if [`MathStyle::NoChange`], the denominator is one style smaller than the fraction
+    pub denominator_style: MathStyle,
+    /// alignment of the numerator when it is narrower than the denominator (e.g. `\cfrac[l]{..}{..}`)
+    pub numerator_alignment: ArrayColumnAlign,
 }
 
 /// Cf [`ParseNode::Color`]
@@ -290,6 +349,108 @@ pub struct Color {
     pub inner: Vec<ParseNode>,
 }
 
+/// Cf [`ParseNode::Phantom`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phantom {
+    /// nodes whose dimensions are reserved
+    pub inner: Vec<ParseNode>,
+    /// whether the width of the nodes is reserved
+    pub horizontal: bool,
+    /// whether the height and depth of the nodes are reserved
+    pub vertical: bool,
+}
+
+/// Cf [`ParseNode::Smash`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Smash {
+    /// nodes drawn without their height and/or depth
+    pub inner: Vec<ParseNode>,
+    /// whether the height of the nodes is ignored
+    pub top: bool,
+    /// whether the depth of the nodes is ignored
+    pub bottom: bool,
+}
+
+/// Cf [`ParseNode::Line`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    /// whether the line is drawn above or below the nodes
+    pub placement: LinePlacement,
+    /// whether the line is drawn as close to the nodes as its thickness (`\overbar`, `\underbar`), rather than at the gap prescribed by the font
+    pub tight: bool,
+    /// nodes decorated by the line
+    pub inner: Vec<ParseNode>,
+}
+
+/// Cf [`ParseNode::Cancel`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cancel {
+    /// which diagonal(s) strike through the nodes
+    pub direction: CancelDirection,
+    /// nodes struck through
+    pub inner: Vec<ParseNode>,
+}
+
+/// Cf [`ParseNode::Boxed`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Boxed {
+    /// nodes in the frame
+    pub inner: Vec<ParseNode>,
+}
+
+/// Cf [`ParseNode::ExtensibleArrow`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensibleArrow {
+    /// the arrow, stretched horizontally
+    pub symbol: Symbol,
+    /// label above the arrow, e.g. `f` in `\xrightarrow{f}`
+    pub over: Vec<ParseNode>,
+    /// label below the arrow, e.g. `g` in `\xrightarrow[g]{f}`
+    pub under: Vec<ParseNode>,
+}
+
+/// Cf [`ParseNode::MultiColumn`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiColumn {
+    /// number of columns spanned by the cell
+    pub span: usize,
+    /// alignment of the nodes within the spanned columns
+    pub alignment: ArrayColumnAlign,
+    /// content of the cell
+    pub inner: Vec<ParseNode>,
+}
+
+/// Cf [`ParseNode::MakeBox`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MakeBox {
+    /// width of the box ; if `None`, the box is as wide as its content
+    pub width: Option<AnyUnit>,
+    /// alignment of the content within the box
+    pub alignment: ArrayColumnAlign,
+    /// content of the box
+    pub inner: Vec<ParseNode>,
+}
+
+/// Position of a line relative to the nodes it decorates
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LinePlacement {
+    /// line above the nodes (`\overline`)
+    Over,
+    /// line below the nodes (`\underline`)
+    Under,
+}
+
+/// Diagonal(s) drawn by [`Cancel`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CancelDirection {
+    /// line from the bottom left to the top right corner (`\cancel`)
+    Forward,
+    /// line from the top left to the bottom right corner (`\bcancel`)
+    Backward,
+    /// both lines (`\xcancel`)
+    Cross,
+}
+
 /// Type of thickness for fraction and binomials
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum BarThickness {
@@ -317,7 +478,7 @@ impl ParseNode {
     /// if parse node is a single symbol, returns it. Otherwise, `None`.
     pub fn is_symbol(&self) -> Option<Symbol> {
         match *self {
-            ParseNode::Symbol(sym) => Some(sym),
+            ParseNode::Symbol(sym) | ParseNode::SmallOperator(sym) => Some(sym),
             ParseNode::Scripts(Scripts { ref base, .. }) =>
                 base.as_ref().and_then(|b| b.is_symbol()),
             ParseNode::Accent(ref acc) => is_symbol(&acc.nucleus),
@@ -327,10 +488,15 @@ impl ParseNode {
         }
     }
 
+    /// Whether the node is one of the prime symbols `′`, `″`, `‴` or `⁗`, which runs of `'` produce
+    pub fn is_prime(&self) -> bool {
+        matches!(self, ParseNode::Symbol(Symbol { codepoint: '′' | '″' | '‴' | '⁗', .. }))
+    }
+
     /// sets atom type
     pub fn set_atom_type(&mut self, at: TexSymbolType) {
         match *self {
-            ParseNode::Symbol(ref mut sym) => sym.atom_type = at,
+            ParseNode::Symbol(ref mut sym) | ParseNode::SmallOperator(ref mut sym) => sym.atom_type = at,
             ParseNode::Scripts(Scripts { ref mut base, .. }) => {
                 if let Some(ref mut b) = *base {
                     b.set_atom_type(at);
@@ -347,6 +513,7 @@ impl ParseNode {
     pub fn atom_type(&self) -> TexSymbolType {
         match *self {
             ParseNode::Symbol(ref sym)  => sym.atom_type,
+            ParseNode::SmallOperator(ref sym) => sym.atom_type,
             ParseNode::Delimited(_)     => TexSymbolType::Inner,
             ParseNode::Radical(_)       => TexSymbolType::Alpha,
             ParseNode::PlainText(_)     => TexSymbolType::Alpha,
@@ -358,6 +525,7 @@ impl ParseNode {
 
             ParseNode::Rule(_)          => TexSymbolType::Alpha,
             ParseNode::Kerning(_)       => TexSymbolType::Transparent,
+            ParseNode::FixedKerning(_)  => TexSymbolType::Transparent,
             ParseNode::Accent(ref acc)  => acc.nucleus.first()
                 .map(|acc| acc.atom_type())
                 .unwrap_or(TexSymbolType::Alpha),
@@ -367,8 +535,23 @@ impl ParseNode {
             ParseNode::Color(ref clr)     => clr.inner.first()
                 .map(|first| first.atom_type())
                 .unwrap_or(TexSymbolType::Alpha),
+            // A phantom of a single node (e.g. `\phantom{\sum}`) stands in for that node: it gets the same spacing and takes limits likewise
+            ParseNode::Phantom(ref phantom) => match phantom.inner.as_slice() {
+                [node] if phantom.horizontal && phantom.vertical => node.atom_type(),
+                _ => TexSymbolType::Alpha,
+            },
+            ParseNode::Smash(ref smash) => match smash.inner.as_slice() {
+                [node] => node.atom_type(),
+                _ => TexSymbolType::Alpha,
+            },
+            ParseNode::Line(_)            => TexSymbolType::Alpha,
+            ParseNode::Cancel(_)          => TexSymbolType::Alpha,
+            ParseNode::Boxed(_)           => TexSymbolType::Alpha,
+            ParseNode::ExtensibleArrow(ref arrow) => arrow.symbol.atom_type,
 
             ParseNode::Array(_)      => TexSymbolType::Inner,
+            ParseNode::MultiColumn(_) => TexSymbolType::Alpha,
+            ParseNode::MakeBox(_)     => TexSymbolType::Alpha,
             ParseNode::Stack(ref s)  => s.atom_type,
 
             ParseNode::DummyNode(ref dummy) => dummy.at,
@@ -386,4 +569,17 @@ pub fn is_symbol(contents: &[ParseNode]) -> Option<Symbol> {
     }
 
     contents[0].is_symbol()
+}
+
+/// if the cell of an array consists of a single `\multicolumn{..}{..}{..}`, returns it ; otherwise, None.
+pub fn as_multicolumn(cell: &[ParseNode]) -> Option<&MultiColumn> {
+    match cell {
+        [ParseNode::MultiColumn(multicolumn)] => Some(multicolumn),
+        _ => None,
+    }
+}
+
+/// Number of columns spanned by a cell of an array
+pub fn cell_span(cell: &[ParseNode]) -> usize {
+    as_multicolumn(cell).map_or(1, |multicolumn| multicolumn.span)
 }
\ No newline at end of file
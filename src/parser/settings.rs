@@ -0,0 +1,74 @@
+//! User configuration of the parser (cf [`ParserSettings`])
+
+use unicode_math::TexSymbolType;
+
+/// Settings which change how formulas are parsed.
+/// Settings are created with [`ParserSettings::new`] and modified with builder methods, e.g.
+/// ```
+/// # use rex::parser::ParserSettings;
+/// # use rex::font::TexSymbolType;
+/// // '/' is treated as a binary operator, as in `a / b`
+/// let settings = ParserSettings::new().atom_type('/', TexSymbolType::Binary);
+/// // `\emptyset` is displayed as 'Ø' rather than '∅'
+/// let settings = settings.symbol("emptyset", 'Ø');
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParserSettings {
+    atom_types : Vec<(char, TexSymbolType)>,
+    symbols    : Vec<(Box<str>, char)>,
+    strict_arrays : bool,
+}
+
+impl ParserSettings {
+    /// Creates settings with no customization
+    pub const fn new() -> Self {
+        Self { atom_types: Vec::new(), symbols: Vec::new(), strict_arrays: false }
+    }
+
+    /// Overrides the atom type of a symbol, wherever it appears in a formula (e.g. as `/` or as `\div`).
+    /// The atom type of a symbol determines the spacing around it.
+    pub fn atom_type(mut self, codepoint : char, atom_type : TexSymbolType) -> Self {
+        self.atom_types.retain(|(c, _)| *c != codepoint);
+        self.atom_types.push((codepoint, atom_type));
+        self
+    }
+
+    /// Returns the atom type the symbol has been given, if it has been overridden
+    pub fn get_atom_type(&self, codepoint : char) -> Option<TexSymbolType> {
+        self.atom_types.iter()
+            .find(|(c, _)| *c == codepoint)
+            .map(|(_, atom_type)| *atom_type)
+    }
+
+    /// Makes the command `\name` produce the given symbol, instead of the built-in one (e.g. to choose between '∅' and 'Ø' for `\emptyset`, depending on the font).
+    /// The symbol keeps the atom type of the built-in command, if there is one.
+    pub fn symbol(mut self, name : &str, codepoint : char) -> Self {
+        self.symbols.retain(|(n, _)| &**n != name);
+        self.symbols.push((Box::from(name), codepoint));
+        self
+    }
+
+    /// Same as [`ParserSettings::symbol`], for several commands at once (e.g. from a `HashMap<&str, char>` of command names to symbols).
+    pub fn symbols<'n>(self, substitutions : impl IntoIterator<Item = (&'n str, char)>) -> Self {
+        substitutions.into_iter().fold(self, |settings, (name, codepoint)| settings.symbol(name, codepoint))
+    }
+
+    /// Returns the symbol produced by the command `\name`, if it has been overridden
+    pub fn get_symbol(&self, name : &str) -> Option<char> {
+        self.symbols.iter()
+            .find(|(n, _)| &**n == name)
+            .map(|(_, codepoint)| *codepoint)
+    }
+
+    /// If set, a row of an array or matrix with fewer or more cells than the array has columns is an error (cf [`ParseError::RaggedArrayRow`](super::error::ParseError::RaggedArrayRow)).
+    /// By default, such rows are accepted: missing cells are left empty, and centered columns are added to the column format for extra cells.
+    pub fn strict_arrays(mut self, enabled : bool) -> Self {
+        self.strict_arrays = enabled;
+        self
+    }
+
+    /// Returns whether rows of arrays must have as many cells as the array has columns (cf [`ParserSettings::strict_arrays`])
+    pub fn get_strict_arrays(&self) -> bool {
+        self.strict_arrays
+    }
+}
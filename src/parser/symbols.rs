@@ -16,7 +16,7 @@ impl Symbol {
         others(name).or_else(|| symbol(name))
     }
 
-    const NULL_DELIMITER : char = '.';
+    pub(crate) const NULL_DELIMITER : char = '.';
 
     /// Checks if symbol is an open delimiter
     pub fn is_open_delimiter(&self) -> bool {
@@ -135,6 +135,9 @@ fn others(name: &str) -> Option<Symbol> {
         "cdots" => Symbol { codepoint: '\u{22EF}', atom_type: TexSymbolType::Ordinary }, // 8943
         "dots"  => Symbol { codepoint: '\u{22EF}', atom_type: TexSymbolType::Ordinary }, // 8943
         "ldots" => Symbol { codepoint: '\u{2026}', atom_type: TexSymbolType::Ordinary }, // 8230
+        // unicode-math makes these relations, which would put thick spaces around them, e.g. between the cells of a matrix row
+        "vdots" => Symbol { codepoint: '\u{22EE}', atom_type: TexSymbolType::Ordinary }, // 8942
+        "ddots" => Symbol { codepoint: '\u{22F1}', atom_type: TexSymbolType::Ordinary }, // 8945
 
         // Misc symbols shim
         "|" => Symbol { codepoint: '\u{2016}', atom_type: TexSymbolType::Fence }, // 8214
@@ -6,6 +6,16 @@ pub enum NumberOfPrimes {
     Simple, Double, Triple
 }
 
+impl NumberOfPrimes {
+    pub fn count(&self) -> usize {
+        match self {
+            NumberOfPrimes::Simple => 1,
+            NumberOfPrimes::Double => 2,
+            NumberOfPrimes::Triple => 3,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TexToken<'a> {
@@ -26,11 +36,23 @@ pub enum TexToken<'a> {
 
 pub struct TokenIterator<'a> {
     input_processor : InputProcessor<'a>,
+    /// the whole input, from which offsets are counted
+    input : & 'a str,
 }
 
 impl<'a> TokenIterator<'a> {
     pub fn new(string : & 'a str) -> Self {
-        Self { input_processor: InputProcessor::new(string) }
+        Self { input_processor: InputProcessor::new(string), input: string }
+    }
+
+    /// The input of the iterator, as given to [`TokenIterator::new`]
+    pub fn input(&self) -> & 'a str {
+        self.input
+    }
+
+    /// The offset in bytes, from the start of the input, of the next token
+    pub fn offset(&self) -> usize {
+        self.input.len() - self.input_processor.stream.len()
     }
 
     pub fn input_processor_mut(&mut self) -> &mut InputProcessor<'a> {
@@ -43,7 +65,7 @@ impl<'a> Iterator for TokenIterator<'a> {
     type Item = TexToken<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Self { input_processor } = self;
+        let Self { input_processor, .. } = self;
 
         let (first_char, rest) = split_first_char((*input_processor).stream)?;
 
@@ -148,7 +170,7 @@ impl<'a> InputProcessor<'a> {
     }
 
     pub fn token_iter(self) -> TokenIterator<'a> {
-        TokenIterator { input_processor: self }
+        TokenIterator { input: self.stream, input_processor: self }
     }
 }
 
@@ -63,6 +63,14 @@ impl GraphicsBackend for CairoBackend {
         context.fill().unwrap();
     }
 
+    fn line(&mut self, from: crate::Cursor, to: crate::Cursor, thickness: f64) {
+        let context = &self.context;
+        context.set_line_width(thickness);
+        context.move_to(from.x, from.y);
+        context.line_to(to.x, to.y);
+        context.stroke().unwrap();
+    }
+
 
     fn begin_color(&mut self, color: crate::RGBA) {
         let old_color = std::mem::replace(&mut self.current_color, (color.0, color.1, color.2, color.3,));
@@ -141,4 +149,34 @@ impl<'a> FontBackend<TtfMathFont<'a>> for CairoBackend {
         builder.fill();
         context.restore().unwrap();
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use cairo::{Context, Format, ImageSurface};
+    use crate::{GraphicsBackend, Cursor};
+    use super::CairoBackend;
+
+    #[test]
+    fn line_is_stroked_between_points() {
+        let mut surface = ImageSurface::create(Format::ARgb32, 20, 20).unwrap();
+        let mut backend = CairoBackend::new(Context::new(&surface).unwrap());
+        backend.line(Cursor { x: 2., y: 18. }, Cursor { x: 18., y: 2. }, 2.);
+        // the context must be released before the pixels of the surface can be read
+        drop(backend.context());
+        surface.flush();
+
+        let stride = surface.stride() as usize;
+        let data = surface.data().unwrap();
+        // ARGB32 pixels are stored as native-endian 32-bit integers, with alpha in the most significant byte
+        let alpha = |x : usize, y : usize| {
+            let offset = y * stride + 4 * x;
+            u32::from_ne_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) >> 24
+        };
+        assert!(alpha(10, 10) > 0);
+        assert!(alpha(5, 15) > 0);
+        assert_eq!(alpha(3, 3), 0);
+        assert_eq!(alpha(17, 17), 0);
+    }
+}
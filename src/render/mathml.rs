@@ -0,0 +1,633 @@
+//! Export of parsed formulas to [MathML](https://www.w3.org/TR/MathML3/)
+//!
+//! Unlike the [`Backend`](crate::Backend)s, [`MathMlWriter`] does not need a font nor a layout: it walks the parse nodes
+//! and leaves the typesetting to the MathML renderer (e.g. a web browser).
+//! Some information is lost in the process, such as the alignment of the content of `\makebox[..][..]{..}`
+//! or the separators of arrays other than vertical bars between columns.
+
+use std::fmt::Write;
+
+use crate::font::TexSymbolType;
+use crate::layout::Style;
+use crate::parser::ParseNode;
+use crate::parser::nodes::{self, ArrayColumnAlign, BarThickness, CancelDirection, ColSeparator, LinePlacement, MathStyle};
+use crate::parser::symbols::Symbol;
+use crate::parser::DelimiterSize;
+
+/// Writes parse nodes as a `<math>..</math>` MathML element, cf [module-level documentation](self).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MathMlWriter {
+    /// When set to true, the formula is written as a displayed formula (`display="block"`), otherwise as an inline formula.
+    pub display: bool,
+}
+
+impl MathMlWriter {
+    /// Creates a writer for inline formulas.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the MathML element representing `nodes`.
+    pub fn write(&self, nodes: &[ParseNode]) -> String {
+        let mut output = String::new();
+        output.push_str(r#"<math xmlns="http://www.w3.org/1998/Math/MathML""#);
+        if self.display {
+            output.push_str(r#" display="block""#);
+        }
+        output.push('>');
+        write_nodes(&mut output, nodes);
+        output.push_str("</math>");
+        output
+    }
+}
+
+
+/// Writes a list of nodes, one element per node.
+/// Since a style change applies to the nodes following it, the nodes after a [`ParseNode::Style`] are enclosed in a `<mstyle>` element.
+fn write_nodes(out: &mut String, nodes: &[ParseNode]) {
+    let mut i = 0;
+    while i < nodes.len() {
+        match &nodes[i] {
+            ParseNode::Style(style) => {
+                open_style(out, *style);
+                write_nodes(out, &nodes[i + 1 ..]);
+                out.push_str("</mstyle>");
+                return;
+            },
+            // Consecutive digits make up a single number
+            ParseNode::Symbol(symbol) if symbol.codepoint.is_numeric() => {
+                let length = number_length(&nodes[i ..]);
+                out.push_str("<mn>");
+                for node in &nodes[i .. i + length] {
+                    if let Some(symbol) = node.is_symbol() {
+                        push_escaped(out, symbol.codepoint);
+                    }
+                }
+                out.push_str("</mn>");
+                i += length;
+                continue;
+            },
+            node => write_node(out, node),
+        }
+        i += 1;
+    }
+}
+
+/// Writes nodes as a single element, e.g. as the numerator of a fraction ; several nodes are grouped in a `<mrow>`.
+fn write_row(out: &mut String, nodes: &[ParseNode]) {
+    match nodes {
+        [node] if !matches!(node, ParseNode::Style(_) | ParseNode::DummyNode(_)) => write_node(out, node),
+        _ => {
+            out.push_str("<mrow>");
+            write_nodes(out, nodes);
+            out.push_str("</mrow>");
+        },
+    }
+}
+
+/// Writes nodes as a single element in the given style (e.g. the numerator of `\cfrac{..}{..}`)
+fn write_row_in_style(out: &mut String, nodes: &[ParseNode], style: MathStyle) {
+    if open_math_style(out, style) {
+        write_nodes(out, nodes);
+        out.push_str("</mstyle>");
+    }
+    else {
+        write_row(out, nodes);
+    }
+}
+
+fn write_node(out: &mut String, node: &ParseNode) {
+    match node {
+        ParseNode::Symbol(symbol) => write_symbol(out, symbol.codepoint, symbol.atom_type),
+        ParseNode::SmallOperator(symbol) => {
+            out.push_str(r#"<mo largeop="false">"#);
+            push_escaped(out, symbol.codepoint);
+            out.push_str("</mo>");
+        },
+        ParseNode::Delimited(delimited) => {
+            out.push_str("<mrow>");
+            let delimiters = delimited.delimiters();
+            for (i, inner) in delimited.inners().iter().enumerate() {
+                write_delimiter(out, delimiters[i], true, None);
+                write_nodes(out, inner);
+            }
+            if let Some(&last) = delimiters.last() {
+                write_delimiter(out, last, true, None);
+            }
+            out.push_str("</mrow>");
+        },
+        ParseNode::ExtendedDelimiter(delimiter) => write_delimiter(out, delimiter.symbol, false, delimiter.size),
+        ParseNode::Radical(radical) => match &radical.index {
+            Some(index) => {
+                out.push_str("<mroot>");
+                write_row(out, &radical.inner);
+                write_row(out, index);
+                out.push_str("</mroot>");
+            },
+            None => {
+                out.push_str("<msqrt>");
+                write_nodes(out, &radical.inner);
+                out.push_str("</msqrt>");
+            },
+        },
+        ParseNode::GenFraction(fraction) => write_fraction(out, fraction),
+        ParseNode::Scripts(scripts) => write_scripts(out, scripts),
+        ParseNode::Rule(rule) => {
+            write!(out, r#"<mspace width="{}" height="{}" mathbackground="currentColor"/>"#, rule.width, rule.height).unwrap();
+        },
+        ParseNode::Kerning(width) | ParseNode::FixedKerning(width) => write!(out, r#"<mspace width="{}"/>"#, width).unwrap(),
+        ParseNode::Accent(accent) => {
+            let under = matches!(accent.symbol.atom_type, TexSymbolType::BotAccent | TexSymbolType::BotAccentWide);
+            let (tag, attribute) = if under { ("munder", "accentunder") } else { ("mover", "accent") };
+            write!(out, r#"<{} {}="true">"#, tag, attribute).unwrap();
+            write_row(out, &accent.nucleus);
+            write!(out, r#"<mo stretchy="{}">"#, accent.extend).unwrap();
+            push_escaped(out, spacing_accent(accent.symbol.codepoint));
+            write!(out, "</mo></{}>", tag).unwrap();
+        },
+        // Style changes apply to the nodes which follow them, cf `write_nodes`
+        ParseNode::Style(_) => (),
+        ParseNode::PlainText(text) => {
+            out.push_str("<mtext>");
+            for character in text.text.chars() {
+                push_escaped(out, character);
+            }
+            out.push_str("</mtext>");
+        },
+        ParseNode::AtomChange(change) => write_atom_change(out, change),
+        ParseNode::Color(color) => {
+            // NB: the alpha channel is ignored
+            let crate::RGBA(r, g, b, _) = color.color;
+            write!(out, r##"<mstyle mathcolor="#{:02x}{:02x}{:02x}">"##, r, g, b).unwrap();
+            write_nodes(out, &color.inner);
+            out.push_str("</mstyle>");
+        },
+        ParseNode::Phantom(phantom) => {
+            let padding = match (phantom.horizontal, phantom.vertical) {
+                (true, false) => Some(r#"height="0" depth="0""#),
+                (false, true) => Some(r#"width="0""#),
+                _             => None,
+            };
+            if let Some(padding) = padding {
+                write!(out, "<mpadded {}>", padding).unwrap();
+            }
+            out.push_str("<mphantom>");
+            write_nodes(out, &phantom.inner);
+            out.push_str("</mphantom>");
+            if padding.is_some() {
+                out.push_str("</mpadded>");
+            }
+        },
+        ParseNode::Smash(smash) => {
+            out.push_str("<mpadded");
+            if smash.top {
+                out.push_str(r#" height="0""#);
+            }
+            if smash.bottom {
+                out.push_str(r#" depth="0""#);
+            }
+            out.push('>');
+            write_nodes(out, &smash.inner);
+            out.push_str("</mpadded>");
+        },
+        ParseNode::Line(line) => {
+            let notation = match line.placement {
+                LinePlacement::Over  => "top",
+                LinePlacement::Under => "bottom",
+            };
+            write_enclosed(out, notation, &line.inner);
+        },
+        ParseNode::Cancel(cancel) => {
+            let notation = match cancel.direction {
+                CancelDirection::Forward  => "updiagonalstrike",
+                CancelDirection::Backward => "downdiagonalstrike",
+                CancelDirection::Cross    => "updiagonalstrike downdiagonalstrike",
+            };
+            write_enclosed(out, notation, &cancel.inner);
+        },
+        ParseNode::Boxed(boxed) => write_enclosed(out, "box", &boxed.inner),
+        ParseNode::ExtensibleArrow(arrow) => {
+            let tag = match (arrow.under.is_empty(), arrow.over.is_empty()) {
+                (true,  true)  => None,
+                (true,  false) => Some("mover"),
+                (false, true)  => Some("munder"),
+                (false, false) => Some("munderover"),
+            };
+            if let Some(tag) = tag {
+                write!(out, "<{}>", tag).unwrap();
+            }
+            out.push_str(r#"<mo stretchy="true">"#);
+            push_escaped(out, arrow.symbol.codepoint);
+            out.push_str("</mo>");
+            if !arrow.under.is_empty() {
+                write_row(out, &arrow.under);
+            }
+            if !arrow.over.is_empty() {
+                write_row(out, &arrow.over);
+            }
+            if let Some(tag) = tag {
+                write!(out, "</{}>", tag).unwrap();
+            }
+        },
+        ParseNode::Group(inner) => {
+            out.push_str("<mrow>");
+            write_nodes(out, inner);
+            out.push_str("</mrow>");
+        },
+        ParseNode::Stack(stack) => {
+            out.push_str("<mtable>");
+            for line in &stack.lines {
+                out.push_str("<mtr><mtd>");
+                write_nodes(out, line);
+                out.push_str("</mtd></mtr>");
+            }
+            out.push_str("</mtable>");
+        },
+        ParseNode::Array(array) => write_array(out, array),
+        // Outside of an array, a cell spanning several columns is just its content
+        ParseNode::MultiColumn(multicolumn) => write_row(out, &multicolumn.inner),
+        ParseNode::MakeBox(make_box) => match make_box.width {
+            Some(width) => {
+                write!(out, r#"<mpadded width="{}">"#, width).unwrap();
+                write_nodes(out, &make_box.inner);
+                out.push_str("</mpadded>");
+            },
+            None => write_row(out, &make_box.inner),
+        },
+        ParseNode::DummyNode(_) => (),
+    }
+}
+
+/// Chooses the token element of a symbol from its atom type: numbers are `<mn>`, letters and other ordinary symbols are `<mi>`,
+/// and everything else (relations, binary operators, delimiters, large operators, etc.) is `<mo>`.
+fn write_symbol(out: &mut String, codepoint: char, atom_type: TexSymbolType) {
+    match atom_type {
+        TexSymbolType::Alpha | TexSymbolType::Ordinary | TexSymbolType::Transparent if codepoint.is_numeric() => {
+            out.push_str("<mn>");
+        },
+        // A single letter in `<mi>` is rendered in italics: letters that the parser left upright (e.g. in `\mathrm{..}`) must be marked as such.
+        // Italic letters have already been replaced by Unicode's mathematical alphanumeric symbols.
+        TexSymbolType::Alpha | TexSymbolType::Ordinary | TexSymbolType::Transparent if codepoint.is_alphabetic() && !is_math_alphanumeric(codepoint) => {
+            out.push_str(r#"<mi mathvariant="normal">"#);
+        },
+        TexSymbolType::Alpha | TexSymbolType::Ordinary | TexSymbolType::Transparent => {
+            out.push_str("<mi>");
+        },
+        // Delimiters outside of `\left..\right` do not grow with their content
+        TexSymbolType::Open | TexSymbolType::Close | TexSymbolType::Fence => {
+            out.push_str(r#"<mo stretchy="false">"#);
+        },
+        _ => {
+            out.push_str("<mo>");
+        },
+    }
+    push_escaped(out, codepoint);
+    match atom_type {
+        TexSymbolType::Alpha | TexSymbolType::Ordinary | TexSymbolType::Transparent if codepoint.is_numeric() => out.push_str("</mn>"),
+        TexSymbolType::Alpha | TexSymbolType::Ordinary | TexSymbolType::Transparent => out.push_str("</mi>"),
+        _ => out.push_str("</mo>"),
+    }
+}
+
+/// Writes a delimiter, which grows with the content it encloses if `stretchy` (e.g. in `\left..\right`) or has the given fixed size (e.g. `\big(`).
+/// Null delimiters (e.g. `\left.`) are omitted.
+fn write_delimiter(out: &mut String, symbol: Symbol, stretchy: bool, size: Option<DelimiterSize>) {
+    if symbol.codepoint == Symbol::NULL_DELIMITER {
+        return;
+    }
+    write!(out, r#"<mo fence="true" stretchy="{}""#, stretchy).unwrap();
+    if let Some(size) = size {
+        // sizes of delimiters in TeX's Computer Modern fonts
+        let size = match size {
+            DelimiterSize::Big   => 1.2,
+            DelimiterSize::BBig  => 1.8,
+            DelimiterSize::Bigg  => 2.4,
+            DelimiterSize::BBigg => 3.0,
+        };
+        write!(out, r#" minsize="{0}em" maxsize="{0}em""#, size).unwrap();
+    }
+    out.push('>');
+    push_escaped(out, symbol.codepoint);
+    out.push_str("</mo>");
+}
+
+fn write_fraction(out: &mut String, fraction: &nodes::GenFraction) {
+    let delimited = fraction.left_delimiter.is_some() || fraction.right_delimiter.is_some();
+    let stretchy = fraction.delimiter_size.is_none();
+    if delimited {
+        out.push_str("<mrow>");
+        if let Some(left) = fraction.left_delimiter {
+            write_delimiter(out, left, stretchy, fraction.delimiter_size);
+        }
+    }
+
+    let styled = open_math_style(out, fraction.style);
+    out.push_str("<mfrac");
+    match fraction.bar_thickness {
+        BarThickness::Default    => (),
+        BarThickness::None       => out.push_str(r#" linethickness="0""#),
+        BarThickness::Unit(unit) => write!(out, r#" linethickness="{}""#, unit).unwrap(),
+    }
+    match fraction.numerator_alignment {
+        ArrayColumnAlign::Centered => (),
+        ArrayColumnAlign::Left     => out.push_str(r#" numalign="left""#),
+        ArrayColumnAlign::Right    => out.push_str(r#" numalign="right""#),
+    }
+    out.push('>');
+    write_row_in_style(out, &fraction.numerator,   fraction.numerator_style);
+    write_row_in_style(out, &fraction.denominator, fraction.denominator_style);
+    out.push_str("</mfrac>");
+    if styled {
+        out.push_str("</mstyle>");
+    }
+
+    if delimited {
+        if let Some(right) = fraction.right_delimiter {
+            write_delimiter(out, right, stretchy, fraction.delimiter_size);
+        }
+        out.push_str("</mrow>");
+    }
+}
+
+/// Writes scripts attached to an operator taking limits (e.g. `\sum`, `\overset{..}{..}`) above and below the base, and other scripts on its right.
+fn write_scripts(out: &mut String, scripts: &nodes::Scripts) {
+    let limits = scripts.base.as_ref().is_some_and(|base| base.atom_type() == TexSymbolType::Operator(true));
+    let tag = match (scripts.subscript.is_some(), scripts.superscript.is_some(), limits) {
+        (false, false, _)   => None,
+        (true,  false, false) => Some("msub"),
+        (false, true,  false) => Some("msup"),
+        (true,  true,  false) => Some("msubsup"),
+        (true,  false, true)  => Some("munder"),
+        (false, true,  true)  => Some("mover"),
+        (true,  true,  true)  => Some("munderover"),
+    };
+
+    if let Some(tag) = tag {
+        write!(out, "<{}>", tag).unwrap();
+    }
+    match &scripts.base {
+        Some(base) => write_row(out, std::slice::from_ref(&**base)),
+        None       => out.push_str("<mrow></mrow>"),
+    }
+    if let Some(subscript) = &scripts.subscript {
+        write_row(out, subscript);
+    }
+    if let Some(superscript) = &scripts.superscript {
+        write_row(out, superscript);
+    }
+    if let Some(tag) = tag {
+        write!(out, "</{}>", tag).unwrap();
+    }
+}
+
+fn write_atom_change(out: &mut String, change: &nodes::AtomChange) {
+    let symbols : Option<Vec<_>> = change.inner.iter().map(ParseNode::is_symbol).collect();
+    match (change.at, symbols.as_deref()) {
+        // Operator names made of several letters, e.g. `\sin` or `\lim`
+        (TexSymbolType::Operator(limits), Some(symbols)) if symbols.len() > 1 => {
+            out.push_str(if limits { r#"<mo movablelimits="true">"# } else { "<mi>" });
+            for symbol in symbols {
+                push_escaped(out, symbol.codepoint);
+            }
+            out.push_str(if limits { "</mo>" } else { "</mi>" });
+        },
+        // e.g. `\mathrel{x}` ; NB: the base of `\overset{..}{..}` is marked as an operator with limits, which says nothing of the symbol itself
+        (at, Some([symbol])) if !matches!(at, TexSymbolType::Operator(_)) => write_symbol(out, symbol.codepoint, at),
+        _ => write_row(out, &change.inner),
+    }
+}
+
+fn write_array(out: &mut String, array: &nodes::Array) {
+    let delimited = array.left_delimiter.is_some() || array.right_delimiter.is_some();
+    if delimited {
+        out.push_str("<mrow>");
+        if let Some(left) = array.left_delimiter {
+            write_delimiter(out, left, true, None);
+        }
+    }
+
+    out.push_str("<mtable");
+    if matches!(array.cell_layout_style, Style::Display | Style::DisplayCramped) {
+        out.push_str(r#" displaystyle="true""#);
+    }
+    // `\begin{smallmatrix} .. \end{smallmatrix}`
+    if matches!(array.cell_layout_style, Style::Script | Style::ScriptCramped) {
+        out.push_str(r#" scriptlevel="1""#);
+    }
+    if array.tight_spacing {
+        out.push_str(r#" columnspacing="0.2778em" rowspacing="0.1em""#);
+    }
+    let alignments = &array.col_format.alignment;
+    if !alignments.is_empty() {
+        let alignments : Vec<_> = alignments.iter().map(|alignment| column_align(*alignment)).collect();
+        write!(out, r#" columnalign="{}""#, alignments.join(" ")).unwrap();
+    }
+    // `p{..}` columns
+    let widths = &array.col_format.widths;
+    if widths.iter().any(Option::is_some) {
+        let widths : Vec<_> = widths.iter().map(|width| width.map_or_else(|| "auto".to_string(), |width| width.to_string())).collect();
+        write!(out, r#" columnwidth="{}""#, widths.join(" ")).unwrap();
+    }
+    // Vertical bars between columns ; bars on the outer sides of the array and `@{..}` expressions are left out
+    let inner_separators = array.col_format.separators.get(1 .. alignments.len()).unwrap_or_default();
+    let has_bar = |separators: &Vec<ColSeparator>| separators.iter().any(ColSeparator::is_vert_bars);
+    if inner_separators.iter().any(has_bar) {
+        let lines : Vec<_> = inner_separators.iter()
+            .map(|separators| if has_bar(separators) { "solid" } else { "none" })
+            .collect();
+        write!(out, r#" columnlines="{}""#, lines.join(" ")).unwrap();
+    }
+    out.push('>');
+
+    for row in &array.rows {
+        out.push_str("<mtr>");
+        for cell in row {
+            match cell.as_slice() {
+                [ParseNode::MultiColumn(multicolumn)] => {
+                    write!(
+                        out, r#"<mtd columnspan="{}" columnalign="{}">"#,
+                        multicolumn.span, column_align(multicolumn.alignment),
+                    ).unwrap();
+                    write_nodes(out, &multicolumn.inner);
+                },
+                _ => {
+                    out.push_str("<mtd>");
+                    write_nodes(out, cell);
+                },
+            }
+            out.push_str("</mtd>");
+        }
+        out.push_str("</mtr>");
+    }
+    out.push_str("</mtable>");
+
+    if delimited {
+        if let Some(right) = array.right_delimiter {
+            write_delimiter(out, right, true, None);
+        }
+        out.push_str("</mrow>");
+    }
+}
+
+fn write_enclosed(out: &mut String, notation: &str, nodes: &[ParseNode]) {
+    write!(out, r#"<menclose notation="{}">"#, notation).unwrap();
+    write_nodes(out, nodes);
+    out.push_str("</menclose>");
+}
+
+/// Opens a `<mstyle>` element for a change of style.
+fn open_style(out: &mut String, style: Style) {
+    let (display, level) = match style {
+        Style::Display      | Style::DisplayCramped      => (true,  0),
+        Style::Text         | Style::TextCramped         => (false, 0),
+        Style::Script       | Style::ScriptCramped       => (false, 1),
+        Style::ScriptScript | Style::ScriptScriptCramped => (false, 2),
+    };
+    write!(out, r#"<mstyle displaystyle="{}" scriptlevel="{}">"#, display, level).unwrap();
+}
+
+/// Opens a `<mstyle>` element for the style of a fraction or of its numerator or denominator, unless it is [`MathStyle::NoChange`] ; returns whether an element was opened.
+fn open_math_style(out: &mut String, style: MathStyle) -> bool {
+    match style {
+        MathStyle::Display  => open_style(out, Style::Display),
+        MathStyle::Text     => open_style(out, Style::Text),
+        MathStyle::NoChange => return false,
+    }
+    true
+}
+
+fn column_align(alignment: ArrayColumnAlign) -> &'static str {
+    match alignment {
+        ArrayColumnAlign::Centered => "center",
+        ArrayColumnAlign::Left     => "left",
+        ArrayColumnAlign::Right    => "right",
+    }
+}
+
+/// Number of nodes at the start of `nodes` that make up a number, e.g. `3.14`
+fn number_length(nodes: &[ParseNode]) -> usize {
+    let is_digit = |node: Option<&ParseNode>| node
+        .and_then(ParseNode::is_symbol)
+        .is_some_and(|symbol| symbol.codepoint.is_numeric());
+
+    let mut length = 0;
+    while is_digit(nodes.get(length))
+       || (nodes.get(length).and_then(ParseNode::is_symbol).map(|symbol| symbol.codepoint) == Some('.') && is_digit(nodes.get(length + 1))) {
+        length += 1;
+    }
+    length
+}
+
+/// Whether the character is in Unicode's "Mathematical Alphanumeric Symbols" block (e.g. '𝑥', '𝐀', '𝔸')
+fn is_math_alphanumeric(character: char) -> bool {
+    ('\u{1D400}' ..= '\u{1D7FF}').contains(&character)
+}
+
+/// MathML renderers expect accents as spacing characters rather than as combining characters.
+fn spacing_accent(accent: char) -> char {
+    match accent {
+        '\u{300}' => '`',
+        '\u{301}' => '\u{B4}',
+        '\u{302}' => '\u{2C6}',
+        '\u{303}' => '\u{2DC}',
+        '\u{304}' => '\u{AF}',
+        '\u{305}' => '\u{203E}',
+        '\u{306}' => '\u{2D8}',
+        '\u{307}' => '\u{2D9}',
+        '\u{308}' => '\u{A8}',
+        '\u{30A}' => '\u{2DA}',
+        '\u{30C}' => '\u{2C7}',
+        '\u{20D7}' => '\u{2192}',
+        _ => accent,
+    }
+}
+
+fn push_escaped(out: &mut String, character: char) {
+    match character {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        _   => out.push(character),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::MathMlWriter;
+    use crate::parser::parse;
+
+    fn mathml(formula: &str) -> String {
+        let nodes = parse(formula).unwrap();
+        let output = MathMlWriter::new().write(&nodes);
+        output
+            .strip_prefix(r#"<math xmlns="http://www.w3.org/1998/Math/MathML">"#).unwrap()
+            .strip_suffix("</math>").unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn symbols() {
+        assert_eq!(mathml(r"x + 12 = \alpha"), "<mi>𝑥</mi><mo>+</mo><mn>12</mn><mo>=</mo><mi>𝛼</mi>");
+        assert_eq!(mathml(r"3.14"), "<mn>3.14</mn>");
+        assert_eq!(mathml(r"\mathrm{d}x"), r#"<mrow><mi mathvariant="normal">d</mi></mrow><mi>𝑥</mi>"#);
+        assert_eq!(mathml(r"(a < b)"), r#"<mo stretchy="false">(</mo><mi>𝑎</mi><mo>&lt;</mo><mi>𝑏</mi><mo stretchy="false">)</mo>"#);
+        assert_eq!(mathml(r"\sin x"), "<mi>sin</mi><mi>𝑥</mi>");
+        assert_eq!(mathml(r"\text{a<b}"), "<mtext>a&lt;b</mtext>");
+    }
+
+    #[test]
+    fn fractions_scripts_and_radicals() {
+        assert_eq!(mathml(r"\frac{1}{x}"), "<mfrac><mn>1</mn><mi>𝑥</mi></mfrac>");
+        assert_eq!(mathml(r"\binom{n}{k}"), r#"<mrow><mo fence="true" stretchy="true">(</mo><mfrac linethickness="0"><mi>𝑛</mi><mi>𝑘</mi></mfrac><mo fence="true" stretchy="true">)</mo></mrow>"#);
+        assert_eq!(mathml(r"x^2"), "<msup><mi>𝑥</mi><mn>2</mn></msup>");
+        assert_eq!(mathml(r"x_i"), "<msub><mi>𝑥</mi><mi>𝑖</mi></msub>");
+        assert_eq!(mathml(r"x_i^{n+1}"), "<msubsup><mi>𝑥</mi><mi>𝑖</mi><mrow><mi>𝑛</mi><mo>+</mo><mn>1</mn></mrow></msubsup>");
+        assert_eq!(mathml(r"\sum_0^n"), "<munderover><mo>∑</mo><mn>0</mn><mi>𝑛</mi></munderover>");
+        assert_eq!(mathml(r"\smallint_0^1"), r#"<msubsup><mo largeop="false">∫</mo><mn>0</mn><mn>1</mn></msubsup>"#);
+        assert_eq!(mathml(r"\sqrt{x}"), "<msqrt><mi>𝑥</mi></msqrt>");
+        assert_eq!(mathml(r"\sqrt[3]{x}"), "<mroot><mi>𝑥</mi><mn>3</mn></mroot>");
+    }
+
+    #[test]
+    fn arrays_and_delimiters() {
+        assert_eq!(
+            mathml(r"\begin{pmatrix}a & b \\ c & d\end{pmatrix}"),
+            concat!(
+                r#"<mrow><mo fence="true" stretchy="true">(</mo><mtable columnalign="center center">"#,
+                "<mtr><mtd><mi>𝑎</mi></mtd><mtd><mi>𝑏</mi></mtd></mtr>",
+                "<mtr><mtd><mi>𝑐</mi></mtd><mtd><mi>𝑑</mi></mtd></mtr>",
+                r#"</mtable><mo fence="true" stretchy="true">)</mo></mrow>"#,
+            ),
+        );
+        assert_eq!(
+            mathml(r"\begin{array}{c|l}1 & 2\end{array}"),
+            r#"<mtable columnalign="center left" columnlines="solid"><mtr><mtd><mn>1</mn></mtd><mtd><mn>2</mn></mtd></mtr></mtable>"#,
+        );
+        assert_eq!(
+            mathml(r"\left( x \right."),
+            r#"<mrow><mo fence="true" stretchy="true">(</mo><mi>𝑥</mi></mrow>"#,
+        );
+        assert_eq!(
+            mathml(r"\begin{smallmatrix}1\end{smallmatrix}"),
+            r#"<mtable scriptlevel="1" columnspacing="0.2778em" rowspacing="0.1em" columnalign="center"><mtr><mtd><mn>1</mn></mtd></mtr></mtable>"#,
+        );
+        assert_eq!(
+            mathml(r"\begin{array}{p{2em}c}1&2\end{array}"),
+            r#"<mtable columnalign="left center" columnwidth="2em auto"><mtr><mtd><mn>1</mn></mtd><mtd><mn>2</mn></mtd></mtr></mtable>"#,
+        );
+    }
+
+    #[test]
+    fn styles() {
+        assert_eq!(
+            mathml(r"a \scriptstyle b"),
+            r#"<mi>𝑎</mi><mstyle displaystyle="false" scriptlevel="1"><mi>𝑏</mi></mstyle>"#,
+        );
+        assert_eq!(
+            MathMlWriter { display: true }.write(&parse("x").unwrap()),
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML" display="block"><mi>𝑥</mi></math>"#,
+        );
+    }
+}
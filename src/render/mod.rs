@@ -9,6 +9,7 @@
 //!
 //!   - setting colors: `GraphicsBackend::begin_color` and `GraphicsBackend::end_color`
 //!   - drawing a filled rectangle: `GraphicsBackend::rule`
+//!   - drawing a straight line: `GraphicsBackend::line`
 //!   - drawing a glyph from a given font (`FontBackend::symbol`). 
 //!
 //! A number of common [`Backend`] have been implemented and can be activated using some features of the crates:
@@ -16,6 +17,11 @@
 //!  - Cairo backend :   `cairo-renderer` (render to screen, png or svg)
 //!  - FemtoVG backend : `femtovg-renderer` (render to screen using OpenGL)
 //!  - Raqote backend : `raqote-renderer` (render to screen, png)
+//!  - PDF backend : `pdf-renderer` (render to a vector PDF document)
+//!  - SVG backend : `svg-renderer` (render to an SVG document)
+//!  - tiny-skia backend : `tiny-skia-renderer` (render to png, without C dependencies)
+//!
+//! Formulas can also be exported to MathML, without a font nor a `Backend`, with [`mathml::MathMlWriter`].
 //! 
 //! ## Caveat on coordinate systems
 //! 
@@ -24,10 +30,11 @@
 
 
 use crate::error::Error;
+use crate::dimensions::Unit;
 use crate::dimensions::units::Px;
 use crate::font::MathFont;
 use crate::font::common::GlyphId;
-use crate::layout::{LayoutNode, LayoutVariant, Alignment, LayoutSettings, Layout, Grid};
+use crate::layout::{LayoutNode, LayoutVariant, Alignment, LayoutSettings, Layout, Grid, Diagonal};
 pub use crate::parser::color::RGBA;
 
 /// Context used for rendering.
@@ -35,6 +42,14 @@ pub struct Renderer {
     /// When set to true, the renderer additionally calls [`GraphicsBackend::bbox`] to draw boxes
     /// around every glyph, horizontal and vertical boxes of the layout.
     pub debug: bool,
+    /// When set to true, the position and thickness of rules (fraction bars, radicals, array bars, etc.)
+    /// are rounded to whole device pixels, so that thin rules are drawn crisply rather than blurred over two rows of pixels.
+    pub snap_rules_to_pixels: bool,
+    /// Number of device pixels per pixel of layout. Only used when [`Renderer::snap_rules_to_pixels`] is true.
+    pub device_scale: f64,
+    /// When set to true, the renderer additionally draws the baseline and the math axis (cf [`Layout::axis_height`]) across the whole formula,
+    /// as thin rules of thickness [`GUIDE_THICKNESS`]. This helps checking the vertical alignment of the parts of a formula.
+    pub draw_baseline_and_axis: bool,
 }
 
 /// Position of the cursor in space. The unit used in pixels.
@@ -108,6 +123,26 @@ pub trait GraphicsBackend {
     fn bbox(&mut self, _pos: Cursor, _width: f64, _height: f64, _role: Role) {}
     /// Draws a filled rectangle whose top-left corner is at `pos`. Used to draw fraction bars and radicals.
     fn rule(&mut self, pos: Cursor, width: f64, height: f64);
+    /// Draws a straight line of thickness `thickness` from `from` to `to`. Used to draw slanted lines, e.g. in `\cancel{..}`.
+    ///
+    /// The default implementation approximates the line with a staircase of small squares drawn with [`GraphicsBackend::rule`] ;
+    /// past [`MAX_LINE_STEPS`] squares, the steps are stretched into rectangles instead.
+    /// Backends which can stroke paths should override it.
+    fn line(&mut self, from: Cursor, to: Cursor, thickness: f64) {
+        if thickness <= 0. {
+            return;
+        }
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let steps = (dx.abs().max(dy.abs()) / thickness).ceil().clamp(1., MAX_LINE_STEPS as f64) as usize;
+        // each step reaches the next one, even if that is more than `thickness` away
+        let width  = thickness.max(dx.abs() / steps as f64);
+        let height = thickness.max(dy.abs() / steps as f64);
+        for i in 0 ..= steps {
+            let t = i as f64 / steps as f64;
+            let center = from.translate(t * dx, t * dy);
+            self.rule(center.translate(- width / 2., - height / 2.), width, height);
+        }
+    }
     /// Makes `color` the current used color. The color previously in use is restored with [`GraphicsBackend::end_color`].
     fn begin_color(&mut self, color: RGBA);
     /// Restores the previously used color. If there were no previous color, this function should return silently and not panic.
@@ -135,6 +170,9 @@ impl Renderer {
     pub fn new() -> Self {
         Renderer {
             debug: false,
+            snap_rules_to_pixels: false,
+            device_scale: 1.0,
+            draw_baseline_and_axis: false,
         }
     }
 
@@ -154,6 +192,76 @@ impl Renderer {
             y: 0.0,
         };
         self.render_hbox(out, pos, &layout.contents, layout.height.unitless(Px), layout.width.unitless(Px), Alignment::Default);
+
+        if self.draw_baseline_and_axis {
+            let width = layout.width.unitless(Px);
+            for y in [0., -layout.axis_height.unitless(Px)] {
+                out.rule(pos.translate(0., y - GUIDE_THICKNESS / 2.), width, GUIDE_THICKNESS);
+            }
+        }
+    }
+
+    /// Renders the given layout onto `out`, breaking it into several lines so that it fits, if possible, within `max_width`, cf [`Layout::wrapped`].
+    /// This is experimental: formulas are only broken before their top-level relations (e.g. `=`).
+    pub fn render_wrapped<F>(&self, layout: &Layout<F>, out: &mut impl Backend<F>, max_width: f64) {
+        self.render(&layout.wrapped(Unit::new(max_width)), out);
+    }
+
+    /// Renders the given layout onto `out`, grouping together all the content drawn in the same color: 
+    /// [`GraphicsBackend::begin_color`] and [`GraphicsBackend::end_color`] are called once per color, around all the content of that color.
+    /// This suits outputs organized by color, e.g. SVG files with one layer per color.
+    ///
+    /// The content in the default color is drawn first, without any call to [`GraphicsBackend::begin_color`], 
+    /// followed by the content of each color, in the order in which the colors first appear in the layout.
+    /// NB: as a result, overlapping content of different colors may be stacked differently than with [`Renderer::render`].
+    pub fn render_grouped_by_color<F>(&self, layout: &Layout<F>, out: &mut impl Backend<F>) {
+        // The layout is rendered once per color, each time forwarding only the content of that color to `out`.
+        let mut filter = ColorFilter { out, target: None, stack: Vec::new(), colors: Vec::new() };
+        self.render(layout, &mut filter);
+
+        let colors = std::mem::take(&mut filter.colors);
+        for color in colors.into_iter().flatten() {
+            filter.out.begin_color(color);
+            filter.target = Some(color);
+            self.render(layout, &mut filter);
+            filter.out.end_color();
+        }
+    }
+
+    /// Draws a caret, i.e. a thin vertical rule of width [`CARET_WIDTH`], on the left edge (if `before` is true) or on the right edge of a glyph of the layout.
+    /// Glyphs are indexed in the order in which [`Renderer::render`] draws them, from 0 to [`Layout::glyph_count`] (excluded).
+    /// The caret spans the whole height and depth of the layout. 
+    /// Returns false, and draws nothing, if there is no glyph with index `glyph_index` in the layout.
+    pub fn render_caret<F>(&self, layout: &Layout<F>, out: &mut impl GraphicsBackend, glyph_index: usize, before: bool) -> bool {
+        // Glyph boxes are found by rendering the layout in debug mode, which reports the bounding box of every glyph
+        let mut locator = GlyphLocator { target: glyph_index, seen: 0, found: None };
+        let debug_renderer = Renderer { debug: true, ..Renderer::new() };
+        debug_renderer.render(layout, &mut locator);
+
+        let (pos, width) = match locator.found {
+            Some(glyph_box) => glyph_box,
+            None => return false,
+        };
+        let x = if before { pos.x } else { pos.x + width };
+        let top = Cursor { x: x - CARET_WIDTH / 2., y: -layout.height.unitless(Px) };
+        self.rule(out, top, CARET_WIDTH, (layout.height - layout.depth).unitless(Px));
+        true
+    }
+
+    /// Renders the given layout onto `out`, like [`Renderer::render`], and returns the bounding box of every glyph drawn, for hit-testing.
+    /// Each entry consists of the top-left corner of the glyph's box, its width, its total height (height plus depth) and the glyph's id.
+    /// Entries follow the drawing order, so that the entry at index `i` is the glyph of index `i` for [`Renderer::render_caret`].
+    pub fn render_with_hit_map<F>(&self, layout: &Layout<F>, out: &mut impl Backend<F>) -> Vec<(Cursor, f64, f64, GlyphId)> {
+        // Glyph boxes are found by rendering the layout in debug mode, which reports the bounding box of every glyph right before drawing it
+        let mut recorder = HitMapRecorder { out, forward_bboxes: self.debug, pending: None, hit_map: Vec::new() };
+        let debug_renderer = Renderer {
+            debug: true,
+            snap_rules_to_pixels: self.snap_rules_to_pixels,
+            device_scale: self.device_scale,
+            draw_baseline_and_axis: self.draw_baseline_and_axis,
+        };
+        debug_renderer.render(layout, &mut recorder);
+        recorder.hit_map
     }
 
     fn render_grid<F>(&self, out: &mut impl Backend<F>, pos: Cursor, _width: f64, _height: f64, grid: &Grid<F>) {
@@ -191,7 +299,7 @@ impl Renderer {
     fn render_vbox<F>(&self, out: &mut impl Backend<F>, mut pos: Cursor, nodes: &[LayoutNode<F>]) {
         for node in nodes {
             match node.node {
-                LayoutVariant::Rule => out.rule(pos, node.width.unitless(Px), node.height.unitless(Px)),
+                LayoutVariant::Rule => self.rule(out, pos, node.width.unitless(Px), node.height.unitless(Px)),
                 LayoutVariant::Grid(ref grid) => self.render_grid(out, pos, node.height.unitless(Px), node.width.unitless(Px), grid),
                 LayoutVariant::HorizontalBox(ref hbox) => {
                     self.render_hbox(out,
@@ -216,6 +324,8 @@ impl Renderer {
                     out.symbol(pos.down(node.height.unitless(Px)), gly.gid, gly.size.unitless(Px), gly.font);
                 }
 
+                LayoutVariant::Diagonal(diagonal) => self.diagonal(out, pos.down(node.height.unitless(Px)), node, diagonal),
+
                 LayoutVariant::Color(_) => panic!("Shouldn't have a color in a vertical box???"),
 
                 LayoutVariant::Kern => { /* NOOP */ }
@@ -234,7 +344,9 @@ impl Renderer {
                 out.symbol(pos, gly.gid, gly.size.unitless(Px), gly.font);
             }
 
-            LayoutVariant::Rule => out.rule(pos.up(node.height.unitless(Px)), node.width.unitless(Px), node.height.unitless(Px)),
+            LayoutVariant::Rule => self.rule(out, pos.up(node.height.unitless(Px)), node.width.unitless(Px), node.height.unitless(Px)),
+
+            LayoutVariant::Diagonal(diagonal) => self.diagonal(out, pos, node, diagonal),
 
             LayoutVariant::VerticalBox(ref vbox) => {
                 if self.debug {
@@ -258,6 +370,386 @@ impl Renderer {
         } // End macth
 
     }
+
+    /// Draws the diagonal of the node whose baseline starts at `pos`
+    fn diagonal<F>(&self, out: &mut impl GraphicsBackend, pos: Cursor, node: &LayoutNode<F>, diagonal: Diagonal) {
+        let top    = pos.up(node.height.unitless(Px));
+        let bottom = pos.up(node.depth.unitless(Px));
+        let width  = node.width.unitless(Px);
+        let thickness = diagonal.thickness.unitless(Px);
+        if diagonal.ascending {
+            out.line(bottom, top.right(width), thickness);
+        }
+        else {
+            out.line(top, bottom.right(width), thickness);
+        }
+    }
+
+    fn rule(&self, out: &mut impl GraphicsBackend, pos: Cursor, width: f64, height: f64) {
+        if !self.snap_rules_to_pixels {
+            return out.rule(pos, width, height);
+        }
+
+        // Round in device pixels, making sure that a thin rule doesn't vanish entirely.
+        let scale = self.device_scale;
+        let snap   = |x: f64| (x * scale).round() / scale;
+        let snap_thickness = |x: f64| if x > 0. { f64::max((x * scale).round(), 1.) / scale } else { x };
+
+        let pos = Cursor { x: snap(pos.x), y: snap(pos.y) };
+        out.rule(pos, snap_thickness(width), snap_thickness(height));
+    }
+}
+
+/// Width of the caret drawn by [`Renderer::render_caret`], in pixels
+pub const CARET_WIDTH : f64 = 1.;
+
+/// Thickness of the baseline and axis drawn when [`Renderer::draw_baseline_and_axis`] is set, in pixels
+pub const GUIDE_THICKNESS : f64 = 0.5;
+
+/// Largest number of steps of the staircase drawn by the default implementation of [`GraphicsBackend::line`]
+pub const MAX_LINE_STEPS : usize = 128;
+
+/// Records the bounding box of the n-th glyph drawn, cf [`Renderer::render_caret`]
+struct GlyphLocator {
+    target: usize,
+    seen:   usize,
+    found:  Option<(Cursor, f64)>,
+}
+
+impl GraphicsBackend for GlyphLocator {
+    fn bbox(&mut self, pos: Cursor, width: f64, _height: f64, role: Role) {
+        if let Role::Glyph = role {
+            if self.seen == self.target {
+                self.found = Some((pos, width));
+            }
+            self.seen += 1;
+        }
+    }
+    fn rule(&mut self, _pos: Cursor, _width: f64, _height: f64) {}
+    fn begin_color(&mut self, _color: RGBA) {}
+    fn end_color(&mut self) {}
+}
+
+impl<F> FontBackend<F> for GlyphLocator {
+    fn symbol(&mut self, _pos: Cursor, _gid: GlyphId, _scale: f64, _ctx: &F) {}
+}
+
+impl<F> Backend<F> for GlyphLocator {}
+
+/// Forwards draw operations to `out` and records the bounding box of every glyph drawn, cf [`Renderer::render_with_hit_map`]
+struct HitMapRecorder<'b, B> {
+    out:            &'b mut B,
+    /// whether debug rectangles are forwarded to `out`
+    forward_bboxes: bool,
+    /// box of the glyph about to be drawn
+    pending:        Option<(Cursor, f64, f64)>,
+    hit_map:        Vec<(Cursor, f64, f64, GlyphId)>,
+}
+
+impl<'b, B : GraphicsBackend> GraphicsBackend for HitMapRecorder<'b, B> {
+    fn bbox(&mut self, pos: Cursor, width: f64, height: f64, role: Role) {
+        if let Role::Glyph = role {
+            self.pending = Some((pos, width, height));
+        }
+        if self.forward_bboxes {
+            self.out.bbox(pos, width, height, role);
+        }
+    }
+    fn rule(&mut self, pos: Cursor, width: f64, height: f64) {
+        self.out.rule(pos, width, height);
+    }
+    fn line(&mut self, from: Cursor, to: Cursor, thickness: f64) {
+        self.out.line(from, to, thickness);
+    }
+    fn begin_color(&mut self, color: RGBA) {
+        self.out.begin_color(color);
+    }
+    fn end_color(&mut self) {
+        self.out.end_color();
+    }
+}
+
+impl<'b, F, B : FontBackend<F>> FontBackend<F> for HitMapRecorder<'b, B> {
+    fn symbol(&mut self, pos: Cursor, gid: GlyphId, scale: f64, ctx: &F) {
+        if let Some((pos, width, height)) = self.pending.take() {
+            self.hit_map.push((pos, width, height, gid));
+        }
+        self.out.symbol(pos, gid, scale, ctx);
+    }
+}
+
+impl<'b, F, B : Backend<F>> Backend<F> for HitMapRecorder<'b, B> {}
+
+/// Forwards to `out` the draw operations performed while the current color is `target` (`None` standing for the default color),
+/// and records the colors of all draw operations, cf [`Renderer::render_grouped_by_color`]
+struct ColorFilter<'b, B> {
+    out:    &'b mut B,
+    target: Option<RGBA>,
+    stack:  Vec<RGBA>,
+    colors: Vec<Option<RGBA>>,
+}
+
+impl<'b, B> ColorFilter<'b, B> {
+    /// Returns whether a draw operation should be forwarded in the current color
+    fn active(&mut self) -> bool {
+        let current = self.stack.last().copied();
+        if !self.colors.contains(&current) {
+            self.colors.push(current);
+        }
+        current == self.target
+    }
+}
+
+impl<'b, B : GraphicsBackend> GraphicsBackend for ColorFilter<'b, B> {
+    fn bbox(&mut self, pos: Cursor, width: f64, height: f64, role: Role) {
+        if self.active() {
+            self.out.bbox(pos, width, height, role);
+        }
+    }
+    fn rule(&mut self, pos: Cursor, width: f64, height: f64) {
+        if self.active() {
+            self.out.rule(pos, width, height);
+        }
+    }
+    fn line(&mut self, from: Cursor, to: Cursor, thickness: f64) {
+        if self.active() {
+            self.out.line(from, to, thickness);
+        }
+    }
+    fn begin_color(&mut self, color: RGBA) {
+        self.stack.push(color);
+    }
+    fn end_color(&mut self) {
+        self.stack.pop();
+    }
+}
+
+impl<'b, F, B : FontBackend<F>> FontBackend<F> for ColorFilter<'b, B> {
+    fn symbol(&mut self, pos: Cursor, gid: GlyphId, scale: f64, ctx: &F) {
+        if self.active() {
+            self.out.symbol(pos, gid, scale, ctx);
+        }
+    }
+}
+
+impl<'b, F, B : Backend<F>> Backend<F> for ColorFilter<'b, B> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::recorder::{Recorder, DefaultLines};
+    use crate::dimensions::Unit;
+    use crate::layout::LayoutGlyph;
+
+    /// A glyph of the unit font `()`, `width` wide, 8 high and 2 deep
+    fn glyph(gid : u16, width : f64) -> LayoutNode<'static, ()> {
+        LayoutNode {
+            node: LayoutVariant::Glyph(LayoutGlyph {
+                gid:        GlyphId::from(gid),
+                size:       Unit::new(10.),
+                offset:     Unit::ZERO,
+                attachment: Unit::ZERO,
+                italics:    Unit::ZERO,
+                font:       &(),
+            }),
+            width:  Unit::new(width),
+            height: Unit::new(8.),
+            depth:  Unit::new(-2.),
+        }
+    }
+
+    #[test]
+    fn snap_rules_to_pixels() {
+        let node : LayoutNode<()> = LayoutNode {
+            node:   LayoutVariant::Rule,
+            width:  Unit::new(10.3),
+            height: Unit::new(1.),
+            depth:  Unit::ZERO,
+        };
+        // the rule's top edge lies at y = 0.4
+        let origin = Cursor { x: 0.2, y: 1.4 };
+
+        let mut renderer = Renderer::new();
+        let mut backend = Recorder::default();
+        renderer.render_node(&mut backend, origin, &node);
+        let (pos, _, _) = backend.rules[0];
+        assert_close!(pos.y, 0.4, 1e-9);
+
+        renderer.snap_rules_to_pixels = true;
+        let mut backend = Recorder::default();
+        renderer.render_node(&mut backend, origin, &node);
+        let (pos, width, height) = backend.rules[0];
+        assert_eq!(pos.y, pos.y.round());
+        assert_eq!(pos.x, pos.x.round());
+        assert_eq!(width, 10.);
+        assert_eq!(height, 1.);
+
+        renderer.device_scale = 2.;
+        let mut backend = Recorder::default();
+        renderer.render_node(&mut backend, origin, &node);
+        let (pos, _, _) = backend.rules[0];
+        assert_eq!(pos.y, 0.5);
+    }
+
+    #[test]
+    fn caret_at_glyph_edges() {
+        let mut layout = Layout::new();
+        layout.add_node(glyph(0, 5.));
+        layout.add_node(glyph(0, 7.));
+
+        let renderer = Renderer::new();
+        let caret_center = |glyph_index, before| {
+            let mut backend = Recorder::default();
+            assert!(renderer.render_caret(&layout, &mut backend, glyph_index, before));
+            let (pos, width, height) = backend.rules[0];
+            assert_close!(pos.y, -8., 1e-9);
+            assert_close!(height, 10., 1e-9);
+            pos.x + width / 2.
+        };
+
+        assert_close!(caret_center(0, true),  0.,  1e-9);
+        assert_close!(caret_center(0, false), 5.,  1e-9);
+        assert_close!(caret_center(1, true),  5.,  1e-9);
+        assert_close!(caret_center(1, false), 12., 1e-9);
+
+        let mut backend = Recorder::default();
+        assert!(!renderer.render_caret(&layout, &mut backend, 2, true));
+        assert!(backend.rules.is_empty());
+    }
+
+    #[test]
+    fn hit_map_follows_drawing_order() {
+        use crate::layout::VerticalBox;
+
+        // a glyph followed by two glyphs stacked on top of each other
+        let stack = LayoutNode {
+            node: LayoutVariant::VerticalBox(VerticalBox {
+                contents:  vec![glyph(1, 4.), glyph(2, 6.)],
+                offset:    Unit::ZERO,
+                alignment: Alignment::Default,
+            }),
+            width:  Unit::new(6.),
+            height: Unit::new(8.),
+            depth:  Unit::new(-12.),
+        };
+        let mut layout = Layout::new();
+        layout.add_node(glyph(0, 5.));
+        layout.add_node(stack);
+
+        let mut backend = Recorder::default();
+        let hit_map = Renderer::new().render_with_hit_map(&layout, &mut backend);
+        let gids : Vec<u16> = hit_map.iter().map(|&(_, _, _, gid)| gid.into()).collect();
+        assert_eq!(gids, vec![0, 1, 2]);
+
+        let (pos, width, height, _) = hit_map[0];
+        assert_eq!((pos, width, height), (Cursor { x: 0., y: -8. }, 5., 10.));
+        // the glyphs of the stack are drawn one below the other, right of the first glyph
+        let (top, bottom) = (hit_map[1].0, hit_map[2].0);
+        assert_eq!((top.x, bottom.x), (5., 5.));
+        assert_close!(bottom.y - top.y, 8., 1e-9);
+        assert_eq!((hit_map[1].1, hit_map[2].1), (4., 6.));
+
+        // debug rectangles are not drawn, unless the renderer is in debug mode
+        let mut backend = Recorder::default();
+        Renderer::new().render_with_hit_map(&layout, &mut backend);
+        assert_eq!(backend.bboxes, 0);
+        let mut backend = Recorder::default();
+        Renderer { debug: true, ..Renderer::new() }.render_with_hit_map(&layout, &mut backend);
+        assert!(backend.bboxes > 3);
+    }
+
+    #[test]
+    fn draw_baseline_and_axis() {
+        let mut layout : Layout<()> = Layout::new();
+        layout.add_node(LayoutNode {
+            node:   LayoutVariant::Kern,
+            width:  Unit::new(20.),
+            height: Unit::new(8.),
+            depth:  Unit::new(-2.),
+        });
+        layout.axis_height = Unit::new(3.);
+
+        let mut renderer = Renderer::new();
+        let mut backend = Recorder::default();
+        renderer.render(&layout, &mut backend);
+        assert!(backend.rules.is_empty());
+
+        renderer.draw_baseline_and_axis = true;
+        renderer.render(&layout, &mut backend);
+        assert_eq!(backend.rules.len(), 2);
+        let centers : Vec<_> = backend.rules.iter().map(|(pos, _, height)| pos.y + height / 2.).collect();
+        for &(pos, width, height) in backend.rules.iter() {
+            assert_eq!(pos.x, 0.);
+            assert_eq!(width, 20.);
+            assert_eq!(height, GUIDE_THICKNESS);
+        }
+        assert_close!(centers[0],  0., 1e-9);
+        assert_close!(centers[1], -3., 1e-9);
+    }
+
+    #[test]
+    fn default_line_follows_segment() {
+        let mut backend = DefaultLines::default();
+        let (from, to) = (Cursor { x: 0., y: 10. }, Cursor { x: 10., y: 0. });
+        backend.line(from, to, 1.);
+        let backend = backend.0;
+
+        assert!(backend.rules.len() > 1);
+        for &(pos, width, height) in backend.rules.iter() {
+            assert_eq!((width, height), (1., 1.));
+            // every square is centered on the segment
+            let center = pos.translate(0.5, 0.5);
+            assert_close!(center.x + center.y, 10., 1e-9);
+        }
+        let first = backend.rules.first().unwrap().0.translate(0.5, 0.5);
+        let last  = backend.rules.last().unwrap().0.translate(0.5, 0.5);
+        assert_eq!((first, last), (from, to));
+    }
+
+    #[test]
+    fn default_line_has_a_bounded_number_of_steps() {
+        let mut backend = DefaultLines::default();
+        let (from, to) = (Cursor { x: 0., y: 0. }, Cursor { x: 1e6, y: 5e5 });
+        backend.line(from, to, 0.5);
+        let backend = backend.0;
+
+        assert_eq!(backend.rules.len(), MAX_LINE_STEPS + 1);
+        // the steps still join up from one end of the line to the other
+        for (&(pos, width, height), &(next, _, _)) in backend.rules.iter().zip(backend.rules.iter().skip(1)) {
+            assert_close!(next.x, pos.x + width,  1e-6);
+            assert_close!(next.y, pos.y + height, 1e-6);
+        }
+        let (first, width, height) = backend.rules[0];
+        assert_eq!(first.translate(width / 2., height / 2.), from);
+        let (last, width, height) = *backend.rules.last().unwrap();
+        assert_close!(last.x + width / 2.,  to.x, 1e-6);
+        assert_close!(last.y + height / 2., to.y, 1e-6);
+    }
+
+    #[test]
+    fn render_grouped_by_color() {
+        use crate::layout::ColorChange;
+
+        let colored = |color : RGBA, gid : u16| LayoutNode {
+            node:   LayoutVariant::Color(ColorChange { color, inner: vec![glyph(gid, 5.)] }),
+            width:  Unit::new(5.),
+            height: Unit::new(8.),
+            depth:  Unit::new(-2.),
+        };
+        let (red, blue) = (RGBA(255, 0, 0, 255), RGBA(0, 0, 255, 255));
+
+        let mut layout = Layout::new();
+        layout.add_node(colored(red, 0));
+        layout.add_node(glyph(1, 5.));
+        layout.add_node(colored(blue, 2));
+        layout.add_node(colored(red, 3));
+
+        let mut backend = Recorder::default();
+        Renderer::new().render_grouped_by_color(&layout, &mut backend);
+        let gids : Vec<u16> = backend.symbols.iter().map(|symbol| symbol.gid.into()).collect();
+        assert_eq!(gids, vec![1, 0, 3, 2]);
+        assert_eq!(backend.colors, vec![(1, Some(red)), (3, None), (3, Some(blue)), (4, None)]);
+    }
 }
 
 #[cfg(feature="pathfinder-renderer")]
@@ -268,3 +760,12 @@ pub mod femtovg;
 pub mod cairo;
 #[cfg(feature="raqote-renderer")]
 pub mod raqote;
+#[cfg(feature="pdf-renderer")]
+pub mod pdf;
+#[cfg(feature="svg-renderer")]
+pub mod svg;
+#[cfg(feature="tiny-skia-renderer")]
+pub mod tiny_skia;
+pub mod mathml;
+#[cfg(test)]
+pub(crate) mod recorder;
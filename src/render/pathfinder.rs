@@ -11,7 +11,7 @@ use pathfinder_renderer::{
     paint::{Paint, PaintId},
 };
 use pathfinder_content::{
-    outline::{Outline},
+    outline::{Outline, Contour},
     stroke::{StrokeStyle, LineCap, LineJoin, OutlineStrokeToFill},
 };
 use pathfinder_geometry::{
@@ -98,6 +98,23 @@ impl<'a> GraphicsBackend for SceneWrapper<'a> {
         let outline = Outline::from_rect(RectF::new(origin, size));
         self.scene.push_draw_path(DrawPath::new(outline.transformed(&self.transform), self.paint));
     }
+    fn line(&mut self, from: Cursor, to: Cursor, thickness: f64) {
+        let mut contour = Contour::new();
+        contour.push_endpoint(v_cursor(from));
+        contour.push_endpoint(v_cursor(to));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let style = StrokeStyle {
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Bevel,
+            line_width: thickness as f32,
+        };
+        let mut stroke = OutlineStrokeToFill::new(&outline, style);
+        stroke.offset();
+        let outline = stroke.into_outline().transformed(&self.transform);
+        self.scene.push_draw_path(DrawPath::new(outline, self.paint));
+    }
     fn begin_color(&mut self, RGBA(r, g, b, a): RGBA) {
         self.color_stack.push(self.paint);
         self.paint = self.scene.push_paint(&Paint::from_color(ColorU::new(r, g, b, a)));
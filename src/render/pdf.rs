@@ -0,0 +1,258 @@
+//! Provides a [`Backend`] producing vector PDF documents
+//!
+//! The type [`PdfBackend`] accumulates the drawing operations in a PDF content stream.
+//! Once the formula is rendered, [`PdfBackend::finish`] writes a one-page PDF document whose page fits the formula.
+//! Glyphs are drawn as filled paths, so no font needs to be embedded in the document.
+//!
+//! The backend works with any font implementing [`MathFont::outline`].
+
+use std::fmt::Write;
+
+use crate::{Backend, font::{MathFont, OutlineBuilder}, GraphicsBackend, FontBackend, RGBA, Cursor};
+use crate::dimensions::Unit;
+use crate::dimensions::units::{Em, FUnit};
+use crate::layout::LayoutDimensions;
+
+/// Collects drawing operations into a PDF content stream, cf [module-level documentation](self).
+///
+/// One unit of layout (i.e. one pixel) is one point in the PDF document.
+pub struct PdfBackend {
+    content : String,
+}
+
+impl PdfBackend {
+    /// Creates a backend with an empty content stream.
+    pub fn new() -> Self {
+        Self {
+            content: String::new(),
+        }
+    }
+
+    /// Writes a one-page PDF document containing the drawing operations performed so far.
+    /// The page is sized after `dimensions`, which should be the dimensions of the rendered layout (cf [`crate::layout::Layout::size`]),
+    /// assuming the formula was rendered with its baseline's origin at (0, 0), as [`crate::Renderer::render`] does.
+    pub fn finish(self, dimensions: LayoutDimensions) -> Vec<u8> {
+        let LayoutDimensions { width, height, depth } = dimensions;
+
+        // PDF's origin is at the bottom left of the page, with the Y axis pointing upwards, while our Y axis points downwards.
+        // The transformation maps the bottom of the formula, at y = -depth, to the bottom of the page.
+        let mut content = String::new();
+        writeln!(content, "1 0 0 -1 0 {} cm", number(-depth)).unwrap();
+        content.push_str(&self.content);
+
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R /Resources << >> >>",
+                number(width), number(height - depth),
+            ),
+            format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+        ];
+
+        let mut document = Vec::new();
+        document.extend_from_slice(b"%PDF-1.4\n");
+
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(document.len());
+            document.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+        }
+
+        let xref_offset = document.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            writeln!(xref, "{:010} 00000 n ", offset).unwrap();
+        }
+        write!(
+            xref,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1, xref_offset,
+        ).unwrap();
+        document.extend_from_slice(xref.as_bytes());
+
+        document
+    }
+}
+
+impl Default for PdfBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats a number for a PDF content stream: PDF does not accept exponents, so we use a fixed number of decimals.
+fn number(x: f64) -> String {
+    let formatted = format!("{:.3}", x);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" | "" => "0".to_string(),
+        _ => formatted.to_string(),
+    }
+}
+
+
+impl<F : MathFont> Backend<F> for PdfBackend {}
+
+
+impl GraphicsBackend for PdfBackend {
+    fn rule(&mut self, pos: Cursor, width: f64, height: f64) {
+        writeln!(self.content, "{} {} {} {} re f", number(pos.x), number(pos.y), number(width), number(height)).unwrap();
+    }
+
+    // Only the fill color is set by `begin_color`, so the line is drawn as a filled quadrilateral rather than stroked.
+    fn line(&mut self, from: Cursor, to: Cursor, thickness: f64) {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = dx.hypot(dy);
+        if length == 0. {
+            return;
+        }
+        // half-thickness offset, perpendicular to the line
+        let (nx, ny) = (-dy / length * thickness / 2., dx / length * thickness / 2.);
+        writeln!(
+            self.content, "{} {} m {} {} l {} {} l {} {} l h f",
+            number(from.x + nx), number(from.y + ny),
+            number(to.x + nx),   number(to.y + ny),
+            number(to.x - nx),   number(to.y - ny),
+            number(from.x - nx), number(from.y - ny),
+        ).unwrap();
+    }
+
+    // PDF fill colors are part of the graphics state, which can be saved and restored.
+    // NB: the alpha channel is ignored, as transparency would require an extended graphics state.
+    fn begin_color(&mut self, color: RGBA) {
+        let RGBA(r, g, b, _) = color;
+        #[inline]
+        fn u8_to_f64(x : u8) -> f64 { f64::from(x) / 255. }
+        writeln!(self.content, "q {} {} {} rg", number(u8_to_f64(r)), number(u8_to_f64(g)), number(u8_to_f64(b))).unwrap();
+    }
+
+    fn end_color(&mut self) {
+        self.content.push_str("Q\n");
+    }
+}
+
+
+impl<F : MathFont> FontBackend<F> for PdfBackend {
+    fn symbol(&mut self, pos: Cursor, gid: crate::font::common::GlyphId, scale: f64, ctx: &F) {
+        struct Builder<'b> {
+            content : &'b mut String,
+            origin  : Cursor,
+            scale   : f64,
+            // last point of the path, needed to turn quadratic Bézier curves into cubic ones
+            current : (f64, f64),
+        }
+
+        impl<'b> Builder<'b> {
+            // Font units have Y pointing upwards, unlike our coordinates
+            fn transform(&self, x: f32, y: f32) -> (f64, f64) {
+                (self.origin.x + self.scale * f64::from(x), self.origin.y - self.scale * f64::from(y))
+            }
+        }
+
+        impl<'b> OutlineBuilder for Builder<'b> {
+            fn move_to(&mut self, x: f32, y: f32) {
+                let (x, y) = self.transform(x, y);
+                writeln!(self.content, "{} {} m", number(x), number(y)).unwrap();
+                self.current = (x, y);
+            }
+
+            fn line_to(&mut self, x: f32, y: f32) {
+                let (x, y) = self.transform(x, y);
+                writeln!(self.content, "{} {} l", number(x), number(y)).unwrap();
+                self.current = (x, y);
+            }
+
+            fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+                // PDF only has cubic Bézier curves: the control points of the equivalent cubic curve are 2/3 of the way to the quadratic control point
+                let (x0, y0) = self.current;
+                let (x1, y1) = self.transform(x1, y1);
+                let (x,  y)  = self.transform(x, y);
+                let (c1x, c1y) = (x0 + 2. / 3. * (x1 - x0), y0 + 2. / 3. * (y1 - y0));
+                let (c2x, c2y) = (x  + 2. / 3. * (x1 - x),  y  + 2. / 3. * (y1 - y));
+                writeln!(self.content, "{} {} {} {} {} {} c", number(c1x), number(c1y), number(c2x), number(c2y), number(x), number(y)).unwrap();
+                self.current = (x, y);
+            }
+
+            fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+                let (x1, y1) = self.transform(x1, y1);
+                let (x2, y2) = self.transform(x2, y2);
+                let (x,  y)  = self.transform(x, y);
+                writeln!(self.content, "{} {} {} {} {} {} c", number(x1), number(y1), number(x2), number(y2), number(x), number(y)).unwrap();
+                self.current = (x, y);
+            }
+
+            fn close(&mut self) {
+                self.content.push_str("h\n");
+            }
+        }
+
+        let mut path = String::new();
+        let mut builder = Builder {
+            content: &mut path,
+            origin:  pos,
+            scale:   scale * (Unit::<FUnit>::new(1.) * ctx.font_units_to_em()).unitless(Em),
+            current: (pos.x, pos.y),
+        };
+        ctx.outline(gid, &mut builder);
+
+        // glyphs without outline, e.g. spaces, are not drawn
+        if !path.is_empty() {
+            self.content.push_str(&path);
+            self.content.push_str("f\n");
+        }
+    }
+}
+
+
+#[cfg(all(test, feature="ttfparser-fontparser"))]
+mod tests {
+    use super::PdfBackend;
+    use crate::font::{FontContext, backend::ttf_parser::TtfMathFont};
+    use crate::layout::{LayoutSettings, engine};
+    use crate::Renderer;
+
+    const XITS_FONT_FILE : &[u8] = include_bytes!("../../resources/XITS_Math.otf");
+
+    #[test]
+    fn pdf_page_fits_the_formula() {
+        let font = TtfMathFont::new(ttf_parser::Face::parse(XITS_FONT_FILE, 0).unwrap()).unwrap();
+        let ctx = FontContext::new(&font);
+        let settings = LayoutSettings::new(&ctx).font_size(10.);
+
+        let nodes = crate::parser::parse(r"\frac{1}{\color{red}{x}}").unwrap();
+        let layout = engine::layout(&nodes, settings).unwrap();
+
+        let mut backend = PdfBackend::new();
+        Renderer::new().render(&layout, &mut backend);
+        let content = backend.content.clone();
+        let pdf = String::from_utf8(backend.finish(layout.size())).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        let size = layout.size();
+        let media_box = format!(
+            "/MediaBox [0 0 {} {}]",
+            super::number(size.width), super::number(size.height - size.depth),
+        );
+        assert!(pdf.contains(&media_box));
+
+        // the content stream flips the Y axis, so that the bottom of the formula is at the bottom of the page
+        let stream = format!("1 0 0 -1 0 {} cm\n{}", super::number(-size.depth), content);
+        assert!(pdf.contains(&format!("<< /Length {} >>\nstream\n{}endstream", stream.len(), stream)));
+
+        // two glyphs, one fraction bar, one color group
+        assert_eq!(content.lines().filter(|line| *line == "f").count(), 2);
+        assert_eq!(content.matches(" re f\n").count(), 1);
+        assert_eq!(content.matches("q 1 0 0 rg\n").count(), 1);
+        assert_eq!(content.matches("Q\n").count(), 1);
+
+        // the cross-reference table points to each object
+        let xref = pdf.rfind("\nxref\n").unwrap() + 1;
+        assert!(pdf.ends_with(&format!("startxref\n{}\n%%EOF\n", xref)));
+        for (i, line) in pdf[xref ..].lines().skip(3).take(4).enumerate() {
+            let offset : usize = line[.. 10].parse().unwrap();
+            assert!(pdf[offset ..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+    }
+}
@@ -2,8 +2,9 @@
 //!
 //! The type [`RaqoteBackend`] is a wrapper around [`DrawTarget`] that implements [`Backend`].
 //! With this, you can render a given formula to a `raqote` draw target.
+//! With [`RaqoteBackend::with_clip`], the formula can be rendered into a region of a larger draw target, e.g. to redraw a single formula in a GUI.
 
-use raqote::{DrawTarget, Source, SolidSource, DrawOptions, Transform, PathBuilder};
+use raqote::{DrawTarget, Source, SolidSource, DrawOptions, Transform, PathBuilder, IntRect};
 
 use crate::{Backend, font::backend::ttf_parser::TtfMathFont, GraphicsBackend, FontBackend};
 
@@ -12,6 +13,8 @@ pub struct RaqoteBackend<'a> {
     target        : &'a mut DrawTarget,
     current_color : SolidSource,
     color_stack   : Vec<SolidSource>,
+    // transform of the draw target before clipping, restored when the backend is dropped
+    previous_transform : Option<Transform>,
 }
 
 impl<'a> RaqoteBackend<'a> {
@@ -21,6 +24,29 @@ impl<'a> RaqoteBackend<'a> {
             target,
             current_color: SolidSource::from_unpremultiplied_argb(0xff, 0x00, 0x00, 0x00),
             color_stack:   Vec::new(),
+            previous_transform: None,
+        }
+    }
+
+    /// Creates a wrapper drawing only into the rectangle `clip` of the draw target, given in pixels of the draw target.
+    /// Drawing operations are offset so that the origin of the formula's coordinates is the top left corner of `clip`, and anything outside of `clip` is left untouched.
+    /// The clip and the transform of the draw target are restored when the wrapper is dropped.
+    pub fn with_clip(target: &'a mut DrawTarget, clip: IntRect) -> Self {
+        let previous_transform = *target.get_transform();
+        target.push_clip_rect(clip);
+        target.set_transform(&Transform::translation(clip.min.x as f32, clip.min.y as f32).then(&previous_transform));
+
+        let mut backend = Self::new(target);
+        backend.previous_transform = Some(previous_transform);
+        backend
+    }
+}
+
+impl<'a> Drop for RaqoteBackend<'a> {
+    fn drop(&mut self) {
+        if let Some(transform) = self.previous_transform.take() {
+            self.target.pop_clip();
+            self.target.set_transform(&transform);
         }
     }
 }
@@ -53,6 +79,16 @@ impl<'dt> GraphicsBackend for RaqoteBackend<'dt> {
         self.target.fill(&path, &Source::Solid(self.current_color), &DrawOptions::default());
     }
 
+    fn line(&mut self, from: crate::Cursor, to: crate::Cursor, thickness: f64) {
+        let mut path_builder = raqote::PathBuilder::new();
+        path_builder.move_to(from.x as f32, from.y as f32);
+        path_builder.line_to(to.x as f32, to.y as f32);
+        let path = path_builder.finish();
+
+        let style = raqote::StrokeStyle { width: thickness as f32, ..raqote::StrokeStyle::default() };
+        self.target.stroke(&path, &Source::Solid(self.current_color), &style, &DrawOptions::default());
+    }
+
 
     fn begin_color(&mut self, color: crate::RGBA) {
         self.color_stack.push(self.current_color);
@@ -138,4 +174,32 @@ impl<'a, 'dt> FontBackend<TtfMathFont<'a>> for RaqoteBackend<'dt> {
         );
 
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use raqote::{DrawTarget, IntRect, IntPoint};
+    use crate::{GraphicsBackend, Cursor};
+    use super::RaqoteBackend;
+
+    #[test]
+    fn clip_rect_bounds_drawing() {
+        let mut target = DrawTarget::new(40, 40);
+        {
+            let mut backend = RaqoteBackend::with_clip(&mut target, IntRect::new(IntPoint::new(10, 10), IntPoint::new(20, 20)));
+            // once offset, this rule would cover the region from (5, 5) to (35, 35)
+            backend.rule(Cursor { x: -5., y: -5. }, 30., 30.);
+        }
+        let alpha = |target : &DrawTarget, x : usize, y : usize| target.get_data()[y * 40 + x] >> 24;
+        assert!(alpha(&target, 10, 10) > 0);
+        assert!(alpha(&target, 19, 19) > 0);
+        for &(x, y) in &[(7, 7), (9, 15), (15, 20), (30, 30), (25, 12)] {
+            assert_eq!(alpha(&target, x, y), 0);
+        }
+
+        // the draw target is no longer clipped nor offset once the backend is dropped
+        RaqoteBackend::new(&mut target).rule(Cursor { x: 0., y: 0. }, 2., 2.);
+        assert!(alpha(&target, 1, 1) > 0);
+    }
+}
@@ -0,0 +1,75 @@
+//! A backend which records what it is asked to draw, for the tests of the crate
+
+use super::{Backend, FontBackend, GraphicsBackend, Cursor, Role, RGBA};
+use crate::font::common::GlyphId;
+
+/// A glyph drawn on a [`Recorder`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RecordedSymbol {
+    pub pos:   Cursor,
+    pub gid:   GlyphId,
+    pub scale: f64,
+}
+
+/// Records the draw calls it receives, for fonts of any type
+#[derive(Debug, Default)]
+pub(crate) struct Recorder {
+    pub symbols: Vec<RecordedSymbol>,
+    /// Top-left corner, width and height of the rules drawn
+    pub rules:   Vec<(Cursor, f64, f64)>,
+    /// Ends and thickness of the lines drawn
+    pub lines:   Vec<(Cursor, Cursor, f64)>,
+    /// Number of debug rectangles drawn
+    pub bboxes:  usize,
+    /// Color changes, with the number of symbols drawn before each: `Some(color)` when a color begins, `None` when it ends
+    pub colors:  Vec<(usize, Option<RGBA>)>,
+}
+
+impl Recorder {
+    /// Positions of the glyphs with id `gid`, in the order they were drawn
+    pub fn positions_of(&self, gid: GlyphId) -> Vec<Cursor> {
+        self.symbols.iter().filter(|symbol| symbol.gid == gid).map(|symbol| symbol.pos).collect()
+    }
+
+    /// Whether nothing at all was drawn
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty() && self.rules.is_empty() && self.lines.is_empty() && self.bboxes == 0 && self.colors.is_empty()
+    }
+}
+
+impl GraphicsBackend for Recorder {
+    fn bbox(&mut self, _pos: Cursor, _width: f64, _height: f64, _role: Role) {
+        self.bboxes += 1;
+    }
+    fn rule(&mut self, pos: Cursor, width: f64, height: f64) {
+        self.rules.push((pos, width, height));
+    }
+    fn line(&mut self, from: Cursor, to: Cursor, thickness: f64) {
+        self.lines.push((from, to, thickness));
+    }
+    fn begin_color(&mut self, color: RGBA) {
+        self.colors.push((self.symbols.len(), Some(color)));
+    }
+    fn end_color(&mut self) {
+        self.colors.push((self.symbols.len(), None));
+    }
+}
+
+impl<F> FontBackend<F> for Recorder {
+    fn symbol(&mut self, pos: Cursor, gid: GlyphId, scale: f64, _ctx: &F) {
+        self.symbols.push(RecordedSymbol { pos, gid, scale });
+    }
+}
+
+impl<F> Backend<F> for Recorder {}
+
+/// Records like [`Recorder`], except that lines are drawn with the default implementation of [`GraphicsBackend::line`]
+#[derive(Debug, Default)]
+pub(crate) struct DefaultLines(pub Recorder);
+
+impl GraphicsBackend for DefaultLines {
+    fn bbox(&mut self, pos: Cursor, width: f64, height: f64, role: Role) { self.0.bbox(pos, width, height, role) }
+    fn rule(&mut self, pos: Cursor, width: f64, height: f64) { self.0.rule(pos, width, height) }
+    fn begin_color(&mut self, color: RGBA) { self.0.begin_color(color) }
+    fn end_color(&mut self) { self.0.end_color() }
+}
@@ -0,0 +1,207 @@
+//! Provides a [`Backend`] producing SVG documents as strings, without any external dependency
+//!
+//! The type [`SvgStringBackend`] accumulates the drawing operations as SVG elements: glyphs are drawn as `<path>` elements following their outlines,
+//! rules as `<rect>` elements and colors are applied with `<g>` elements.
+//! Once the formula is rendered, [`SvgStringBackend::finish`] returns a self-contained `<svg>` document whose view box fits the formula.
+//!
+//! The backend works with any font implementing [`MathFont::outline`].
+
+use std::fmt::Write;
+
+use crate::{Backend, font::{MathFont, OutlineBuilder}, GraphicsBackend, FontBackend, RGBA, Cursor};
+use crate::dimensions::Unit;
+use crate::dimensions::units::{Em, FUnit};
+use crate::layout::LayoutDimensions;
+
+/// Collects drawing operations into SVG elements, cf [module-level documentation](self).
+///
+/// One unit of layout (i.e. one pixel) is one user unit in the SVG document.
+pub struct SvgStringBackend {
+    content : String,
+}
+
+impl SvgStringBackend {
+    /// Creates a backend with no SVG elements.
+    pub fn new() -> Self {
+        Self {
+            content: String::new(),
+        }
+    }
+
+    /// Returns an SVG document containing the drawing operations performed so far.
+    /// The view box is sized after `dimensions`, which should be the dimensions of the rendered layout (cf [`crate::layout::Layout::size`]),
+    /// assuming the formula was rendered with its baseline's origin at (0, 0), as [`crate::Renderer::render`] does.
+    pub fn finish(self, dimensions: LayoutDimensions) -> String {
+        let LayoutDimensions { width, height, depth } = dimensions;
+
+        // The Y axis of SVG points downwards, like ours: the top of the formula is at y = -height and its bottom at y = -depth.
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 {2} {0} {1}">{3}</svg>"#,
+            number(width), number(height - depth), number(-height), self.content,
+        )
+    }
+}
+
+impl Default for SvgStringBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats a number for an SVG attribute with a fixed number of decimals.
+fn number(x: f64) -> String {
+    let formatted = format!("{:.3}", x);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" | "" => "0".to_string(),
+        _ => formatted.to_string(),
+    }
+}
+
+
+impl<F : MathFont> Backend<F> for SvgStringBackend {}
+
+
+impl GraphicsBackend for SvgStringBackend {
+    fn rule(&mut self, pos: Cursor, width: f64, height: f64) {
+        write!(
+            self.content, r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#,
+            number(pos.x), number(pos.y), number(width), number(height),
+        ).unwrap();
+    }
+
+    // Colors are set as fill colors by `begin_color`, so the line is drawn as a filled quadrilateral rather than stroked.
+    fn line(&mut self, from: Cursor, to: Cursor, thickness: f64) {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = dx.hypot(dy);
+        if length == 0. {
+            return;
+        }
+        // half-thickness offset, perpendicular to the line
+        let (nx, ny) = (-dy / length * thickness / 2., dx / length * thickness / 2.);
+        write!(
+            self.content, r#"<path d="M{} {}L{} {}L{} {}L{} {}Z"/>"#,
+            number(from.x + nx), number(from.y + ny),
+            number(to.x + nx),   number(to.y + ny),
+            number(to.x - nx),   number(to.y - ny),
+            number(from.x - nx), number(from.y - ny),
+        ).unwrap();
+    }
+
+    fn begin_color(&mut self, color: RGBA) {
+        let RGBA(r, g, b, a) = color;
+        write!(self.content, r##"<g fill="#{:02x}{:02x}{:02x}""##, r, g, b).unwrap();
+        if a != 0xff {
+            write!(self.content, r#" fill-opacity="{}""#, number(f64::from(a) / 255.)).unwrap();
+        }
+        self.content.push('>');
+    }
+
+    fn end_color(&mut self) {
+        self.content.push_str("</g>");
+    }
+}
+
+
+impl<F : MathFont> FontBackend<F> for SvgStringBackend {
+    fn symbol(&mut self, pos: Cursor, gid: crate::font::common::GlyphId, scale: f64, ctx: &F) {
+        struct Builder<'b> {
+            content : &'b mut String,
+            origin  : Cursor,
+            scale   : f64,
+        }
+
+        impl<'b> Builder<'b> {
+            // Font units have Y pointing upwards, unlike our coordinates
+            fn transform(&self, x: f32, y: f32) -> (String, String) {
+                (number(self.origin.x + self.scale * f64::from(x)), number(self.origin.y - self.scale * f64::from(y)))
+            }
+        }
+
+        impl<'b> OutlineBuilder for Builder<'b> {
+            fn move_to(&mut self, x: f32, y: f32) {
+                let (x, y) = self.transform(x, y);
+                write!(self.content, "M{} {}", x, y).unwrap();
+            }
+
+            fn line_to(&mut self, x: f32, y: f32) {
+                let (x, y) = self.transform(x, y);
+                write!(self.content, "L{} {}", x, y).unwrap();
+            }
+
+            fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+                let (x1, y1) = self.transform(x1, y1);
+                let (x,  y)  = self.transform(x, y);
+                write!(self.content, "Q{} {} {} {}", x1, y1, x, y).unwrap();
+            }
+
+            fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+                let (x1, y1) = self.transform(x1, y1);
+                let (x2, y2) = self.transform(x2, y2);
+                let (x,  y)  = self.transform(x, y);
+                write!(self.content, "C{} {} {} {} {} {}", x1, y1, x2, y2, x, y).unwrap();
+            }
+
+            fn close(&mut self) {
+                self.content.push('Z');
+            }
+        }
+
+        let mut path = String::new();
+        let mut builder = Builder {
+            content: &mut path,
+            origin:  pos,
+            scale:   scale * (Unit::<FUnit>::new(1.) * ctx.font_units_to_em()).unitless(Em),
+        };
+        ctx.outline(gid, &mut builder);
+
+        // glyphs without outline, e.g. spaces, are not drawn
+        if !path.is_empty() {
+            write!(self.content, r#"<path d="{}"/>"#, path).unwrap();
+        }
+    }
+}
+
+
+#[cfg(all(test, feature="ttfparser-fontparser"))]
+mod tests {
+    use super::SvgStringBackend;
+    use crate::font::{FontContext, backend::ttf_parser::TtfMathFont};
+    use crate::layout::{LayoutSettings, engine};
+    use crate::{Renderer, GraphicsBackend, RGBA};
+
+    const XITS_FONT_FILE : &[u8] = include_bytes!("../../resources/XITS_Math.otf");
+
+    #[test]
+    fn svg_document_fits_the_formula() {
+        let font = TtfMathFont::new(ttf_parser::Face::parse(XITS_FONT_FILE, 0).unwrap()).unwrap();
+        let ctx = FontContext::new(&font);
+        let settings = LayoutSettings::new(&ctx).font_size(10.);
+
+        let nodes = crate::parser::parse(r"\frac{1}{\color{red}{x}}").unwrap();
+        let layout = engine::layout(&nodes, settings).unwrap();
+
+        let mut backend = SvgStringBackend::new();
+        Renderer::new().render(&layout, &mut backend);
+        let svg = backend.finish(layout.size());
+
+        let size = layout.size();
+        let expected_start = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 {2} {0} {1}">"#,
+            super::number(size.width), super::number(size.height - size.depth), super::number(-size.height),
+        );
+        assert!(svg.starts_with(&expected_start));
+        assert!(svg.ends_with("</svg>"));
+
+        // two glyphs, one fraction bar, one color group
+        assert_eq!(svg.matches("<path ").count(), 2);
+        assert_eq!(svg.matches("<rect ").count(), 1);
+        assert_eq!(svg.matches(r##"<g fill="#ff0000">"##).count(), 1);
+        assert_eq!(svg.matches("</g>").count(), 1);
+
+        let mut backend = SvgStringBackend::new();
+        backend.begin_color(RGBA(0, 0, 0xff, 0x80));
+        backend.end_color();
+        assert_eq!(backend.content, r##"<g fill="#0000ff" fill-opacity="0.502"></g>"##);
+    }
+}
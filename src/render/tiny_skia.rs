@@ -0,0 +1,194 @@
+//! Provides a [`Backend`] for [tiny-skia](https://crates.io/crates/tiny-skia)
+//!
+//! The type [`TinySkiaBackend`] is a wrapper around [`Pixmap`] that implements [`Backend`].
+//! With this, you can render a given formula to a raster image (e.g. a PNG file with [`Pixmap::save_png`]) without any C dependency.
+//! Glyphs are filled following their outlines, so the backend works with any font implementing [`MathFont::outline`].
+
+use tiny_skia::{Pixmap, Paint, PathBuilder, Transform, Rect, FillRule, Stroke, Color};
+
+use crate::{Backend, font::{MathFont, OutlineBuilder}, GraphicsBackend, FontBackend};
+use crate::dimensions::Unit;
+use crate::dimensions::units::{Em, FUnit};
+
+/// Wrapper around [`Pixmap`]
+pub struct TinySkiaBackend<'a> {
+    pixmap        : &'a mut Pixmap,
+    current_color : Color,
+    color_stack   : Vec<Color>,
+    transform     : Transform,
+}
+
+impl<'a> TinySkiaBackend<'a> {
+    /// Creates wrapper from mutable reference to a pixmap.
+    pub fn new(pixmap: &'a mut Pixmap) -> Self {
+        Self::with_transform(pixmap, Transform::identity())
+    }
+
+    /// Creates a wrapper mapping the coordinates of the formula to those of the pixmap with `transform`.
+    /// Since [`crate::Renderer::render`] puts the origin of the formula's baseline at (0, 0), 
+    /// `Transform::from_translate(0., height)` where `height` is the height of the layout draws the formula at the top left corner of the pixmap.
+    pub fn with_transform(pixmap: &'a mut Pixmap, transform: Transform) -> Self {
+        Self {
+            pixmap,
+            current_color: Color::BLACK,
+            color_stack:   Vec::new(),
+            transform,
+        }
+    }
+
+    fn paint(&self) -> Paint<'static> {
+        let mut paint = Paint::default();
+        paint.set_color(self.current_color);
+        paint.anti_alias = true;
+        paint
+    }
+}
+
+
+impl<'a, F : MathFont> Backend<F> for TinySkiaBackend<'a> {}
+
+
+impl<'a> GraphicsBackend for TinySkiaBackend<'a> {
+    fn bbox(&mut self, pos: crate::Cursor, width: f64, height: f64, role: crate::Role) {
+        match role {
+            crate::Role::Glyph => self.begin_color(crate::RGBA(0x00, 0xc1, 0x00, 0xff,)),
+            crate::Role::VBox  => self.begin_color(crate::RGBA(0xc1, 0x00, 0x00, 0xff,)),
+            crate::Role::HBox  => self.begin_color(crate::RGBA(0x00, 0x00, 0xc1, 0xff,)),
+        }
+        if let Some(rect) = Rect::from_xywh(pos.x as f32, pos.y as f32, width as f32, height as f32) {
+            let path = PathBuilder::from_rect(rect);
+            self.pixmap.stroke_path(&path, &self.paint(), &Stroke::default(), self.transform, None);
+        }
+        self.end_color();
+    }
+
+    fn rule(&mut self, pos: crate::Cursor, width: f64, height: f64) {
+        // Empty rectangles are not drawn
+        if let Some(rect) = Rect::from_xywh(pos.x as f32, pos.y as f32, width as f32, height as f32) {
+            self.pixmap.fill_rect(rect, &self.paint(), self.transform, None);
+        }
+    }
+
+    fn line(&mut self, from: crate::Cursor, to: crate::Cursor, thickness: f64) {
+        let mut path_builder = PathBuilder::new();
+        path_builder.move_to(from.x as f32, from.y as f32);
+        path_builder.line_to(to.x as f32, to.y as f32);
+
+        if let Some(path) = path_builder.finish() {
+            let stroke = Stroke { width: thickness as f32, ..Stroke::default() };
+            self.pixmap.stroke_path(&path, &self.paint(), &stroke, self.transform, None);
+        }
+    }
+
+    fn begin_color(&mut self, color: crate::RGBA) {
+        self.color_stack.push(self.current_color);
+        let crate::RGBA(r, g, b, a) = color;
+        self.current_color = Color::from_rgba8(r, g, b, a);
+    }
+
+    fn end_color(&mut self) {
+        if let Some(color) = self.color_stack.pop() {
+            self.current_color = color;
+        }
+    }
+}
+
+
+impl<'a, F : MathFont> FontBackend<F> for TinySkiaBackend<'a> {
+    fn symbol(&mut self, pos: crate::Cursor, gid: crate::font::common::GlyphId, scale: f64, ctx: &F) {
+        struct Builder(PathBuilder);
+
+        impl OutlineBuilder for Builder {
+            fn move_to(&mut self, x: f32, y: f32) {
+                self.0.move_to(x, y);
+            }
+
+            fn line_to(&mut self, x: f32, y: f32) {
+                self.0.line_to(x, y);
+            }
+
+            fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+                self.0.quad_to(x1, y1, x, y);
+            }
+
+            fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+                self.0.cubic_to(x1, y1, x2, y2, x, y);
+            }
+
+            fn close(&mut self) {
+                self.0.close();
+            }
+        }
+
+        let mut builder = Builder(PathBuilder::new());
+        ctx.outline(gid, &mut builder);
+
+        // Glyphs without outline, e.g. spaces, are not drawn
+        let path = match builder.0.finish() {
+            Some(path) => path,
+            None => return,
+        };
+
+        // Font units have Y pointing upwards, unlike our coordinates, hence the negative vertical scale
+        let scale = scale * (Unit::<FUnit>::new(1.) * ctx.font_units_to_em()).unitless(Em);
+        let transform = self.transform.pre_translate(pos.x as f32, pos.y as f32).pre_scale(scale as f32, - scale as f32);
+
+        self.pixmap.fill_path(&path, &self.paint(), FillRule::Winding, transform, None);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use tiny_skia::Pixmap;
+    use crate::{GraphicsBackend, Cursor, RGBA};
+    use super::TinySkiaBackend;
+
+    #[test]
+    fn rules_are_filled_with_the_current_color() {
+        let mut pixmap = Pixmap::new(20, 20).unwrap();
+        {
+            let mut backend = TinySkiaBackend::new(&mut pixmap);
+            backend.rule(Cursor { x: 0., y: 0. }, 10., 10.);
+            backend.begin_color(RGBA(0xff, 0, 0, 0xff));
+            backend.rule(Cursor { x: 10., y: 10. }, 10., 10.);
+            backend.end_color();
+        }
+
+        let pixel = |x, y| pixmap.pixel(x, y).unwrap();
+        assert_eq!((pixel(5, 5).red(), pixel(5, 5).alpha()), (0, 0xff));
+        assert_eq!((pixel(15, 15).red(), pixel(15, 15).alpha()), (0xff, 0xff));
+        assert_eq!(pixel(15, 5).alpha(), 0);
+        assert_eq!(pixel(5, 15).alpha(), 0);
+    }
+
+    #[cfg(feature="ttfparser-fontparser")]
+    #[test]
+    fn glyphs_are_drawn_above_the_baseline() {
+        use crate::font::{FontContext, backend::ttf_parser::TtfMathFont};
+        use crate::layout::{LayoutSettings, engine};
+        use crate::Renderer;
+        use tiny_skia::Transform;
+
+        const XITS_FONT_FILE : &[u8] = include_bytes!("../../resources/XITS_Math.otf");
+        let font = TtfMathFont::new(ttf_parser::Face::parse(XITS_FONT_FILE, 0).unwrap()).unwrap();
+        let ctx = FontContext::new(&font);
+        let settings = LayoutSettings::new(&ctx).font_size(40.);
+
+        let nodes = crate::parser::parse("x").unwrap();
+        let layout = engine::layout(&nodes, settings).unwrap();
+        let size = layout.size();
+
+        // the formula's baseline is placed at mid-height of the pixmap
+        let mut pixmap = Pixmap::new(size.width.ceil() as u32, 80).unwrap();
+        Renderer::new().render(&layout, &mut TinySkiaBackend::with_transform(&mut pixmap, Transform::from_translate(0., 40.)));
+
+        let covered_rows : Vec<_> = (0 .. 80)
+            .filter(|&y| (0 .. pixmap.width()).any(|x| pixmap.pixel(x, y).unwrap().alpha() > 0))
+            .collect();
+        assert!(!covered_rows.is_empty());
+        // 'x' sits on the baseline: it is drawn above it (with antialiasing spilling over by at most one pixel), and no higher than its height
+        assert!(*covered_rows.last().unwrap() <= 40);
+        assert!(f64::from(*covered_rows.first().unwrap()) >= 40. - size.height - 1.);
+    }
+}
@@ -42,7 +42,12 @@ pub enum DrawCmd {
         pos      : (f64, f64),
         glyph_id : u16,
         scale    : f64,  
-    }
+    },
+    Line {
+        from      : (f64, f64),
+        to        : (f64, f64),
+        thickness : f64,
+    },
 }
 
 impl<A> Backend<A> for DebugRender {}
@@ -55,6 +60,14 @@ impl GraphicsBackend for DebugRender {
         })
     }
 
+    fn line(&mut self, from: rex::Cursor, to: rex::Cursor, thickness: f64) {
+        self.commands.push(DrawCmd::Line { 
+            from: (from.x, from.y), 
+            to:   (to.x, to.y), 
+            thickness, 
+        })
+    }
+
     fn begin_color(&mut self, _color: rex::RGBA) {
     }
 